@@ -0,0 +1,72 @@
+use rust_decimal::prelude::ToPrimitive;
+pub use rust_decimal::Decimal;
+
+/// 一万个基点，费率和滑点的 bps 除以它得到比例
+pub const BPS: Decimal = Decimal::from_parts(10_000, 0, 0, false, 0);
+
+/// 链上原始整数按小数位数转换为定点数，例如 (1_500_000, 6) -> 1.5
+///
+/// 小数位数超过 Decimal 支持的 28 位时返回 None。
+pub fn from_raw(raw: u64, decimals: u32) -> Option<Decimal> {
+    Decimal::try_from_i128_with_scale(raw as i128, decimals).ok()
+}
+
+/// Serum/OpenBook 价格 lots 转换为 UI 价格
+///
+/// price = price_lots * quote_lot_size * 10^base_decimals / (base_lot_size * 10^quote_decimals)
+pub fn lots_to_price(
+    price_lots: u64,
+    base_lot_size: u64,
+    quote_lot_size: u64,
+    base_decimals: u32,
+    quote_decimals: u32,
+) -> Option<Decimal> {
+    let numerator = Decimal::from(price_lots).checked_mul(Decimal::from(quote_lot_size))?;
+    let denominator = Decimal::from(base_lot_size);
+    let price = numerator.checked_div(denominator)?;
+    let scale = base_decimals as i64 - quote_decimals as i64;
+    if scale >= 0 {
+        price.checked_mul(Decimal::from(10u64.checked_pow(scale as u32)?))
+    } else {
+        price.checked_div(Decimal::from(10u64.checked_pow((-scale) as u32)?))
+    }
+}
+
+/// 数量 lots 转换为 UI 数量
+pub fn lots_to_size(size_lots: u64, base_lot_size: u64, base_decimals: u32) -> Option<Decimal> {
+    let native = (size_lots as u128).checked_mul(base_lot_size as u128)?;
+    Decimal::try_from_i128_with_scale(native as i128, base_decimals).ok()
+}
+
+/// 转换为 f64，仅用于显示和统计指标，不要用于金额计算
+pub fn to_f64(value: Decimal) -> f64 {
+    value.to_f64().unwrap_or(0.0)
+}
+
+/// UI 价格转换为 Serum/OpenBook 价格 lots，`round_up` 为 false 时向下取整
+pub fn price_to_lots(
+    price: Decimal,
+    base_lot_size: u64,
+    quote_lot_size: u64,
+    base_decimals: u32,
+    quote_decimals: u32,
+    round_up: bool,
+) -> Option<u64> {
+    let numerator = price
+        .checked_mul(Decimal::from(base_lot_size))?
+        .checked_mul(Decimal::from(10u64.checked_pow(quote_decimals)?))?;
+    let denominator = Decimal::from(quote_lot_size)
+        .checked_mul(Decimal::from(10u64.checked_pow(base_decimals)?))?;
+    let lots = numerator.checked_div(denominator)?;
+    let lots = if round_up { lots.ceil() } else { lots.floor() };
+    lots.to_u64()
+}
+
+/// UI 数量转换为数量 lots，向下取整
+pub fn size_to_lots(size: Decimal, base_lot_size: u64, base_decimals: u32) -> Option<u64> {
+    let native = size.checked_mul(Decimal::from(10u64.checked_pow(base_decimals)?))?;
+    native
+        .checked_div(Decimal::from(base_lot_size))?
+        .floor()
+        .to_u64()
+}
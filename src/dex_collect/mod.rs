@@ -1,3 +1,9 @@
+pub mod fixed;
+pub mod oracle;
 #[allow(dead_code, unused_variables, unused_imports, deprecated, unused_mut)]
 pub mod raydium;
+pub mod record;
+pub mod rpc;
 pub mod serum;
+pub mod stamp;
+pub mod stream;
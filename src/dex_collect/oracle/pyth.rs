@@ -0,0 +1,240 @@
+use anyhow::Result;
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::dex_collect::fixed::{Decimal, BPS};
+
+/// Pyth 账户魔数
+const PYTH_MAGIC: u32 = 0xa1b2c3d4;
+/// 账户类型: 价格账户
+const ACCOUNT_TYPE_PRICE: u32 = 3;
+/// 价格账户到聚合价格 (agg) 为止的最小长度
+const PRICE_ACCOUNT_MIN_SIZE: usize = 240;
+
+/// 聚合价格状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PriceStatus {
+    Unknown,
+    Trading,
+    Halted,
+    Auction,
+    Ignored,
+}
+
+impl From<u32> for PriceStatus {
+    fn from(value: u32) -> Self {
+        match value {
+            1 => PriceStatus::Trading,
+            2 => PriceStatus::Halted,
+            3 => PriceStatus::Auction,
+            4 => PriceStatus::Ignored,
+            _ => PriceStatus::Unknown,
+        }
+    }
+}
+
+/// Pyth 价格账户 (v2) 中的聚合价格
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PythPrice {
+    pub price: Decimal,
+    pub conf: Decimal, // 置信区间半宽
+    pub expo: i32,
+    pub status: PriceStatus,
+    pub publish_slot: u64, // 聚合价格发布的 slot
+    pub valid_slot: u64,
+    pub ema_price: Decimal,
+    pub ema_conf: Decimal,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub product: Pubkey,
+}
+
+/// 价格可用性检查配置
+#[derive(Debug, Clone)]
+pub struct OracleConfig {
+    pub max_slot_lag: u64,     // 发布 slot 落后当前 slot 的上限
+    pub max_conf_bps: Decimal, // 置信区间相对价格的上限
+}
+
+impl Default for OracleConfig {
+    fn default() -> Self {
+        Self {
+            max_slot_lag: 25,
+            max_conf_bps: Decimal::from(200),
+        }
+    }
+}
+
+impl PythPrice {
+    /// 解析价格账户数据
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < PRICE_ACCOUNT_MIN_SIZE {
+            return Err(anyhow::anyhow!("Pyth price account too short"));
+        }
+        if read_u32(data, 0) != PYTH_MAGIC {
+            return Err(anyhow::anyhow!("Invalid Pyth magic"));
+        }
+        if read_u32(data, 8) != ACCOUNT_TYPE_PRICE {
+            return Err(anyhow::anyhow!("Not a Pyth price account"));
+        }
+
+        let expo = read_u32(data, 20) as i32;
+        let timestamp = read_u64(data, 96) as i64;
+        Ok(Self {
+            price: scale(read_u64(data, 208) as i64 as i128, expo)?,
+            conf: scale(read_u64(data, 216) as i128, expo)?,
+            expo,
+            status: PriceStatus::from(read_u32(data, 224)),
+            publish_slot: read_u64(data, 232),
+            valid_slot: read_u64(data, 40),
+            ema_price: scale(read_u64(data, 48) as i64 as i128, expo)?,
+            ema_conf: scale(read_u64(data, 72) as i128, expo)?,
+            timestamp: Utc.timestamp_opt(timestamp, 0).single(),
+            product: Pubkey::try_from(&data[112..144])?,
+        })
+    }
+
+    /// 发布 slot 是否落后当前 slot 超过上限
+    pub fn is_stale(&self, current_slot: u64, max_slot_lag: u64) -> bool {
+        current_slot.saturating_sub(self.publish_slot) > max_slot_lag
+    }
+
+    /// 置信区间相对价格 (bps)
+    pub fn conf_bps(&self) -> Option<Decimal> {
+        (!self.price.is_zero()).then(|| self.conf / self.price.abs() * BPS)
+    }
+
+    /// 检查状态、时效和置信区间，不可用时返回原因
+    pub fn check(&self, current_slot: u64, config: &OracleConfig) -> Result<&Self> {
+        if self.status != PriceStatus::Trading {
+            return Err(anyhow::anyhow!("Oracle status is {:?}", self.status));
+        }
+        if self.is_stale(current_slot, config.max_slot_lag) {
+            return Err(anyhow::anyhow!(
+                "Oracle price is stale: published at slot {}, current slot {}",
+                self.publish_slot,
+                current_slot
+            ));
+        }
+        match self.conf_bps() {
+            Some(conf_bps) if conf_bps <= config.max_conf_bps => Ok(self),
+            Some(conf_bps) => Err(anyhow::anyhow!(
+                "Oracle confidence too wide: {} bps",
+                conf_bps.round_dp(2)
+            )),
+            None => Err(anyhow::anyhow!("Oracle price is zero")),
+        }
+    }
+}
+
+/// DEX 中间价相对预言机价格的偏离
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OracleDeviation {
+    pub mid: Decimal,
+    pub oracle_price: Decimal,
+    pub oracle_conf: Decimal,
+    pub deviation_bps: Decimal,         // (mid - oracle) / oracle
+    pub conf_multiple: Option<Decimal>, // (mid - oracle) / conf，置信区间为 0 时为 None
+    pub publish_slot: u64,
+}
+
+/// 计算中间价相对预言机价格的偏离，预言机价格为 0 时返回 None
+pub fn deviation(mid: Decimal, oracle: &PythPrice) -> Option<OracleDeviation> {
+    if oracle.price.is_zero() {
+        return None;
+    }
+    let diff = mid - oracle.price;
+    Some(OracleDeviation {
+        mid,
+        oracle_price: oracle.price,
+        oracle_conf: oracle.conf,
+        deviation_bps: diff / oracle.price * BPS,
+        conf_multiple: (!oracle.conf.is_zero()).then(|| diff / oracle.conf),
+        publish_slot: oracle.publish_slot,
+    })
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+/// 按指数换算整数价格: value * 10^expo
+fn scale(value: i128, expo: i32) -> Result<Decimal> {
+    if expo <= 0 {
+        return Decimal::try_from_i128_with_scale(value, (-expo) as u32)
+            .map_err(|e| anyhow::anyhow!("Invalid Pyth price: {}", e));
+    }
+    10i128
+        .checked_pow(expo as u32)
+        .and_then(|factor| value.checked_mul(factor))
+        .and_then(|value| Decimal::try_from_i128_with_scale(value, 0).ok())
+        .ok_or_else(|| anyhow::anyhow!("Pyth price overflow"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 构造价格 12.345 ± 0.01、expo -8 的价格账户
+    fn account(status: u32, publish_slot: u64) -> Vec<u8> {
+        let mut data = vec![0u8; PRICE_ACCOUNT_MIN_SIZE];
+        let mut put =
+            |offset: usize, bytes: &[u8]| data[offset..offset + bytes.len()].copy_from_slice(bytes);
+        put(0, &PYTH_MAGIC.to_le_bytes());
+        put(8, &ACCOUNT_TYPE_PRICE.to_le_bytes());
+        put(20, &(-8i32).to_le_bytes());
+        put(40, &99u64.to_le_bytes());
+        put(48, &(-1_234_500_000i64).to_le_bytes());
+        put(72, &500_000u64.to_le_bytes());
+        put(96, &1_700_000_000i64.to_le_bytes());
+        put(112, &[7u8; 32]);
+        put(208, &1_234_500_000i64.to_le_bytes());
+        put(216, &1_000_000u64.to_le_bytes());
+        put(224, &status.to_le_bytes());
+        put(232, &publish_slot.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn decodes_price_account() {
+        let price = PythPrice::from_bytes(&account(1, 100)).unwrap();
+        assert_eq!(price.price, Decimal::new(12_345, 3));
+        assert_eq!(price.conf, Decimal::new(1, 2));
+        assert_eq!(price.expo, -8);
+        assert_eq!(price.status, PriceStatus::Trading);
+        assert_eq!(price.publish_slot, 100);
+        assert_eq!(price.valid_slot, 99);
+        // EMA 价格按有符号数解析
+        assert_eq!(price.ema_price, Decimal::new(-12_345, 3));
+        assert_eq!(price.ema_conf, Decimal::new(5, 3));
+        assert_eq!(price.timestamp.map(|t| t.timestamp()), Some(1_700_000_000));
+        assert_eq!(price.product, Pubkey::new_from_array([7; 32]));
+        assert!(price.check(110, &OracleConfig::default()).is_ok());
+    }
+
+    #[test]
+    fn rejects_bad_accounts_and_unusable_prices() {
+        let mut data = account(1, 100);
+        assert!(PythPrice::from_bytes(&data[..PRICE_ACCOUNT_MIN_SIZE - 1]).is_err());
+        data[8] = 2;
+        assert!(PythPrice::from_bytes(&data).is_err());
+        data[0] = 0;
+        assert!(PythPrice::from_bytes(&data).is_err());
+
+        let config = OracleConfig::default();
+        let halted = PythPrice::from_bytes(&account(2, 100)).unwrap();
+        assert!(halted.check(100, &config).is_err());
+        let stale = PythPrice::from_bytes(&account(1, 100)).unwrap();
+        assert!(stale.check(100 + config.max_slot_lag + 1, &config).is_err());
+    }
+
+    #[test]
+    fn positive_exponent_scales_up() {
+        assert_eq!(scale(12, 3).unwrap(), Decimal::from(12_000));
+        assert!(scale(i128::MAX, 2).is_err());
+    }
+}
@@ -1,12 +1,12 @@
 
-use solana_client::rpc_client::RpcClient;
+use crate::dex_collect::rpc::client::RpcHandle;
 pub struct RaydiumPriceFetcher{
-    rpc_client: RpcClient,
+    rpc: RpcHandle,
 }
 impl RaydiumPriceFetcher {
-    fn new(url: &str) -> RaydiumPriceFetcher {
+    fn new(rpc: RpcHandle) -> RaydiumPriceFetcher {
         Self{
-            rpc_client: RpcClient::new(url),
+            rpc,
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,430 @@
+use anyhow::Result;
+use chrono::{DateTime, TimeZone, Utc};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::{BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::Path,
+    time::Duration,
+};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use crate::dex_collect::stamp::SnapshotStamp;
+use crate::dex_collect::stream::update::{MarketUpdate, UpdateDecoder};
+
+/// 文件头: 魔数和格式版本
+const FILE_MAGIC: &[u8; 8] = b"MMREC\0\0\x01";
+/// 块头: 压缩后长度 (u32) + 记录数 (u32)
+const BLOCK_HEADER_SIZE: usize = 8;
+/// 单块压缩后大小上限，超过视为文件损坏
+const MAX_BLOCK_SIZE: usize = 64 * 1024 * 1024;
+
+/// 一次原始账户更新
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountRecord {
+    pub pubkey: Pubkey,
+    pub owner: Pubkey,
+    pub slot: u64,
+    pub received_at: DateTime<Utc>,
+    pub block_time: Option<DateTime<Utc>>,
+    pub source: String,
+    pub data: Vec<u8>,
+}
+
+impl AccountRecord {
+    pub fn new(pubkey: &Pubkey, owner: &Pubkey, data: &[u8], stamp: &SnapshotStamp) -> Self {
+        Self {
+            pubkey: *pubkey,
+            owner: *owner,
+            slot: stamp.slot,
+            received_at: stamp.received_at,
+            block_time: stamp.block_time,
+            source: stamp.source.clone(),
+            data: data.to_vec(),
+        }
+    }
+
+    /// 还原记录时的快照时间戳
+    pub fn stamp(&self) -> SnapshotStamp {
+        SnapshotStamp {
+            slot: self.slot,
+            block_time: self.block_time,
+            received_at: self.received_at,
+            source: self.source.clone(),
+        }
+    }
+
+    /// 编码: pubkey 32 | owner 32 | slot u64 | received_at i64 (微秒) |
+    /// block_time 标志 u8 + i64 | source 长度 u16 + 字节 | data 长度 u32 + 字节
+    fn encode(&self, buf: &mut Vec<u8>) {
+        let source = &self.source.as_bytes()[..self.source.len().min(u16::MAX as usize)];
+        let len = 32 + 32 + 8 + 8 + 9 + 2 + source.len() + 4 + self.data.len();
+        buf.extend_from_slice(&(len as u32).to_le_bytes());
+        buf.extend_from_slice(self.pubkey.as_ref());
+        buf.extend_from_slice(self.owner.as_ref());
+        buf.extend_from_slice(&self.slot.to_le_bytes());
+        buf.extend_from_slice(&self.received_at.timestamp_micros().to_le_bytes());
+        buf.push(self.block_time.is_some() as u8);
+        let block_time = self.block_time.map(|t| t.timestamp_micros()).unwrap_or(0);
+        buf.extend_from_slice(&block_time.to_le_bytes());
+        buf.extend_from_slice(&(source.len() as u16).to_le_bytes());
+        buf.extend_from_slice(source);
+        buf.extend_from_slice(&(self.data.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&self.data);
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut cursor = Cursor { bytes, offset: 0 };
+        let pubkey = Pubkey::try_from(cursor.take(32)?)?;
+        let owner = Pubkey::try_from(cursor.take(32)?)?;
+        let slot = u64::from_le_bytes(cursor.take(8)?.try_into()?);
+        let received_at = micros(i64::from_le_bytes(cursor.take(8)?.try_into()?))?;
+        let has_block_time = cursor.take(1)?[0] != 0;
+        let block_time = i64::from_le_bytes(cursor.take(8)?.try_into()?);
+        let source_len = u16::from_le_bytes(cursor.take(2)?.try_into()?) as usize;
+        let source = String::from_utf8_lossy(cursor.take(source_len)?).into_owned();
+        let data_len = u32::from_le_bytes(cursor.take(4)?.try_into()?) as usize;
+        let data = cursor.take(data_len)?.to_vec();
+        Ok(Self {
+            pubkey,
+            owner,
+            slot,
+            received_at,
+            block_time: if has_block_time {
+                Some(micros(block_time)?)
+            } else {
+                None
+            },
+            source,
+            data,
+        })
+    }
+}
+
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.offset + len;
+        let slice = self
+            .bytes
+            .get(self.offset..end)
+            .ok_or_else(|| anyhow::anyhow!("Record truncated"))?;
+        self.offset = end;
+        Ok(slice)
+    }
+}
+
+fn micros(value: i64) -> Result<DateTime<Utc>> {
+    Utc.timestamp_micros(value)
+        .single()
+        .ok_or_else(|| anyhow::anyhow!("Invalid timestamp {}", value))
+}
+
+/// 记录文件写入器
+///
+/// 文件只追加: 文件头之后是一连串独立压缩的块，每块由块头和 deflate 压缩的
+/// 长度前缀记录组成。进程中途退出最多丢失未落盘的最后一块。
+pub struct RecordWriter {
+    file: BufWriter<File>,
+    buffer: Vec<u8>,
+    count: u32,
+    block_size: usize, // 未压缩数据达到该大小时写出一块
+}
+
+impl RecordWriter {
+    /// 打开或创建记录文件，已有文件会校验文件头后继续追加
+    ///
+    /// 上次进程中途退出留下的不完整块会被截掉，新块接在最后一个完整块之后。
+    pub fn open(path: impl AsRef<Path>, block_size: usize) -> Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path.as_ref())?;
+        if file.metadata()?.len() == 0 {
+            file.write_all(FILE_MAGIC)?;
+        } else {
+            let mut magic = [0u8; 8];
+            file.read_exact(&mut magic)?;
+            if &magic != FILE_MAGIC {
+                return Err(anyhow::anyhow!(
+                    "Not a record file: {}",
+                    path.as_ref().display()
+                ));
+            }
+            let len = file.metadata()?.len();
+            let end = complete_length(&file)?;
+            if end < len {
+                log::warn!(
+                    "记录文件 {} 末尾有 {} 字节不完整数据，已截断",
+                    path.as_ref().display(),
+                    len - end
+                );
+                file.set_len(end)?;
+            }
+        }
+        Ok(Self {
+            file: BufWriter::new(file),
+            buffer: Vec::with_capacity(block_size),
+            count: 0,
+            block_size,
+        })
+    }
+
+    pub fn append(&mut self, record: &AccountRecord) -> Result<()> {
+        record.encode(&mut self.buffer);
+        self.count += 1;
+        if self.buffer.len() >= self.block_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// 压缩并写出当前块
+    pub fn flush(&mut self) -> Result<()> {
+        if self.count == 0 {
+            return Ok(());
+        }
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&self.buffer)?;
+        let compressed = encoder.finish()?;
+        self.file
+            .write_all(&(compressed.len() as u32).to_le_bytes())?;
+        self.file.write_all(&self.count.to_le_bytes())?;
+        self.file.write_all(&compressed)?;
+        self.file.flush()?;
+        self.buffer.clear();
+        self.count = 0;
+        Ok(())
+    }
+}
+
+/// 文件头和所有完整块的总长度，末尾未写完或无法解压的块不计入
+fn complete_length(file: &File) -> Result<u64> {
+    let mut reader = BufReader::new(file);
+    let mut end = FILE_MAGIC.len() as u64;
+    reader.seek(SeekFrom::Start(end))?;
+    loop {
+        let mut header = [0u8; BLOCK_HEADER_SIZE];
+        if reader.read_exact(&mut header).is_err() {
+            break;
+        }
+        let len = u32::from_le_bytes(header[0..4].try_into()?) as usize;
+        if len > MAX_BLOCK_SIZE {
+            break;
+        }
+        let mut compressed = vec![0u8; len];
+        if reader.read_exact(&mut compressed).is_err() {
+            break;
+        }
+        if DeflateDecoder::new(compressed.as_slice())
+            .read_to_end(&mut Vec::new())
+            .is_err()
+        {
+            break;
+        }
+        end += (BLOCK_HEADER_SIZE + len) as u64;
+    }
+    Ok(end)
+}
+
+impl Drop for RecordWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            log::warn!("记录文件写出失败: {}", e);
+        }
+    }
+}
+
+/// 记录文件读取器，按写入顺序逐条返回记录
+pub struct RecordReader<R: Read> {
+    reader: R,
+    pending: VecDeque<AccountRecord>,
+    done: bool,
+}
+
+impl RecordReader<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> RecordReader<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != FILE_MAGIC {
+            return Err(anyhow::anyhow!("Not a record file"));
+        }
+        Ok(Self {
+            reader,
+            pending: VecDeque::new(),
+            done: false,
+        })
+    }
+
+    /// 读取下一块，文件正常结束时返回 Ok(false)
+    fn read_block(&mut self) -> Result<bool> {
+        let mut header = [0u8; BLOCK_HEADER_SIZE];
+        match self.reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(false),
+            Err(e) => return Err(e.into()),
+        }
+        let len = u32::from_le_bytes(header[0..4].try_into()?) as usize;
+        let count = u32::from_le_bytes(header[4..8].try_into()?) as usize;
+        if len > MAX_BLOCK_SIZE {
+            return Err(anyhow::anyhow!("Block too large: {} bytes", len));
+        }
+        let mut compressed = vec![0u8; len];
+        self.reader
+            .read_exact(&mut compressed)
+            .map_err(|e| anyhow::anyhow!("Truncated block: {}", e))?;
+
+        let mut raw = Vec::new();
+        DeflateDecoder::new(compressed.as_slice()).read_to_end(&mut raw)?;
+        let mut cursor = Cursor {
+            bytes: &raw,
+            offset: 0,
+        };
+        for _ in 0..count {
+            let len = u32::from_le_bytes(cursor.take(4)?.try_into()?) as usize;
+            self.pending
+                .push_back(AccountRecord::decode(cursor.take(len)?)?);
+        }
+        Ok(true)
+    }
+}
+
+impl<R: Read> Iterator for RecordReader<R> {
+    type Item = Result<AccountRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() && !self.done {
+            match self.read_block() {
+                Ok(true) => {}
+                Ok(false) => self.done = true,
+                Err(e) => {
+                    // 损坏或未写完的块之后的数据不再可信
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        self.pending.pop_front().map(Ok)
+    }
+}
+
+/// 读取整个记录文件，末尾未写完的块只记录警告
+pub fn read_records(path: impl AsRef<Path>) -> Result<Vec<AccountRecord>> {
+    let mut records = Vec::new();
+    for record in RecordReader::open(path.as_ref())? {
+        match record {
+            Ok(record) => records.push(record),
+            Err(e) => log::warn!("记录文件 {} 读取中断: {}", path.as_ref().display(), e),
+        }
+    }
+    Ok(records)
+}
+
+/// 用解码器按 slot 顺序重建市场更新
+///
+/// 解码器需按采集时的方式注册账户，且不应再挂记录器。
+pub fn replay(records: Vec<AccountRecord>, decoder: &mut UpdateDecoder) -> Vec<MarketUpdate> {
+    let mut records = records;
+    records.sort_by_key(|r| (r.slot, r.received_at));
+    records
+        .iter()
+        .filter_map(|r| decoder.decode(&r.pubkey, &r.owner, &r.data, r.stamp()))
+        .collect()
+}
+
+/// 采集端持有的记录句柄，发送不阻塞解码流程
+#[derive(Debug, Clone)]
+pub struct RecorderHandle {
+    tx: mpsc::UnboundedSender<AccountRecord>,
+}
+
+impl RecorderHandle {
+    pub fn record(&self, record: AccountRecord) {
+        // 记录任务已退出时丢弃，不影响采集
+        let _ = self.tx.send(record);
+    }
+}
+
+/// 在后台任务中写入记录文件，每隔 `flush_interval` 落盘一次
+pub fn spawn_recorder(
+    path: impl AsRef<Path>,
+    block_size: usize,
+    flush_interval: Duration,
+) -> Result<(RecorderHandle, JoinHandle<Result<()>>)> {
+    let mut writer = RecordWriter::open(path, block_size)?;
+    let (tx, mut rx) = mpsc::unbounded_channel::<AccountRecord>();
+    let task = tokio::spawn(async move {
+        let mut flush = tokio::time::interval(flush_interval);
+        loop {
+            tokio::select! {
+                record = rx.recv() => match record {
+                    Some(record) => writer.append(&record)?,
+                    None => break,
+                },
+                _ = flush.tick() => writer.flush()?,
+            }
+        }
+        writer.flush()
+    });
+    Ok((RecorderHandle { tx }, task))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(slot: u64) -> AccountRecord {
+        let mut stamp = SnapshotStamp::new(slot, "test");
+        // 文件中时间只保留到微秒
+        stamp.received_at = micros(stamp.received_at.timestamp_micros()).unwrap();
+        AccountRecord::new(
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &[slot as u8; 16],
+            &stamp,
+        )
+    }
+
+    #[test]
+    fn append_after_truncated_block_round_trips() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("record-{}.bin", Pubkey::new_unique()));
+        let first: Vec<AccountRecord> = (1..=3).map(record).collect();
+        let second: Vec<AccountRecord> = (4..=5).map(record).collect();
+
+        let mut writer = RecordWriter::open(&path, 1024)?;
+        for r in &first {
+            writer.append(r)?;
+        }
+        drop(writer);
+        // 模拟写块时进程退出: 块头声明的长度超过实际写入的数据
+        let mut file = OpenOptions::new().append(true).open(&path)?;
+        file.write_all(&100u32.to_le_bytes())?;
+        file.write_all(&1u32.to_le_bytes())?;
+        file.write_all(&[0u8; 10])?;
+        drop(file);
+
+        let mut writer = RecordWriter::open(&path, 1024)?;
+        for r in &second {
+            writer.append(r)?;
+        }
+        drop(writer);
+
+        let records: Vec<AccountRecord> = RecordReader::open(&path)?.collect::<Result<_>>()?;
+        std::fs::remove_file(&path)?;
+        assert_eq!(records, [first, second].concat());
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_config::RpcAccountInfoConfig;
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use std::collections::{HashMap, HashSet};
+
+use crate::dex_collect::rpc::client::RpcHandle;
+use crate::dex_collect::stamp::SnapshotStamp;
+
+/// getMultipleAccounts 单次请求允许的最大账户数
+pub const MAX_ACCOUNTS_PER_REQUEST: usize = 100;
+
+/// 分片 slot 不一致时的最大重取次数
+const MAX_RESYNC_ROUNDS: usize = 3;
+
+/// 同一 slot 下批量获取的账户集合
+#[derive(Debug, Clone)]
+pub struct AccountBatch {
+    /// 账户数据对应的上下文 slot
+    pub slot: u64,
+    /// 各分片中最小的 slot，等于 `slot` 时说明所有账户来自同一 slot
+    pub min_slot: u64,
+    /// 应答节点地址，分片来自多个节点时以逗号分隔
+    pub source: String,
+    /// 本地接收时间
+    pub received_at: DateTime<Utc>,
+    accounts: HashMap<Pubkey, Account>,
+}
+
+impl AccountBatch {
+    pub fn get(&self, pubkey: &Pubkey) -> Option<&Account> {
+        self.accounts.get(pubkey)
+    }
+
+    /// 获取账户，不存在时报错
+    pub fn require(&self, pubkey: &Pubkey) -> Result<&Account> {
+        self.get(pubkey)
+            .ok_or_else(|| anyhow::anyhow!("Account not found: {}", pubkey))
+    }
+
+    /// 所有账户是否来自同一 slot
+    pub fn is_consistent(&self) -> bool {
+        self.slot == self.min_slot
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    /// 本批数据的快照时间戳 (不含出块时间)
+    pub fn stamp(&self) -> SnapshotStamp {
+        SnapshotStamp {
+            slot: self.slot,
+            block_time: None,
+            received_at: self.received_at,
+            source: self.source.clone(),
+        }
+    }
+}
+
+impl RpcHandle {
+    /// 调用一次 getMultipleAccounts，返回上下文 slot、账户列表和应答节点
+    pub async fn get_multiple_accounts_at(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<u64>,
+    ) -> Result<(u64, Vec<Option<Account>>, String)> {
+        let config = RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            min_context_slot,
+            ..RpcAccountInfoConfig::default()
+        };
+        let (response, source) = self
+            .call_with_source(|client| {
+                let config = config.clone();
+                async move {
+                    client
+                        .get_multiple_accounts_with_config(pubkeys, config)
+                        .await
+                }
+            })
+            .await?;
+        Ok((response.context.slot, response.value, source))
+    }
+
+    /// 批量获取账户，尽量保证所有账户来自同一 slot
+    ///
+    /// 不超过 [`MAX_ACCOUNTS_PER_REQUEST`] 个账户时只发一次请求，结果天然一致；
+    /// 超过时按分片请求，后续分片以已见到的最大 slot 作为 minContextSlot，
+    /// 落后的分片会被重取，直到一致或达到重试上限。
+    pub async fn get_accounts_batch(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<u64>,
+    ) -> Result<AccountBatch> {
+        // 去重并保持请求顺序
+        let mut seen = HashSet::with_capacity(pubkeys.len());
+        let keys: Vec<Pubkey> = pubkeys
+            .iter()
+            .copied()
+            .filter(|k| seen.insert(*k))
+            .collect();
+
+        let chunks: Vec<&[Pubkey]> = keys.chunks(MAX_ACCOUNTS_PER_REQUEST).collect();
+        let mut chunk_slots = vec![0u64; chunks.len()];
+        let mut accounts = HashMap::with_capacity(keys.len());
+        let mut target = min_context_slot;
+        let mut sources: Vec<String> = Vec::new();
+
+        for round in 0..=MAX_RESYNC_ROUNDS {
+            let max_slot = chunk_slots.iter().copied().max().unwrap_or(0);
+            for (i, chunk) in chunks.iter().enumerate() {
+                // 首轮取全部分片，之后只重取落后的分片
+                if round > 0 && chunk_slots[i] == max_slot {
+                    continue;
+                }
+                let (slot, values, chunk_source) =
+                    self.get_multiple_accounts_at(chunk, target).await?;
+                if !sources.contains(&chunk_source) {
+                    sources.push(chunk_source);
+                }
+                for (key, account) in chunk.iter().zip(values) {
+                    match account {
+                        Some(account) => {
+                            accounts.insert(*key, account);
+                        }
+                        None => {
+                            accounts.remove(key);
+                        }
+                    }
+                }
+                chunk_slots[i] = slot;
+                target = Some(target.map_or(slot, |t| t.max(slot)));
+            }
+
+            let min = chunk_slots.iter().copied().min().unwrap_or(0);
+            let max = chunk_slots.iter().copied().max().unwrap_or(0);
+            if min == max {
+                break;
+            }
+            if round == MAX_RESYNC_ROUNDS {
+                log::warn!("批量账户未能对齐到同一 slot: {} - {}", min, max);
+            }
+        }
+
+        let slot = chunk_slots
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or_else(|| min_context_slot.unwrap_or(0));
+        let min_slot = chunk_slots.iter().copied().min().unwrap_or(slot);
+
+        let source = if sources.is_empty() {
+            self.url()
+        } else {
+            sources.join(",")
+        };
+        Ok(AccountBatch {
+            slot,
+            min_slot,
+            source,
+            received_at: Utc::now(),
+            accounts,
+        })
+    }
+}
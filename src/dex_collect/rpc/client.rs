@@ -0,0 +1,126 @@
+use anyhow::Result;
+use chrono::{DateTime, TimeZone, Utc};
+use solana_client::{client_error::Result as ClientResult, nonblocking::rpc_client::RpcClient};
+use solana_sdk::{account::Account, commitment_config::CommitmentConfig, pubkey::Pubkey};
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use crate::dex_collect::rpc::pool::RpcPool;
+use crate::dex_collect::stamp::SnapshotStamp;
+
+/// 默认 RPC 节点
+pub const DEFAULT_RPC_URL: &str = "https://api.mainnet-beta.solana.com";
+
+/// 出块时间缓存的最大条目数
+const BLOCK_TIME_CACHE_SIZE: usize = 1024;
+
+/// 共享的非阻塞 RPC 客户端句柄
+///
+/// 内部为 `Arc<RpcPool>`，clone 开销很小，可在多个采集器和任务之间共享，
+/// 不会阻塞 tokio 运行时。
+#[derive(Clone)]
+pub struct RpcHandle {
+    pool: Arc<RpcPool>,
+    block_times: Arc<Mutex<BTreeMap<u64, DateTime<Utc>>>>,
+}
+
+impl RpcHandle {
+    /// 使用指定节点和确认级别创建
+    pub fn new(url: &str, commitment: CommitmentConfig) -> Result<Self> {
+        Ok(Self::from_pool(RpcPool::single(url, commitment)?))
+    }
+
+    /// 使用默认主网节点 (confirmed)
+    pub fn mainnet() -> Self {
+        Self::new(DEFAULT_RPC_URL, CommitmentConfig::confirmed())
+            .expect("default single-endpoint rate limit is valid")
+    }
+
+    /// 使用多节点池创建
+    pub fn from_pool(pool: RpcPool) -> Self {
+        Self {
+            pool: Arc::new(pool),
+            block_times: Arc::new(Mutex::new(BTreeMap::new())),
+        }
+    }
+
+    /// 底层节点池，可查询统计或启动健康检查
+    pub fn pool(&self) -> &Arc<RpcPool> {
+        &self.pool
+    }
+
+    pub fn url(&self) -> String {
+        self.pool.primary_url().to_string()
+    }
+
+    /// 通过节点池执行 RPC 调用
+    pub async fn call<'a, T, F, Fut>(&'a self, f: F) -> Result<T>
+    where
+        F: Fn(Arc<RpcClient>) -> Fut,
+        Fut: Future<Output = ClientResult<T>> + 'a,
+    {
+        self.pool.call(f).await
+    }
+
+    /// 通过节点池执行 RPC 调用，同时返回应答节点地址
+    pub async fn call_with_source<'a, T, F, Fut>(&'a self, f: F) -> Result<(T, String)>
+    where
+        F: Fn(Arc<RpcClient>) -> Fut,
+        Fut: Future<Output = ClientResult<T>> + 'a,
+    {
+        self.pool.call_with_source(f).await
+    }
+
+    /// 获取 slot 的出块时间，结果按 slot 缓存
+    ///
+    /// 刚产生的 slot 可能还查不到出块时间，此时返回 None，下次再查。
+    pub async fn get_block_time(&self, slot: u64) -> Option<DateTime<Utc>> {
+        if let Some(time) = self.block_times.lock().unwrap().get(&slot) {
+            return Some(*time);
+        }
+
+        let unix = self
+            .call(|client| async move { client.get_block_time(slot).await })
+            .await
+            .ok()?;
+        let time = Utc.timestamp_opt(unix, 0).single()?;
+
+        let mut cache = self.block_times.lock().unwrap();
+        cache.insert(slot, time);
+        while cache.len() > BLOCK_TIME_CACHE_SIZE {
+            cache.pop_first();
+        }
+        Some(time)
+    }
+
+    /// 按需补齐快照的出块时间，已有时不再查询
+    ///
+    /// 批量取数不附带出块时间，需要按链上时间对齐的调用方再调用本方法。
+    pub async fn fill_block_time(&self, stamp: &mut SnapshotStamp) {
+        if stamp.block_time.is_none() {
+            stamp.block_time = self.get_block_time(stamp.slot).await;
+        }
+    }
+
+    /// 获取单个账户
+    pub async fn get_account(&self, pubkey: &Pubkey) -> Result<Account> {
+        self.call(|client| async move { client.get_account(pubkey).await })
+            .await
+    }
+
+    /// 按地址字符串获取账户
+    pub async fn get_account_by_address(&self, address: &str) -> Result<Account> {
+        let pubkey = Pubkey::from_str(address)?;
+        self.get_account(&pubkey).await
+    }
+}
+
+impl Default for RpcHandle {
+    fn default() -> Self {
+        Self::mainnet()
+    }
+}
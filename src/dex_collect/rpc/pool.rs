@@ -0,0 +1,518 @@
+use anyhow::Result;
+use rand::Rng;
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind, Result as ClientResult},
+    nonblocking::rpc_client::RpcClient,
+    rpc_custom_error::{
+        JSON_RPC_SERVER_ERROR_BLOCK_NOT_AVAILABLE,
+        JSON_RPC_SERVER_ERROR_LONG_TERM_STORAGE_SLOT_SKIPPED, JSON_RPC_SERVER_ERROR_SLOT_SKIPPED,
+    },
+    rpc_request::RpcError,
+};
+use solana_sdk::commitment_config::CommitmentConfig;
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::task::JoinHandle;
+
+/// 单节点池的每秒请求上限
+const SINGLE_ENDPOINT_RATE: f64 = 10.0;
+
+/// 单个节点配置
+#[derive(Debug, Clone)]
+pub struct EndpointConfig {
+    pub url: String,
+    pub requests_per_second: f64, // 每秒请求上限
+    pub burst: f64,               // 令牌桶容量
+}
+
+impl EndpointConfig {
+    pub fn new(url: &str, requests_per_second: f64) -> Self {
+        Self {
+            url: url.to_string(),
+            requests_per_second,
+            burst: requests_per_second.max(1.0),
+        }
+    }
+
+    /// 速率需为正的有限值，令牌桶容量至少为 1，否则无法计算等待时间
+    fn has_valid_rate(&self) -> bool {
+        self.requests_per_second.is_finite()
+            && self.requests_per_second > 0.0
+            && self.burst.is_finite()
+            && self.burst >= 1.0
+    }
+}
+
+/// 节点池配置
+#[derive(Debug, Clone)]
+pub struct PoolConfig {
+    pub commitment: CommitmentConfig,
+    pub max_retries: usize,     // 单次调用最大重试次数
+    pub base_backoff: Duration, // 首次重试等待
+    pub max_backoff: Duration,  // 重试等待上限
+    pub max_slot_lag: u64,      // 允许落后最高 slot 的数量，超过视为不健康
+    pub health_check_interval: Duration,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            commitment: CommitmentConfig::confirmed(),
+            max_retries: 4,
+            base_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            max_slot_lag: 20,
+            health_check_interval: Duration::from_secs(10),
+        }
+    }
+}
+
+/// 节点统计信息
+#[derive(Debug, Clone)]
+pub struct EndpointStats {
+    pub url: String,
+    pub healthy: bool,
+    pub requests: u64,
+    pub errors: u64,
+    pub rate_limited: u64, // 429 次数
+    pub last_latency: Duration,
+    pub avg_latency: Duration,
+    pub slot: u64,
+    pub slot_lag: u64,
+}
+
+impl EndpointStats {
+    /// 错误率
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.requests as f64
+        }
+    }
+}
+
+/// 令牌桶限流器
+struct RateLimiter {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    fn new(rate: f64, capacity: f64) -> Self {
+        Self {
+            rate,
+            capacity,
+            tokens: capacity,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// 尝试取一个令牌，不足时返回需要等待的时间
+    fn try_acquire(&mut self) -> Option<Duration> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - self.tokens) / self.rate))
+        }
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    requests: u64,
+    errors: u64,
+    rate_limited: u64,
+    total_latency: Duration,
+    last_latency: Duration,
+}
+
+struct Endpoint {
+    url: String,
+    client: Arc<RpcClient>,
+    limiter: Mutex<RateLimiter>,
+    counters: Mutex<Counters>,
+    healthy: AtomicBool,
+    slot: AtomicU64,
+    slot_lag: AtomicU64,
+}
+
+impl Endpoint {
+    fn new(config: &EndpointConfig, commitment: CommitmentConfig) -> Self {
+        Self {
+            url: config.url.clone(),
+            client: Arc::new(RpcClient::new_with_commitment(
+                config.url.clone(),
+                commitment,
+            )),
+            limiter: Mutex::new(RateLimiter::new(config.requests_per_second, config.burst)),
+            counters: Mutex::new(Counters::default()),
+            healthy: AtomicBool::new(true),
+            slot: AtomicU64::new(0),
+            slot_lag: AtomicU64::new(0),
+        }
+    }
+
+    async fn acquire(&self) {
+        loop {
+            let wait = self.limiter.lock().unwrap().try_acquire();
+            match wait {
+                Some(wait) => tokio::time::sleep(wait).await,
+                None => return,
+            }
+        }
+    }
+
+    fn record(&self, latency: Duration, error: Option<&ClientError>) {
+        let mut counters = self.counters.lock().unwrap();
+        counters.requests += 1;
+        counters.total_latency += latency;
+        counters.last_latency = latency;
+        if let Some(err) = error {
+            counters.errors += 1;
+            if is_rate_limited(err) {
+                counters.rate_limited += 1;
+            }
+        }
+    }
+
+    fn stats(&self) -> EndpointStats {
+        let counters = self.counters.lock().unwrap();
+        let avg_latency = if counters.requests == 0 {
+            Duration::ZERO
+        } else {
+            counters.total_latency / counters.requests as u32
+        };
+        EndpointStats {
+            url: self.url.clone(),
+            healthy: self.healthy.load(Ordering::Relaxed),
+            requests: counters.requests,
+            errors: counters.errors,
+            rate_limited: counters.rate_limited,
+            last_latency: counters.last_latency,
+            avg_latency,
+            slot: self.slot.load(Ordering::Relaxed),
+            slot_lag: self.slot_lag.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// 是否为 429 限流错误
+fn is_rate_limited(err: &ClientError) -> bool {
+    match err.kind() {
+        ClientErrorKind::Reqwest(e) => e.status().is_some_and(|s| s.as_u16() == 429),
+        _ => err.to_string().contains("429"),
+    }
+}
+
+/// 是否值得换节点重试，交易、签名错误以及区块不存在/被跳过重试无意义
+fn is_retryable(err: &ClientError) -> bool {
+    match err.kind() {
+        ClientErrorKind::TransactionError(_) | ClientErrorKind::SigningError(_) => false,
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => !matches!(
+            *code,
+            JSON_RPC_SERVER_ERROR_BLOCK_NOT_AVAILABLE
+                | JSON_RPC_SERVER_ERROR_SLOT_SKIPPED
+                | JSON_RPC_SERVER_ERROR_LONG_TERM_STORAGE_SLOT_SKIPPED
+        ),
+        _ => true,
+    }
+}
+
+/// RPC 节点池
+///
+/// 在多个节点之间轮询分发请求，每个节点独立限流；失败时按指数退避加抖动
+/// 换节点重试。健康检查通过 getHealth 和 slot 落后程度标记节点，不健康的
+/// 节点在有健康节点可用时不会被选中。
+pub struct RpcPool {
+    endpoints: Vec<Endpoint>,
+    config: PoolConfig,
+    next: AtomicUsize,
+}
+
+impl RpcPool {
+    pub fn new(endpoints: Vec<EndpointConfig>, config: PoolConfig) -> Result<Self> {
+        if endpoints.is_empty() {
+            return Err(anyhow::anyhow!("RPC pool requires at least one endpoint"));
+        }
+        if let Some(e) = endpoints.iter().find(|e| !e.has_valid_rate()) {
+            return Err(anyhow::anyhow!(
+                "Invalid rate limit for {}: {} req/s, burst {}",
+                e.url,
+                e.requests_per_second,
+                e.burst
+            ));
+        }
+        Ok(Self {
+            endpoints: endpoints
+                .iter()
+                .map(|e| Endpoint::new(e, config.commitment))
+                .collect(),
+            config,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// 单节点池，限流配置与多节点池一样经过校验
+    pub fn single(url: &str, commitment: CommitmentConfig) -> Result<Self> {
+        let config = PoolConfig {
+            commitment,
+            ..PoolConfig::default()
+        };
+        Self::new(vec![EndpointConfig::new(url, SINGLE_ENDPOINT_RATE)], config)
+    }
+
+    pub fn config(&self) -> &PoolConfig {
+        &self.config
+    }
+
+    /// 第一个节点的地址
+    pub fn primary_url(&self) -> &str {
+        &self.endpoints[0].url
+    }
+
+    /// 轮询选择下一个节点，优先健康节点
+    fn pick(&self) -> usize {
+        let n = self.endpoints.len();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        (0..n)
+            .map(|i| (start + i) % n)
+            .find(|&i| self.endpoints[i].healthy.load(Ordering::Relaxed))
+            .unwrap_or(start % n)
+    }
+
+    fn backoff(&self, attempt: usize) -> Duration {
+        let exp = self
+            .config
+            .base_backoff
+            .saturating_mul(1u32 << attempt.min(16))
+            .min(self.config.max_backoff);
+        // 半抖动：在 [exp/2, exp] 之间随机
+        let jitter = rand::thread_rng().gen_range(0.5..=1.0);
+        exp.mul_f64(jitter)
+    }
+
+    /// 在池中执行一次 RPC 调用，失败时换节点重试
+    pub async fn call<'a, T, F, Fut>(&'a self, f: F) -> Result<T>
+    where
+        F: Fn(Arc<RpcClient>) -> Fut,
+        Fut: Future<Output = ClientResult<T>> + 'a,
+    {
+        Ok(self.call_with_source(f).await?.0)
+    }
+
+    /// 同 [`RpcPool::call`]，同时返回实际应答的节点地址
+    pub async fn call_with_source<'a, T, F, Fut>(&'a self, f: F) -> Result<(T, String)>
+    where
+        F: Fn(Arc<RpcClient>) -> Fut,
+        Fut: Future<Output = ClientResult<T>> + 'a,
+    {
+        let mut attempt = 0;
+        loop {
+            let endpoint = &self.endpoints[self.pick()];
+            endpoint.acquire().await;
+
+            let started = Instant::now();
+            let result = f(endpoint.client.clone()).await;
+            let latency = started.elapsed();
+
+            match result {
+                Ok(value) => {
+                    endpoint.record(latency, None);
+                    return Ok((value, endpoint.url.clone()));
+                }
+                Err(err) => {
+                    endpoint.record(latency, Some(&err));
+                    if attempt >= self.config.max_retries || !is_retryable(&err) {
+                        return Err(anyhow::anyhow!("RPC 调用失败 ({}): {}", endpoint.url, err));
+                    }
+                    log::warn!(
+                        "RPC 调用失败 ({}), 第 {} 次重试: {}",
+                        endpoint.url,
+                        attempt + 1,
+                        err
+                    );
+                    tokio::time::sleep(self.backoff(attempt)).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// 检查所有节点健康状态和 slot 落后程度
+    pub async fn check_health(&self) {
+        let mut slots = Vec::with_capacity(self.endpoints.len());
+        for endpoint in &self.endpoints {
+            let started = Instant::now();
+            let health = endpoint.client.get_health().await;
+            let slot = endpoint.client.get_slot().await;
+            let latency = started.elapsed();
+
+            match (&health, &slot) {
+                (Ok(()), Ok(slot)) => {
+                    endpoint.record(latency, None);
+                    endpoint.slot.store(*slot, Ordering::Relaxed);
+                    slots.push(Some(*slot));
+                }
+                (Err(e), _) | (_, Err(e)) => {
+                    endpoint.record(latency, Some(e));
+                    log::warn!("节点健康检查失败 ({}): {}", endpoint.url, e);
+                    slots.push(None);
+                }
+            }
+        }
+
+        self.update_health(&slots);
+    }
+
+    /// 按健康检查结果标记节点，失败或落后最高 slot 过多的节点视为不健康
+    fn update_health(&self, slots: &[Option<u64>]) {
+        let max_slot = slots.iter().flatten().copied().max().unwrap_or(0);
+        for (endpoint, &slot) in self.endpoints.iter().zip(slots) {
+            let healthy = match slot {
+                Some(slot) => {
+                    let lag = max_slot.saturating_sub(slot);
+                    endpoint.slot_lag.store(lag, Ordering::Relaxed);
+                    lag <= self.config.max_slot_lag
+                }
+                None => false,
+            };
+            if endpoint.healthy.swap(healthy, Ordering::Relaxed) != healthy {
+                log::info!("节点 {} 健康状态变为 {}", endpoint.url, healthy);
+            }
+        }
+    }
+
+    /// 启动后台健康检查任务
+    pub fn spawn_health_checker(self: &Arc<Self>) -> JoinHandle<()> {
+        let pool = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(pool.config.health_check_interval);
+            loop {
+                interval.tick().await;
+                pool.check_health().await;
+            }
+        })
+    }
+
+    /// 所有节点的延迟和错误统计
+    pub fn stats(&self) -> Vec<EndpointStats> {
+        self.endpoints.iter().map(|e| e.stats()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_client::rpc_request::RpcResponseErrorData;
+    use solana_sdk::transaction::TransactionError;
+
+    fn pool(n: usize) -> RpcPool {
+        let endpoints = (0..n)
+            .map(|i| EndpointConfig::new(&format!("http://node{}", i), 10.0))
+            .collect();
+        RpcPool::new(endpoints, PoolConfig::default()).unwrap()
+    }
+
+    fn response_error(code: i64) -> ClientError {
+        ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            code,
+            message: String::new(),
+            data: RpcResponseErrorData::Empty,
+        })
+        .into()
+    }
+
+    #[test]
+    fn token_bucket_allows_burst_then_waits() {
+        let mut limiter = RateLimiter::new(2.0, 3.0);
+        for _ in 0..3 {
+            assert_eq!(limiter.try_acquire(), None);
+        }
+        // 令牌用完后约需 1 / rate 秒补充一个
+        let wait = limiter.try_acquire().unwrap();
+        assert!(wait > Duration::from_millis(400) && wait <= Duration::from_millis(500));
+
+        limiter.last_refill -= Duration::from_secs(10);
+        assert_eq!(limiter.try_acquire(), None);
+        assert!(limiter.tokens <= 2.0);
+    }
+
+    #[test]
+    fn invalid_rates_are_rejected() {
+        for (rate, burst) in [
+            (0.0, 1.0),
+            (f64::NAN, 1.0),
+            (10.0, 0.5),
+            (10.0, f64::INFINITY),
+        ] {
+            let endpoint = EndpointConfig {
+                burst,
+                ..EndpointConfig::new("http://node", rate)
+            };
+            assert!(RpcPool::new(vec![endpoint], PoolConfig::default()).is_err());
+        }
+        assert!(RpcPool::new(Vec::new(), PoolConfig::default()).is_err());
+        assert!(RpcPool::single("http://node", CommitmentConfig::confirmed()).is_ok());
+    }
+
+    #[test]
+    fn retryable_errors() {
+        let transaction: ClientError =
+            ClientErrorKind::TransactionError(TransactionError::AccountNotFound).into();
+        assert!(!is_retryable(&transaction));
+        assert!(!is_retryable(&response_error(
+            JSON_RPC_SERVER_ERROR_SLOT_SKIPPED
+        )));
+        assert!(!is_retryable(&response_error(
+            JSON_RPC_SERVER_ERROR_BLOCK_NOT_AVAILABLE
+        )));
+        assert!(is_retryable(&response_error(-32005)));
+
+        let throttled: ClientError =
+            ClientErrorKind::Custom("HTTP 429 Too Many Requests".into()).into();
+        assert!(is_retryable(&throttled));
+        assert!(is_rate_limited(&throttled));
+    }
+
+    #[test]
+    fn pick_skips_unhealthy_and_lagging_endpoints() {
+        let pool = pool(3);
+        // 节点 1 检查失败，节点 2 落后 21 个 slot
+        pool.update_health(&[Some(100), None, Some(79)]);
+        let stats = pool.stats();
+        assert!(stats[0].healthy);
+        assert!(!stats[1].healthy);
+        assert!(!stats[2].healthy);
+        assert_eq!(stats[2].slot_lag, 21);
+        for _ in 0..6 {
+            assert_eq!(pool.pick(), 0);
+        }
+
+        // 落后在允许范围内恢复健康，按轮询分发
+        pool.update_health(&[Some(100), None, Some(80)]);
+        let picks: Vec<usize> = (0..4).map(|_| pool.pick()).collect();
+        assert!(picks.contains(&0) && picks.contains(&2));
+        assert!(!picks.contains(&1));
+
+        // 全部不健康时仍按轮询选择
+        pool.update_health(&[None, None, None]);
+        let picks: Vec<usize> = (0..3).map(|_| pool.pick()).collect();
+        assert_eq!(picks.len(), 3);
+        assert!(picks.contains(&0) && picks.contains(&1) && picks.contains(&2));
+    }
+}
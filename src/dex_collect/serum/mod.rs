@@ -1,3 +1,5 @@
 #[allow(dead_code, unused_variables, unused_imports, deprecated, unused_mut)]
 pub mod serum_client;
 pub mod serum_depth;
+pub mod serum_events;
+pub mod serum_slippage;
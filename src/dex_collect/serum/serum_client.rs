@@ -1,14 +1,35 @@
 use anyhow::Result;
 use chrono::NaiveDateTime;
 use chrono::{DateTime, TimeZone, Utc};
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
-use solana_client::rpc_client::RpcClient;
-use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use solana_sdk::pubkey::Pubkey;
 use std::{
     collections::{HashMap, VecDeque},
     str::FromStr,
 };
-use crate::dex_collect::serum::serum_depth::MarketDepthFetcher;
+
+use crate::dex_collect::fixed::{self, Decimal};
+use crate::dex_collect::oracle::pyth::{self, OracleConfig, OracleDeviation, PythPrice};
+use crate::dex_collect::rpc::client::RpcHandle;
+use crate::dex_collect::serum::serum_depth::{
+    self, BookScale, MarketDepthFetcher, MINT_DECIMALS_OFFSET,
+};
+use crate::dex_collect::serum::serum_events::{self, FillEvent};
+use crate::dex_collect::stamp::SnapshotStamp;
+use crate::dex_collect::stream::update::{BookSide, MarketUpdate, UpdateDecoder};
+use crate::dex_collect::stream::websocket::{self, StreamConfig};
+use crate::executer::serum::TAKER_FEE_BPS;
+use crate::monitor::candle::{Candle, CandleAggregator, Interval};
+use crate::monitor::consolidated::{ConsolidatedAggregator, ConsolidatedBbo, Venue};
+use crate::monitor::microstructure::{BookMetrics, MetricsConfig};
+use crate::monitor::reference::ReferencePrices;
+use crate::monitor::volatility::{self, Jump, JumpConfig, VolatilityStats};
+
+/// 参考价格使用的交易场所名
+const VENUE: &str = "serum";
+/// 计算价格详情和微观结构指标时读取的档位数
+const DETAILS_DEPTH: usize = 50;
 #[derive(Debug)]
 pub struct SerumMarketState {
     pub account_flags: u64,
@@ -92,35 +113,37 @@ impl SerumMarketState {
 pub struct PriceDetails {
     pub base_symbol: String,      // 基础代币符号
     pub quote_symbol: String,     // 计价代币符号
-    pub price: f64,               // 当前价格
-    pub high_24h: f64,            // 24小时最高价
-    pub low_24h: f64,             // 24小时最低价
-    pub volume_24h: f64,          // 24小时交易量
-    pub bid: f64,                 // 最佳买价
-    pub ask: f64,                 // 最佳卖价
-    pub spread: f64,              // 买卖价差
+    pub price: Decimal,           // 当前价格
+    pub high_24h: Decimal,        // 24小时最高价
+    pub low_24h: Decimal,         // 24小时最低价
+    pub volume_24h: Decimal,      // 24小时交易量
+    pub bid: Decimal,             // 最佳买价
+    pub ask: Decimal,             // 最佳卖价
+    pub spread: Decimal,          // 买卖价差
     pub timestamp: DateTime<Utc>, // 时间戳
+    pub stamp: SnapshotStamp,     // 链上 slot、出块时间、接收时间和来源
+    pub metrics: BookMetrics,     // 订单簿微观结构指标
 }
 
 /// 市场深度结构
 #[derive(Debug, Clone)]
 pub struct MarketDepth {
-    pub bids: Vec<(f64, f64)>,    // 买单 (价格, 数量)
-    pub asks: Vec<(f64, f64)>,    // 卖单 (价格, 数量)
-    pub timestamp: DateTime<Utc>, // 时间戳
+    pub bids: Vec<(Decimal, Decimal)>, // 买单 (价格, 数量)
+    pub asks: Vec<(Decimal, Decimal)>, // 卖单 (价格, 数量)
+    pub timestamp: DateTime<Utc>,      // 时间戳
 }
 /// 深度级别结构
 #[derive(Debug, Clone)]
 pub struct Level {
-    pub price: f64,
-    pub size: f64,
-    pub total: f64,
+    pub price: Decimal,
+    pub size: Decimal,
+    pub total: Decimal,
 }
 /// 价格追踪器
 #[derive(Clone)]
 struct PriceTracker {
-    prices: VecDeque<(f64, DateTime<Utc>)>, // 价格历史
-    max_size: usize,                        // 最大历史记录数
+    prices: VecDeque<(Decimal, DateTime<Utc>)>, // 价格历史
+    max_size: usize,                            // 最大历史记录数
 }
 
 impl PriceTracker {
@@ -131,47 +154,68 @@ impl PriceTracker {
         }
     }
 
-    fn add_price(&mut self, price: f64, timestamp: DateTime<Utc>) {
+    fn add_price(&mut self, price: Decimal, timestamp: DateTime<Utc>) {
         if self.prices.len() >= self.max_size {
             self.prices.pop_front();
         }
         self.prices.push_back((price, timestamp));
     }
 
-    fn get_high_low_24h(&self) -> (f64, f64) {
+    fn get_high_low_24h(&self) -> (Decimal, Decimal) {
         let day_ago = Utc::now() - chrono::Duration::days(1);
-        let recent_prices: Vec<f64> = self
+        let recent_prices: Vec<Decimal> = self
             .prices
             .iter()
             .filter(|(_, ts)| *ts > day_ago)
             .map(|(price, _)| *price)
             .collect();
 
-        if recent_prices.is_empty() {
-            return (0.0, 0.0);
-        }
-
-        let high = recent_prices
-            .iter()
-            .fold(f64::NEG_INFINITY, |a, &b| a.max(b));
-        let low = recent_prices.iter().fold(f64::INFINITY, |a, &b| a.min(b));
+        let high = recent_prices.iter().max().copied().unwrap_or(Decimal::ZERO);
+        let low = recent_prices.iter().min().copied().unwrap_or(Decimal::ZERO);
         (high, low)
     }
+
+    /// 最新价格
+    fn latest(&self) -> Option<(Decimal, DateTime<Utc>)> {
+        self.prices.back().copied()
+    }
+
+    /// 按时间升序的价格历史
+    fn history(&self) -> Vec<(Decimal, DateTime<Utc>)> {
+        self.prices.iter().copied().collect()
+    }
 }
 
 pub struct SerumPriceFetcher {
-    rpc_client: RpcClient,
+    rpc: RpcHandle,
+    depth_fetcher: MarketDepthFetcher,
     markets: HashMap<String, String>,
     price_trackers: HashMap<String, PriceTracker>,
+    candles: CandleAggregator,
+    references: ReferencePrices,
+    consolidated: ConsolidatedAggregator, // 跨场所合并订单簿，Serum 深度在每次计算价格时写入
+    reference_windows: Vec<chrono::Duration>, // monitor_price 输出的 TWAP/VWAP 窗口
+    mint_decimals: HashMap<Pubkey, u32>,
+    fill_seqs: HashMap<String, u64>, // 各市场已处理的最新成交序号
+    metrics_config: MetricsConfig,
+    jump_config: JumpConfig,
+    oracles: HashMap<String, Pubkey>, // 市场对 -> Pyth 价格账户
+    oracle_config: OracleConfig,
+}
+
+impl Default for SerumPriceFetcher {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SerumPriceFetcher {
     pub fn new() -> Self {
-        let rpc_client = RpcClient::new_with_commitment(
-            "https://api.mainnet-beta.solana.com",
-            CommitmentConfig::confirmed(),
-        );
+        Self::with_rpc(RpcHandle::mainnet())
+    }
 
+    /// 使用共享的 RPC 句柄创建，深度获取器复用同一个句柄
+    pub fn with_rpc(rpc: RpcHandle) -> Self {
         let mut markets = HashMap::new();
         markets.insert(
             "SOL/USDC".to_string(),
@@ -186,23 +230,51 @@ impl SerumPriceFetcher {
             "4tSvZvnbyzHXLMTiFonMyxZoHmFqau1XArcRCVHLZ5gX".to_string(),
         );
 
+        // Pyth 价格账户，USDC 近似按 USD 计价
+        let oracles = [
+            ("SOL/USDC", "H6ARHf6YXhGYeQfUzQNGk6rDNnLBQKrenN712K4AQJEG"),
+            ("BTC/USDC", "GVXRSBjFk6e6J3NbVPXohDJetcTjaeeuykUpbQF8UoMU"),
+            ("ETH/USDC", "JBu1AL4obBcCMqKBBxhpWCNUt136ijcuMZLFvTP7iWdB"),
+        ]
+        .into_iter()
+        .filter_map(|(pair, account)| Some((pair.to_string(), Pubkey::from_str(account).ok()?)))
+        .collect();
+
         let price_trackers = markets
             .keys()
             .map(|k| (k.clone(), PriceTracker::new(1440))) // 存储24小时的分钟数据
             .collect();
 
         Self {
-            rpc_client,
+            depth_fetcher: MarketDepthFetcher::with_rpc(rpc.clone()),
+            rpc,
             markets,
             price_trackers,
+            candles: CandleAggregator::default(),
+            references: ReferencePrices::default(),
+            consolidated: ConsolidatedAggregator::default(),
+            reference_windows: vec![
+                chrono::Duration::minutes(1),
+                chrono::Duration::minutes(5),
+                chrono::Duration::hours(1),
+            ],
+            mint_decimals: HashMap::new(),
+            fill_seqs: HashMap::new(),
+            metrics_config: MetricsConfig::default(),
+            jump_config: JumpConfig::default(),
+            oracles,
+            oracle_config: OracleConfig::default(),
         }
     }
     pub async fn get_account(&self, market_address: &str) -> Result<solana_sdk::account::Account> {
-        let market_pubkey = Pubkey::from_str(market_address)?;
-        let account = self.rpc_client.get_account(&market_pubkey)?;
-        Ok(account)
+        self.rpc.get_account_by_address(market_address).await
+    }
+
+    /// 共享的 RPC 句柄
+    pub fn rpc(&self) -> &RpcHandle {
+        &self.rpc
     }
-   
+
     /// 获取实时价格详情
     pub async fn get_price_details(&mut self, market_pair: &str) -> Result<PriceDetails> {
         let market_address = self
@@ -210,35 +282,69 @@ impl SerumPriceFetcher {
             .get(market_pair)
             .ok_or_else(|| anyhow::anyhow!("Unsupported market pair"))?;
 
-        let (bids, asks) = self.get_orderbook(market_address).await?;
+        let market_address = market_address.clone();
+        let depth = self
+            .depth_fetcher
+            .get_depth(&market_address, DETAILS_DEPTH)
+            .await?;
+        let bids: Vec<(Decimal, Decimal)> = depth.bids.iter().map(|l| (l.price, l.size)).collect();
+        let asks: Vec<(Decimal, Decimal)> = depth.asks.iter().map(|l| (l.price, l.size)).collect();
+        self.price_details_from_book(market_pair, &bids, &asks, depth.stamp)?
+            .ok_or_else(|| anyhow::anyhow!("One-sided order book for {}", market_pair))
+    }
 
-        let bid = bids.first().map(|(price, _)| *price).unwrap_or(0.0);
-        let ask = asks.first().map(|(price, _)| *price).unwrap_or(0.0);
-        let price = (bid + ask) / 2.0;
+    /// 根据订单簿计算价格详情并更新价格追踪器
+    ///
+    /// 任一侧为空时没有有效的中间价，返回 None 且不记录任何数据。
+    fn price_details_from_book(
+        &mut self,
+        market_pair: &str,
+        bids: &[(Decimal, Decimal)],
+        asks: &[(Decimal, Decimal)],
+        stamp: SnapshotStamp,
+    ) -> Result<Option<PriceDetails>> {
+        let (Some(&(bid, _)), Some(&(ask, _))) = (bids.first(), asks.first()) else {
+            return Ok(None);
+        };
+        let price = (bid + ask) / Decimal::TWO;
         let spread = ask - bid;
+        let metrics = BookMetrics::compute(bids, asks, &self.metrics_config);
 
         let timestamp = Utc::now();
 
-        // 更新价格追踪器
+        // 更新价格追踪器，优先使用链上出块时间
+        let chain_time = stamp.block_time.unwrap_or(stamp.received_at);
         if let Some(tracker) = self.price_trackers.get_mut(market_pair) {
-            tracker.add_price(price, timestamp);
+            tracker.add_price(price, chain_time);
         }
+        self.candles.add_price(market_pair, price, chain_time);
+        self.references
+            .add_mid(VENUE, market_pair, price, chain_time);
+        self.consolidated.book_mut(market_pair).update(
+            Venue::Serum,
+            bids.to_vec(),
+            asks.to_vec(),
+            Decimal::from(TAKER_FEE_BPS),
+            stamp.clone(),
+        );
 
         // 获取24小时高低价
         let (high_24h, low_24h) = self
             .price_trackers
             .get(market_pair)
             .map(|t| t.get_high_low_24h())
-            .unwrap_or((0.0, 0.0));
+            .unwrap_or((Decimal::ZERO, Decimal::ZERO));
 
         // 计算24小时交易量
-        let volume_24h = self.calculate_volume_24h(market_address).await?;
+        let volume_24h =
+            self.references
+                .volume(VENUE, market_pair, chrono::Duration::hours(24), chain_time);
 
         let (base, quote) = market_pair
             .split_once('/')
             .ok_or_else(|| anyhow::anyhow!("Invalid market pair format"))?;
 
-        Ok(PriceDetails {
+        Ok(Some(PriceDetails {
             base_symbol: base.to_string(),
             quote_symbol: quote.to_string(),
             price,
@@ -249,38 +355,48 @@ impl SerumPriceFetcher {
             ask,
             spread,
             timestamp,
-        })
+            stamp,
+            metrics,
+        }))
     }
 
     /// 获取市场状态
     pub async fn get_market_state(&self, market_address: &str) -> Result<SerumMarketState> {
         let market_pubkey = Pubkey::from_str(market_address)?;
-        let account = self.rpc_client.get_account(&market_pubkey)?;
+        let account = self.rpc.get_account(&market_pubkey).await?;
         SerumMarketState::from_bytes(&account.data)
     }
+    /// 获取带时间戳的市场深度快照
+    pub async fn get_depth_snapshot(
+        &self,
+        market_address: &str,
+    ) -> Result<serum_depth::MarketDepth> {
+        self.depth_fetcher.get_depth(market_address, 1).await
+    }
+
     /// 获取市场深度
-    pub  async fn get_orderbook(
+    pub async fn get_orderbook(
         &self,
         market_address: &str,
-    ) -> Result<(Vec<(f64, f64)>, Vec<(f64, f64)>)> {
-        // 原有的订单簿获取逻辑
-        let marker = MarketDepthFetcher::new();
-        let depth = marker
-            .get_depth(market_address, 1)
-            .await?;
-        marker.print_depth(&depth);
+    ) -> Result<(Vec<(Decimal, Decimal)>, Vec<(Decimal, Decimal)>)> {
+        let depth = self.get_depth_snapshot(market_address).await?;
+        self.depth_fetcher.print_depth(&depth);
 
         let mut bids = vec![];
         for level in &depth.bids {
             bids.push((level.price, level.size));
-            println!("{:<15.6} {:<15.6} {:<15.6}", 
-                level.price, level.size, level.total);
+            println!(
+                "{:<15.6} {:<15.6} {:<15.6}",
+                level.price, level.size, level.total
+            );
         }
         let mut asks = vec![];
         for level in &depth.asks {
             asks.push((level.price, level.size));
-            println!("{:<15.6} {:<15.6} {:<15.6}", 
-                level.price, level.size, level.total);
+            println!(
+                "{:<15.6} {:<15.6} {:<15.6}",
+                level.price, level.size, level.total
+            );
         }
         Ok((bids, asks)) // 临时返回，需要实现实际逻辑
     }
@@ -311,14 +427,16 @@ impl SerumPriceFetcher {
 
             if let Ok(price_bytes) = chunk[0..8].try_into() {
                 if let Ok(size_bytes) = chunk[8..16].try_into() {
-                    let price = u64::from_le_bytes(price_bytes) as f64 / 1_000_000.0;
-                    let size = u64::from_le_bytes(size_bytes) as f64 / 1_000_000.0;
+                    let price =
+                        fixed::from_raw(u64::from_le_bytes(price_bytes), 6).unwrap_or_default();
+                    let size =
+                        fixed::from_raw(u64::from_le_bytes(size_bytes), 6).unwrap_or_default();
 
-                    if price > 0.0 && size > 0.0 {
+                    if price > Decimal::ZERO && size > Decimal::ZERO {
                         orders.push(Level {
                             price,
                             size,
-                            total: 0.0,
+                            total: Decimal::ZERO,
                         });
                     }
                 }
@@ -328,10 +446,195 @@ impl SerumPriceFetcher {
         Ok(())
     }
 
-    /// 计算24小时交易量
-    async fn calculate_volume_24h(&self, market_address: &str) -> Result<f64> {
-        // ... 实现交易量计算逻辑 ...
-        Ok(0.0) // 临时返回，需要实现实际逻辑
+    /// 获取市场最近 n 根K线
+    pub fn get_candles(&self, market_pair: &str, interval: Interval, n: usize) -> Vec<Candle> {
+        self.candles.last_candles(market_pair, interval, n)
+    }
+
+    /// 设置微观结构指标的档位和深度范围
+    pub fn set_metrics_config(&mut self, config: MetricsConfig) {
+        self.metrics_config = config;
+    }
+
+    /// 设置跳变检测阈值
+    pub fn set_jump_config(&mut self, config: JumpConfig) {
+        self.jump_config = config;
+    }
+
+    /// 基于最近 n 根K线的已实现波动率和收益率 z 值
+    pub fn get_volatility(
+        &self,
+        market_pair: &str,
+        interval: Interval,
+        n: usize,
+    ) -> VolatilityStats {
+        let candles = self.candles.last_candles(market_pair, interval, n);
+        volatility::realized_volatility(&candles, interval)
+    }
+
+    /// 检测最新价格是否在短时间内偏离超过阈值
+    pub fn detect_jump(&self, market_pair: &str) -> Option<Jump> {
+        let prices = self.price_trackers.get(market_pair)?.history();
+        volatility::detect_jump(&prices, &self.jump_config)
+    }
+
+    /// 设置市场对应的 Pyth 价格账户
+    pub fn set_oracle(&mut self, market_pair: &str, price_account: Pubkey) {
+        self.oracles.insert(market_pair.to_string(), price_account);
+    }
+
+    /// 设置预言机时效和置信区间检查
+    pub fn set_oracle_config(&mut self, config: OracleConfig) {
+        self.oracle_config = config;
+    }
+
+    /// 读取市场对应的 Pyth 价格，并按当前 slot 检查状态和时效
+    pub async fn get_oracle_price(&self, market_pair: &str) -> Result<PythPrice> {
+        let account = *self
+            .oracles
+            .get(market_pair)
+            .ok_or_else(|| anyhow::anyhow!("No oracle configured for {}", market_pair))?;
+        let batch = self.rpc.get_accounts_batch(&[account], None).await?;
+        let price = PythPrice::from_bytes(&batch.require(&account)?.data)?;
+        price.check(batch.slot, &self.oracle_config)?;
+        Ok(price)
+    }
+
+    /// 最新中间价相对预言机价格的偏离
+    pub async fn get_oracle_deviation(&self, market_pair: &str) -> Result<OracleDeviation> {
+        let (mid, _) = self
+            .price_trackers
+            .get(market_pair)
+            .and_then(|t| t.latest())
+            .ok_or_else(|| anyhow::anyhow!("No price tracked for {}", market_pair))?;
+        let oracle = self.get_oracle_price(market_pair).await?;
+        pyth::deviation(mid, &oracle).ok_or_else(|| anyhow::anyhow!("Oracle price is zero"))
+    }
+
+    /// 设置 monitor_price 输出的 TWAP/VWAP 窗口
+    pub fn set_reference_windows(&mut self, windows: Vec<chrono::Duration>) {
+        self.reference_windows = windows;
+    }
+
+    /// 市场在窗口内的时间加权平均价，基于价格快照历史
+    pub fn get_twap(&self, market_pair: &str, window: chrono::Duration) -> Option<Decimal> {
+        self.references.twap(VENUE, market_pair, window, Utc::now())
+    }
+
+    /// 市场在窗口内的成交量加权平均价，基于事件队列中解码出的成交
+    pub fn get_vwap(&self, market_pair: &str, window: chrono::Duration) -> Option<Decimal> {
+        self.references.vwap(VENUE, market_pair, window, Utc::now())
+    }
+
+    /// 读取 base/quote mint 的精度，结果会缓存
+    async fn market_decimals(&mut self, state: &SerumMarketState) -> Result<(u32, u32)> {
+        let mints = [state.base_mint, state.quote_mint];
+        let missing: Vec<Pubkey> = mints
+            .iter()
+            .filter(|m| !self.mint_decimals.contains_key(m))
+            .copied()
+            .collect();
+        if !missing.is_empty() {
+            let batch = self.rpc.get_accounts_batch(&missing, None).await?;
+            for mint in &missing {
+                let decimals = *batch
+                    .require(mint)?
+                    .data
+                    .get(MINT_DECIMALS_OFFSET)
+                    .ok_or_else(|| anyhow::anyhow!("Invalid mint account {}", mint))?;
+                self.mint_decimals.insert(*mint, decimals as u32);
+            }
+        }
+        Ok((
+            self.mint_decimals[&state.base_mint],
+            self.mint_decimals[&state.quote_mint],
+        ))
+    }
+
+    /// 把成交计入 VWAP 和K线，只统计 maker 一侧避免重复
+    fn apply_fills(
+        &mut self,
+        market_pair: &str,
+        fills: &[FillEvent],
+        decimals: (u32, u32),
+        stamp: &SnapshotStamp,
+    ) -> usize {
+        let time = stamp.block_time.unwrap_or(stamp.received_at);
+        let last_seq = self.fill_seqs.get(market_pair).copied();
+        let mut applied = 0;
+        for fill in fills {
+            if last_seq.is_some_and(|seq| fill.seq_num <= seq) {
+                continue;
+            }
+            self.fill_seqs.insert(market_pair.to_string(), fill.seq_num);
+            if !fill.maker {
+                continue;
+            }
+            let (Some(price), Some(size)) =
+                (fill.price(decimals.0, decimals.1), fill.size(decimals.0))
+            else {
+                continue;
+            };
+            self.references
+                .add_fill(VENUE, market_pair, price, size, time);
+            self.candles.add_trade(market_pair, price, size, time);
+            applied += 1;
+        }
+        applied
+    }
+
+    /// 拉取事件队列并处理新成交，返回计入的成交笔数
+    pub async fn update_fills(&mut self, market_pair: &str) -> Result<usize> {
+        let market_address = self
+            .markets
+            .get(market_pair)
+            .ok_or_else(|| anyhow::anyhow!("Unsupported market pair"))?
+            .clone();
+        let state = self.get_market_state(&market_address).await?;
+        let decimals = self.market_decimals(&state).await?;
+
+        let batch = self
+            .rpc
+            .get_accounts_batch(&[state.event_queue], None)
+            .await?;
+        let event_queue = batch.require(&state.event_queue)?;
+        let (header, fills) = serum_events::decode_event_queue(&event_queue.data)?;
+        // 首次拉取时队列里都是旧成交，只记下当前序号
+        if !self.fill_seqs.contains_key(market_pair) {
+            self.fill_seqs
+                .insert(market_pair.to_string(), header.seq_num.saturating_sub(1));
+            return Ok(0);
+        }
+        Ok(self.apply_fills(market_pair, &fills, decimals, &batch.stamp()))
+    }
+
+    /// 写入 AMM 池储备 (UI 数量)，与 Serum 深度合并
+    pub fn update_amm_book(
+        &mut self,
+        market_pair: &str,
+        venue: Venue,
+        base_reserve: Decimal,
+        quote_reserve: Decimal,
+        fee_bps: Decimal,
+        stamp: SnapshotStamp,
+    ) {
+        self.consolidated.book_mut(market_pair).update_amm(
+            venue,
+            base_reserve,
+            quote_reserve,
+            fee_bps,
+            stamp,
+        );
+    }
+
+    /// 跨场所合并订单簿
+    pub fn consolidated(&self) -> &ConsolidatedAggregator {
+        &self.consolidated
+    }
+
+    /// 市场的跨场所最优买卖价 (计入手续费)
+    pub fn get_consolidated_bbo(&self, market_pair: &str) -> Option<ConsolidatedBbo> {
+        self.consolidated.bbo(market_pair)
     }
 
     /// 获取所有支持的市场对
@@ -340,31 +643,213 @@ impl SerumPriceFetcher {
     }
 
     /// 监控价格变化
+    ///
+    /// 订阅市场 bids/asks 账户，每次推送更新后重新计算价格，
+    /// websocket 不可用时订阅层会自动退回轮询。
     pub async fn monitor_price(&mut self, market_pair: &str) -> Result<()> {
         println!("开始监控 {:?} 价格变化...", market_pair);
 
-        let mut last_price = 0.0;
-        loop {
-            match self.get_price_details(market_pair).await {
-                Ok(details) => {
-                    //if (details.price - last_price).abs() > 0.01 {
-                        println!("\n价格更新 - {:?}", market_pair);
-                        println!("时间: {:?}", details.timestamp);
-                        println!("当前价格: {:?} {}", details.price, details.quote_symbol);
-                        println!("买价: {} {}", details.bid, details.quote_symbol);
-                        println!("卖价: {} {}", details.ask, details.quote_symbol);
-                        println!("价差: {} {}", details.spread, details.quote_symbol);
-                        println!("24h高: {} {}", details.high_24h, details.quote_symbol);
-                        println!("24h低: {} {}", details.low_24h, details.quote_symbol);
-                        println!("24h成交量: {} {}", details.volume_24h, details.base_symbol);
-
-                        last_price = details.price;
-                    //}
+        let market_address = self
+            .markets
+            .get(market_pair)
+            .ok_or_else(|| anyhow::anyhow!("Unsupported market pair"))?
+            .clone();
+        let market = Pubkey::from_str(&market_address)?;
+        let state = self.get_market_state(&market_address).await?;
+        let decimals = self.market_decimals(&state).await?;
+
+        let mut decoder = UpdateDecoder::default();
+        let scale = BookScale {
+            base_lot_size: state.base_lot_size,
+            quote_lot_size: state.quote_lot_size,
+            base_decimals: decimals.0,
+            quote_decimals: decimals.1,
+        };
+        decoder.track_book(market, state.bids, state.asks, scale);
+        decoder.track_event_queue(market, state.event_queue);
+        let mut updates =
+            websocket::subscribe(self.rpc.clone(), decoder, StreamConfig::from_rpc(&self.rpc));
+
+        let mut bids: Vec<(Decimal, Decimal)> = Vec::new();
+        let mut asks: Vec<(Decimal, Decimal)> = Vec::new();
+        while let Some(update) = updates.next().await {
+            let (side, levels, stamp) = match update {
+                MarketUpdate::Book {
+                    side,
+                    levels,
+                    stamp,
+                    ..
+                } => (side, levels, stamp),
+                MarketUpdate::Fills { fills, stamp, .. } => {
+                    self.apply_fills(market_pair, &fills, decimals, &stamp);
+                    continue;
+                }
+                _ => continue,
+            };
+            let levels = levels.iter().map(|l| (l.price, l.size)).collect();
+            match side {
+                BookSide::Bids => bids = levels,
+                BookSide::Asks => asks = levels,
+            }
+
+            match self.price_details_from_book(market_pair, &bids, &asks, stamp) {
+                // 另一侧尚未推送
+                Ok(None) => {}
+                Ok(Some(details)) => {
+                    Self::print_price_details(market_pair, &details);
+                    self.print_reference_prices(market_pair, &details.quote_symbol);
+                    self.print_volatility(market_pair);
+                    if let Some(bbo) = self
+                        .get_consolidated_bbo(market_pair)
+                        .filter(|bbo| bbo.crossed)
+                    {
+                        log::warn!("{} 跨场所价格交叉: {:?}", market_pair, bbo);
+                    }
                 }
                 Err(e) => println!("获取价格失败: {}", e),
             }
+        }
+
+        Err(anyhow::anyhow!("价格订阅已结束"))
+    }
+
+    fn print_volatility(&self, market_pair: &str) {
+        let stats = self.get_volatility(market_pair, Interval::M1, 60);
+        let format = |value: Option<f64>| {
+            value
+                .map(|v| format!("{:.4}%", v * 100.0))
+                .unwrap_or_else(|| "-".to_string())
+        };
+        println!(
+            "1h 年化波动率: 收盘 {}  Parkinson {}  收益率 z 值: {}",
+            format(stats.annualized_close_to_close),
+            format(stats.annualized_parkinson),
+            stats
+                .last_return_zscore
+                .map(|z| format!("{:.2}", z))
+                .unwrap_or_else(|| "-".to_string()),
+        );
+
+        if let Some(jump) = self.detect_jump(market_pair) {
+            log::warn!(
+                "{} 价格{}: {} -> {} ({:.1} 倍标准差, {} 秒内)",
+                market_pair,
+                if jump.is_drop() { "急跌" } else { "急涨" },
+                jump.from_price,
+                jump.to_price,
+                jump.sigmas,
+                (jump.to_time - jump.from_time).num_seconds(),
+            );
+        }
+    }
+
+    fn print_reference_prices(&self, market_pair: &str, quote_symbol: &str) {
+        let format = |price: Option<Decimal>| {
+            price
+                .map(|p| format!("{} {}", p.round_dp(6), quote_symbol))
+                .unwrap_or_else(|| "-".to_string())
+        };
+        for window in &self.reference_windows {
+            println!(
+                "{}分钟 TWAP: {}  VWAP: {}",
+                window.num_minutes(),
+                format(self.get_twap(market_pair, *window)),
+                format(self.get_vwap(market_pair, *window)),
+            );
+        }
+    }
 
-            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+    fn print_price_details(market_pair: &str, details: &PriceDetails) {
+        println!("\n价格更新 - {:?}", market_pair);
+        println!("时间: {:?}", details.timestamp);
+        println!(
+            "slot: {} 出块时间: {:?} 来源: {}",
+            details.stamp.slot, details.stamp.block_time, details.stamp.source
+        );
+        println!("当前价格: {:?} {}", details.price, details.quote_symbol);
+        println!("买价: {} {}", details.bid, details.quote_symbol);
+        println!("卖价: {} {}", details.ask, details.quote_symbol);
+        println!("价差: {} {}", details.spread, details.quote_symbol);
+        println!("24h高: {} {}", details.high_24h, details.quote_symbol);
+        println!("24h低: {} {}", details.low_24h, details.quote_symbol);
+        println!("24h成交量: {} {}", details.volume_24h, details.base_symbol);
+
+        let metrics = &details.metrics;
+        let format = |value: Option<Decimal>| {
+            value
+                .map(|v| v.round_dp(6).to_string())
+                .unwrap_or_else(|| "-".to_string())
+        };
+        println!(
+            "微价格: {}  加权中间价: {}  价差: {} bps",
+            format(metrics.microprice),
+            format(metrics.weighted_mid),
+            format(metrics.spread_bps.map(|v| v.round_dp(2))),
+        );
+        println!(
+            "档位: 买 {} / 卖 {}  最优档排队: 买 {} / 卖 {}",
+            metrics.bid_levels, metrics.ask_levels, metrics.bid_touch_size, metrics.ask_touch_size
+        );
+        for imbalance in &metrics.imbalance {
+            println!(
+                "前{}档不平衡: {}",
+                imbalance.levels,
+                imbalance.value.round_dp(4)
+            );
         }
+        for band in &metrics.depth {
+            println!(
+                "±{} bps 深度: 买 {} / 卖 {}",
+                band.bps, band.bid_size, band.ask_size
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAIR: &str = "SOL/USDC";
+
+    #[test]
+    fn one_sided_book_records_nothing() {
+        let mut fetcher = SerumPriceFetcher::new();
+        let bids = vec![(Decimal::from(100), Decimal::ONE)];
+        let asks = vec![(Decimal::from(102), Decimal::ONE)];
+
+        let details = fetcher
+            .price_details_from_book(PAIR, &bids, &[], SnapshotStamp::new(1, "test"))
+            .unwrap();
+        assert!(details.is_none());
+        assert!(fetcher.price_trackers[PAIR].prices.is_empty());
+        assert!(fetcher.candles.markets().is_empty());
+
+        // 24 小时成交量取自记录的成交
+        let now = Utc::now();
+        let size = Decimal::from(3);
+        fetcher.references.add_fill(
+            VENUE,
+            PAIR,
+            Decimal::from(101),
+            size,
+            now - chrono::Duration::hours(25),
+        );
+        fetcher.references.add_fill(
+            VENUE,
+            PAIR,
+            Decimal::from(101),
+            size,
+            now - chrono::Duration::hours(1),
+        );
+
+        let details = fetcher
+            .price_details_from_book(PAIR, &bids, &asks, SnapshotStamp::new(2, "test"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(details.price, Decimal::from(101));
+        assert_eq!(details.spread, Decimal::TWO);
+        assert_eq!(details.volume_24h, size);
+        assert_eq!(fetcher.price_trackers[PAIR].prices.len(), 1);
     }
 }
@@ -1,14 +1,63 @@
 use anyhow::Result;
-use solana_client::rpc_client::RpcClient;
-use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
-use std::{collections::HashMap, str::FromStr, mem::size_of};
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
+
+use crate::dex_collect::fixed::{self, Decimal};
+use crate::dex_collect::rpc::batch::AccountBatch;
+use crate::dex_collect::rpc::client::RpcHandle;
+use crate::dex_collect::stamp::SnapshotStamp;
+
+/// SPL Mint 账户中 decimals 字段的偏移
+pub const MINT_DECIMALS_OFFSET: usize = 44;
+
+/// 订单簿 slab 布局: 5 字节 "serum" 头 + 8 字节 account_flags + 32 字节 slab 头，
+/// 之后是 72 字节的节点数组，末尾 7 字节填充
+const SLAB_HEAD_PADDING: usize = 5;
+const SLAB_TAIL_PADDING: usize = 7;
+const SLAB_HEADER_SIZE: usize = 8 + 32;
+const SLAB_NODE_SIZE: usize = 72;
+
+const NODE_TAG_INNER: u32 = 1;
+const NODE_TAG_LEAF: u32 = 2;
+
+/// 订单簿 lots 与 UI 数量的换算参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookScale {
+    pub base_lot_size: u64,
+    pub quote_lot_size: u64,
+    pub base_decimals: u32,
+    pub quote_decimals: u32,
+}
 
-/// 深度级别结构
-#[derive(Debug, Clone)]
+impl BookScale {
+    /// 价格 lots 转换为 UI 价格
+    pub fn price(&self, price_lots: u64) -> Option<Decimal> {
+        fixed::lots_to_price(
+            price_lots,
+            self.base_lot_size,
+            self.quote_lot_size,
+            self.base_decimals,
+            self.quote_decimals,
+        )
+    }
+
+    /// 数量 lots 转换为 UI 数量
+    pub fn size(&self, size_lots: u64) -> Option<Decimal> {
+        fixed::lots_to_size(size_lots, self.base_lot_size, self.base_decimals)
+    }
+}
+
+/// 深度级别结构，价格和数量均为定点数
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Level {
-    pub price: f64,
-    pub size: f64,
-    pub total: f64,
+    pub price: Decimal,
+    pub size: Decimal,
+    pub total: Decimal,
 }
 
 /// 市场深度结构
@@ -16,13 +65,14 @@ pub struct Level {
 pub struct MarketDepth {
     pub bids: Vec<Level>,
     pub asks: Vec<Level>,
-    pub spread: f64,
-    pub total_bid_size: f64,
-    pub total_ask_size: f64,
+    pub spread: Decimal,
+    pub total_bid_size: Decimal,
+    pub total_ask_size: Decimal,
+    pub stamp: SnapshotStamp, // slot、出块时间、接收时间和来源
 }
 
 /// 市场状态结构 - 不使用 bytemuck，直接解析字段
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MarketState {
     pub account_flags: u64,
     pub own_address: Pubkey,
@@ -71,7 +121,7 @@ impl MarketState {
             if *pos + 32 > data.len() {
                 return Err(anyhow::anyhow!("Buffer overflow while reading Pubkey"));
             }
-            let pubkey = Pubkey::new(&data[*pos..*pos + 32]);
+            let pubkey = Pubkey::try_from(&data[*pos..*pos + 32])?;
             *pos += 32;
             Ok(pubkey)
         };
@@ -99,67 +149,168 @@ impl MarketState {
             referrer_rebates_accrued: read_u64(data, &mut pos)?,
         })
     }
+
+    /// 结合 base/quote mint 精度得到订单簿换算参数
+    pub fn scale(&self, decimals: (u32, u32)) -> BookScale {
+        BookScale {
+            base_lot_size: self.base_lot_size,
+            quote_lot_size: self.quote_lot_size,
+            base_decimals: decimals.0,
+            quote_decimals: decimals.1,
+        }
+    }
 }
 
+type BookAccounts = (Pubkey, Pubkey, (u32, u32));
+
+#[derive(Clone)]
 pub struct MarketDepthFetcher {
-    rpc_client: RpcClient,
-    markets: HashMap<String, String>,
+    rpc: RpcHandle,
+    // 市场地址 -> (bids, asks, base/quote 精度)，市场创建后不会变化
+    book_accounts: Arc<RwLock<HashMap<Pubkey, BookAccounts>>>,
+}
+
+impl Default for MarketDepthFetcher {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MarketDepthFetcher {
     pub fn new() -> Self {
-        let rpc_client = RpcClient::new_with_commitment(
-            "https://api.mainnet-beta.solana.com",
-            CommitmentConfig::confirmed(),
-        );
-
-        let mut markets = HashMap::new();
-        markets.insert(
-            "SOL/USDC".to_string(),
-            "9wFFyRfZBsuAha4YcuxcXLKwMxJR43S7fPfQLusDBzvT".to_string(),
-        );
+        Self::with_rpc(RpcHandle::mainnet())
+    }
 
+    /// 使用共享的 RPC 句柄创建
+    pub fn with_rpc(rpc: RpcHandle) -> Self {
         Self {
-            rpc_client,
-            markets,
+            rpc,
+            book_accounts: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
     pub async fn get_market_state(&self, market_address: &str) -> Result<MarketState> {
         let market_pubkey = Pubkey::from_str(market_address)?;
-        let account = self.rpc_client.get_account(&market_pubkey)?;
+        let account = self.rpc.get_account(&market_pubkey).await?;
         MarketState::from_bytes(&account.data)
     }
 
+    /// 查找市场的 bids/asks 账户和 mint 精度，未缓存的市场批量获取一次后缓存
+    async fn resolve_book_accounts(&self, markets: &[Pubkey]) -> Result<Vec<BookAccounts>> {
+        let missing: Vec<Pubkey> = {
+            let cache = self.book_accounts.read().unwrap();
+            markets
+                .iter()
+                .filter(|m| !cache.contains_key(m))
+                .copied()
+                .collect()
+        };
+
+        if !missing.is_empty() {
+            let batch = self.rpc.get_accounts_batch(&missing, None).await?;
+            let states = missing
+                .iter()
+                .map(|market| MarketState::from_bytes(&batch.require(market)?.data))
+                .collect::<Result<Vec<_>>>()?;
+            let mints: Vec<Pubkey> = states
+                .iter()
+                .flat_map(|state| [state.base_mint, state.quote_mint])
+                .collect();
+            let mint_batch = self.rpc.get_accounts_batch(&mints, None).await?;
+            let decimals = |mint: &Pubkey| -> Result<u32> {
+                let data = &mint_batch.require(mint)?.data;
+                data.get(MINT_DECIMALS_OFFSET)
+                    .map(|d| *d as u32)
+                    .ok_or_else(|| anyhow::anyhow!("Invalid mint account {}", mint))
+            };
+
+            let mut cache = self.book_accounts.write().unwrap();
+            for (market, state) in missing.iter().zip(&states) {
+                let decimals = (decimals(&state.base_mint)?, decimals(&state.quote_mint)?);
+                cache.insert(*market, (state.bids, state.asks, decimals));
+            }
+        }
+
+        let cache = self.book_accounts.read().unwrap();
+        Ok(markets.iter().map(|m| cache[m]).collect())
+    }
+
+    /// 获取单个市场深度，market/bids/asks 来自同一 slot
     pub async fn get_depth(&self, market_address: &str, depth_level: usize) -> Result<MarketDepth> {
-        let market_state = self.get_market_state(market_address).await?;
+        let mut depths = self.get_depths(&[market_address], depth_level).await?;
+        Ok(depths.remove(0))
+    }
 
-        // 获取订单簿账户数据
-        let bids_account = self.rpc_client.get_account(&market_state.bids)?;
-        let asks_account = self.rpc_client.get_account(&market_state.asks)?;
+    /// 批量获取多个市场深度，所有订单簿账户在一次批量请求中按同一 slot 获取
+    ///
+    /// 不查询出块时间，需要时用 [`RpcHandle::fill_block_time`] 补齐。
+    pub async fn get_depths(
+        &self,
+        market_addresses: &[&str],
+        depth_level: usize,
+    ) -> Result<Vec<MarketDepth>> {
+        let markets = market_addresses
+            .iter()
+            .map(|address| Pubkey::from_str(address))
+            .collect::<Result<Vec<_>, _>>()?;
+        let book_accounts = self.resolve_book_accounts(&markets).await?;
+
+        let mut keys = Vec::with_capacity(markets.len() * 3);
+        for (market, (bids, asks, _)) in markets.iter().zip(&book_accounts) {
+            keys.extend([*market, *bids, *asks]);
+        }
+        let mut batch = self.rpc.get_accounts_batch(&keys, None).await?;
+        // 分片落在不同 slot 时以最大 slot 重取一次，仍不一致则不返回拼接的深度
+        if !batch.is_consistent() {
+            batch = self.rpc.get_accounts_batch(&keys, Some(batch.slot)).await?;
+            if !batch.is_consistent() {
+                return Err(anyhow::anyhow!(
+                    "Order book accounts span slots {} - {}",
+                    batch.min_slot,
+                    batch.slot
+                ));
+            }
+        }
 
-        // 解析订单簿
-        let (mut bids, mut asks) = self.parse_orderbook(&bids_account.data, &asks_account.data)?;
+        markets
+            .iter()
+            .zip(&book_accounts)
+            .map(|(market, (_, _, decimals))| {
+                self.depth_from_batch(market, &batch, *decimals, depth_level)
+            })
+            .collect()
+    }
+
+    /// 从批量结果中解析单个市场深度
+    pub fn depth_from_batch(
+        &self,
+        market: &Pubkey,
+        batch: &AccountBatch,
+        decimals: (u32, u32),
+        depth_level: usize,
+    ) -> Result<MarketDepth> {
+        let market_state = MarketState::from_bytes(&batch.require(market)?.data)?;
+        let scale = market_state.scale(decimals);
 
-        // 排序
-        bids.sort_by(|a, b| b.price.partial_cmp(&a.price).unwrap()); // 买单降序
-        asks.sort_by(|a, b| a.price.partial_cmp(&b.price).unwrap()); // 卖单升序
+        // 获取订单簿账户数据
+        let bids_account = batch.require(&market_state.bids)?;
+        let asks_account = batch.require(&market_state.asks)?;
 
-        // 计算累计数量
-        self.calculate_totals(&mut bids);
-        self.calculate_totals(&mut asks);
+        // 解析订单簿
+        let bids = decode_book_side(&bids_account.data, true, &scale)?;
+        let asks = decode_book_side(&asks_account.data, false, &scale)?;
 
         // 截取指定深度
         let bids: Vec<Level> = bids.into_iter().take(depth_level).collect();
         let asks: Vec<Level> = asks.into_iter().take(depth_level).collect();
 
         // 计算统计数据
-        let total_bid_size: f64 = bids.iter().map(|level| level.size).sum();
-        let total_ask_size: f64 = asks.iter().map(|level| level.size).sum();
+        let total_bid_size: Decimal = bids.iter().map(|level| level.size).sum();
+        let total_ask_size: Decimal = asks.iter().map(|level| level.size).sum();
         let spread = if !bids.is_empty() && !asks.is_empty() {
             asks[0].price - bids[0].price
         } else {
-            0.0
+            Decimal::ZERO
         };
 
         Ok(MarketDepth {
@@ -168,60 +319,13 @@ impl MarketDepthFetcher {
             spread,
             total_bid_size,
             total_ask_size,
+            stamp: batch.stamp(),
         })
     }
 
-    fn parse_orderbook(&self, bids_data: &[u8], asks_data: &[u8]) -> Result<(Vec<Level>, Vec<Level>)> {
-        let mut bids = Vec::new();
-        let mut asks = Vec::new();
-
-        self.parse_orders(bids_data, true, &mut bids)?;
-        self.parse_orders(asks_data, false, &mut asks)?;
-
-        Ok((bids, asks))
-    }
-
-    fn parse_orders(&self, data: &[u8], _is_bids: bool, orders: &mut Vec<Level>) -> Result<()> {
-        if data.len() < 5 {
-            return Ok(());
-        }
-
-        let data = &data[5..]; // 跳过头部
-
-        for chunk in data.chunks(32) {
-            if chunk.len() < 16 {
-                break;
-            }
-
-            if let Ok(price_bytes) = chunk[0..8].try_into() {
-                if let Ok(size_bytes) = chunk[8..16].try_into() {
-                    let price = u64::from_le_bytes(price_bytes) as f64 / 1_000_000.0;
-                    let size = u64::from_le_bytes(size_bytes) as f64 / 1_000_000.0;
-
-                    if price > 0.0 && size > 0.0 {
-                        orders.push(Level {
-                            price,
-                            size,
-                            total: 0.0,
-                        });
-                    }
-                }
-            }
-        }
-
-        Ok(())
-    }
-
-    fn calculate_totals(&self, levels: &mut Vec<Level>) {
-        let mut running_total = 0.0;
-        for level in levels.iter_mut() {
-            running_total += level.size;
-            level.total = running_total;
-        }
-    }
-
     pub fn print_depth(&self, depth: &MarketDepth) {
         println!("\n市场深度信息:");
+        println!("slot: {} 来源: {}", depth.stamp.slot, depth.stamp.source);
         println!("买卖价差: {:.6} USDC", depth.spread);
         println!("买单总量: {:.6}", depth.total_bid_size);
         println!("卖单总量: {:.6}", depth.total_ask_size);
@@ -242,4 +346,212 @@ impl MarketDepthFetcher {
                 level.price, level.size, level.total);
         }
     }
-}
\ No newline at end of file
+}
+
+/// 解析单边订单簿账户，返回按价格排序并计算好累计数量的深度
+///
+/// 同一价格的订单合并为一档，买单按价格降序，卖单按价格升序。
+pub fn decode_book_side(data: &[u8], is_bids: bool, scale: &BookScale) -> Result<Vec<Level>> {
+    // 价格 lots -> 数量 lots
+    let mut book: BTreeMap<u64, u64> = BTreeMap::new();
+    for (price_lots, quantity) in slab_leaves(data)? {
+        let total = book.entry(price_lots).or_default();
+        *total = total.saturating_add(quantity);
+    }
+
+    let mut levels = book
+        .into_iter()
+        .filter(|(price_lots, quantity)| *price_lots > 0 && *quantity > 0)
+        .map(|(price_lots, quantity)| {
+            Ok(Level {
+                price: scale
+                    .price(price_lots)
+                    .ok_or_else(|| anyhow::anyhow!("Price lots out of range: {}", price_lots))?,
+                size: scale
+                    .size(quantity)
+                    .ok_or_else(|| anyhow::anyhow!("Size lots out of range: {}", quantity))?,
+                total: Decimal::ZERO,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if is_bids {
+        levels.sort_by_key(|level| Reverse(level.price)); // 买单降序
+    } else {
+        levels.sort_by_key(|level| level.price); // 卖单升序
+    }
+
+    calculate_totals(&mut levels);
+    Ok(levels)
+}
+
+/// 从根节点遍历 slab 的临界位树，返回所有叶子的 (价格 lots, 数量 lots)
+///
+/// 叶子的 key 高 64 位为价格 lots，低 64 位为订单序号。
+fn slab_leaves(data: &[u8]) -> Result<Vec<(u64, u64)>> {
+    if data.len() < SLAB_HEAD_PADDING + SLAB_HEADER_SIZE + SLAB_TAIL_PADDING {
+        return Err(anyhow::anyhow!("Slab data too short"));
+    }
+    let data = &data[SLAB_HEAD_PADDING..data.len() - SLAB_TAIL_PADDING];
+    let root = read_u32(data, 8 + 20) as usize;
+    let leaf_count = read_u64(data, 8 + 24);
+    let nodes = &data[SLAB_HEADER_SIZE..];
+    let capacity = nodes.len() / SLAB_NODE_SIZE;
+
+    let mut leaves = Vec::new();
+    if leaf_count == 0 {
+        return Ok(leaves);
+    }
+    let mut stack = vec![root];
+    // 每个节点最多访问一次，防止损坏数据造成死循环
+    let mut visited = 0;
+    while let Some(index) = stack.pop() {
+        visited += 1;
+        if index >= capacity || visited > capacity {
+            return Err(anyhow::anyhow!("Invalid slab node index {}", index));
+        }
+        let node = &nodes[index * SLAB_NODE_SIZE..(index + 1) * SLAB_NODE_SIZE];
+        match read_u32(node, 0) {
+            NODE_TAG_INNER => {
+                stack.push(read_u32(node, 28) as usize);
+                stack.push(read_u32(node, 24) as usize);
+            }
+            NODE_TAG_LEAF => {
+                let key = u128::from_le_bytes(node[8..24].try_into()?);
+                leaves.push(((key >> 64) as u64, read_u64(node, 56)));
+            }
+            tag => return Err(anyhow::anyhow!("Unexpected slab node tag {}", tag)),
+        }
+    }
+    if leaves.len() as u64 != leaf_count {
+        return Err(anyhow::anyhow!(
+            "Slab leaf count mismatch: header {}, found {}",
+            leaf_count,
+            leaves.len()
+        ));
+    }
+    Ok(leaves)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn calculate_totals(levels: &mut [Level]) {
+    let mut running_total = Decimal::ZERO;
+    for level in levels.iter_mut() {
+        running_total += level.size;
+        level.total = running_total;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// SOL/USDC: base lot 0.001 SOL，quote lot 0.00001 USDC，价格 lots 步长 0.01
+    const SCALE: BookScale = BookScale {
+        base_lot_size: 1_000_000,
+        quote_lot_size: 10,
+        base_decimals: 9,
+        quote_decimals: 6,
+    };
+
+    enum Node {
+        Inner(u32, u32),
+        Leaf(u64, u64), // 价格 lots, 数量 lots
+        Free,
+    }
+
+    fn slab(root: u32, leaf_count: u64, nodes: &[Node]) -> Vec<u8> {
+        let mut data = b"serum".to_vec();
+        data.extend_from_slice(&0u64.to_le_bytes()); // account_flags
+        data.extend_from_slice(&(nodes.len() as u64).to_le_bytes()); // bump_index
+        data.extend_from_slice(&0u64.to_le_bytes()); // free_list_len
+        data.extend_from_slice(&0u32.to_le_bytes()); // free_list_head
+        data.extend_from_slice(&root.to_le_bytes());
+        data.extend_from_slice(&leaf_count.to_le_bytes());
+        for (i, node) in nodes.iter().enumerate() {
+            let mut bytes = [0u8; SLAB_NODE_SIZE];
+            match node {
+                Node::Inner(left, right) => {
+                    bytes[0..4].copy_from_slice(&NODE_TAG_INNER.to_le_bytes());
+                    bytes[24..28].copy_from_slice(&left.to_le_bytes());
+                    bytes[28..32].copy_from_slice(&right.to_le_bytes());
+                }
+                Node::Leaf(price_lots, quantity) => {
+                    let key = ((*price_lots as u128) << 64) | i as u128;
+                    bytes[0..4].copy_from_slice(&NODE_TAG_LEAF.to_le_bytes());
+                    bytes[8..24].copy_from_slice(&key.to_le_bytes());
+                    bytes[56..64].copy_from_slice(&quantity.to_le_bytes());
+                }
+                Node::Free => bytes[0..4].copy_from_slice(&3u32.to_le_bytes()),
+            }
+            data.extend_from_slice(&bytes);
+        }
+        data.extend_from_slice(b"padding");
+        data
+    }
+
+    fn book() -> Vec<u8> {
+        slab(
+            0,
+            3,
+            &[
+                Node::Inner(1, 2),
+                Node::Leaf(2051, 2),
+                Node::Inner(3, 4),
+                Node::Leaf(2050, 5),
+                Node::Leaf(2050, 1),
+                // 已释放的节点不在树上，不应被读出
+                Node::Free,
+                Node::Leaf(9999, 7),
+            ],
+        )
+    }
+
+    #[test]
+    fn walks_leaves_and_merges_price_levels() {
+        let bids = decode_book_side(&book(), true, &SCALE).unwrap();
+        assert_eq!(
+            bids,
+            vec![
+                Level {
+                    price: Decimal::new(2051, 2),
+                    size: Decimal::new(2, 3),
+                    total: Decimal::new(2, 3),
+                },
+                Level {
+                    price: Decimal::new(2050, 2),
+                    size: Decimal::new(6, 3),
+                    total: Decimal::new(8, 3),
+                },
+            ]
+        );
+
+        let asks = decode_book_side(&book(), false, &SCALE).unwrap();
+        assert_eq!(asks[0].price, Decimal::new(2050, 2));
+    }
+
+    #[test]
+    fn rejects_malformed_slabs() {
+        assert!(decode_book_side(&[0u8; 20], true, &SCALE).is_err());
+        // 头部叶子数与树上叶子数不符
+        let mismatch = slab(
+            0,
+            3,
+            &[Node::Inner(1, 2), Node::Leaf(1, 1), Node::Leaf(2, 1)],
+        );
+        assert!(decode_book_side(&mismatch, true, &SCALE).is_err());
+        // 子节点越界
+        let dangling = slab(0, 1, &[Node::Inner(1, 9), Node::Leaf(1, 1)]);
+        assert!(decode_book_side(&dangling, true, &SCALE).is_err());
+        assert!(decode_book_side(&slab(0, 0, &[]), true, &SCALE)
+            .unwrap()
+            .is_empty());
+    }
+}
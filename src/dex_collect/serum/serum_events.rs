@@ -0,0 +1,241 @@
+use anyhow::Result;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::dex_collect::fixed::{self, Decimal};
+use crate::dex_collect::stream::update::BookSide;
+
+/// 事件队列布局: 5 字节 "serum" 头 + 32 字节队列头 + 88 字节事件环形缓冲 + 7 字节尾部
+const ACCOUNT_HEAD_PADDING: usize = 5;
+const ACCOUNT_TAIL_PADDING: usize = 7;
+const HEADER_SIZE: usize = 32;
+const EVENT_SIZE: usize = 88;
+
+const EVENT_FLAG_FILL: u8 = 0x01;
+const EVENT_FLAG_BID: u8 = 0x04;
+const EVENT_FLAG_MAKER: u8 = 0x08;
+
+/// 事件队列头
+#[derive(Debug, Clone, Copy)]
+pub struct EventQueueHeader {
+    pub account_flags: u64,
+    pub head: u64,
+    pub count: u64,
+    pub seq_num: u64, // 下一个事件的序号
+}
+
+/// 成交事件，数量为原始精度
+///
+/// 每笔撮合会产生 maker 和 taker 两条事件，统计成交量时只取 maker 一侧。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FillEvent {
+    pub seq_num: u64,
+    pub side: BookSide, // 该事件所属订单的方向
+    pub maker: bool,
+    pub owner_slot: u8,
+    pub fee_tier: u8,
+    pub native_qty_released: u64,
+    pub native_qty_paid: u64,
+    pub native_fee_or_rebate: u64,
+    pub order_id: u128,
+    pub owner: Pubkey, // open orders 账户
+    pub client_order_id: u64,
+}
+
+impl FillEvent {
+    /// 成交的 base 原始数量
+    pub fn native_base(&self) -> u64 {
+        match self.side {
+            BookSide::Bids => self.native_qty_released,
+            BookSide::Asks => self.native_qty_paid,
+        }
+    }
+
+    /// 扣除手续费/返佣前的 quote 原始数量
+    pub fn native_quote(&self) -> u64 {
+        let fee = self.native_fee_or_rebate;
+        match (self.side, self.maker) {
+            (BookSide::Bids, true) => self.native_qty_paid.saturating_add(fee),
+            (BookSide::Bids, false) => self.native_qty_paid.saturating_sub(fee),
+            (BookSide::Asks, true) => self.native_qty_released.saturating_sub(fee),
+            (BookSide::Asks, false) => self.native_qty_released.saturating_add(fee),
+        }
+    }
+
+    /// 成交数量 (base)
+    pub fn size(&self, base_decimals: u32) -> Option<Decimal> {
+        fixed::from_raw(self.native_base(), base_decimals)
+    }
+
+    /// 成交价格，base 数量为 0 时返回 None
+    pub fn price(&self, base_decimals: u32, quote_decimals: u32) -> Option<Decimal> {
+        let base = self.size(base_decimals)?;
+        if base.is_zero() {
+            return None;
+        }
+        fixed::from_raw(self.native_quote(), quote_decimals)?.checked_div(base)
+    }
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+/// 拆分队列头和事件环形缓冲
+fn split_queue(data: &[u8]) -> Result<(EventQueueHeader, &[u8])> {
+    if data.len() < ACCOUNT_HEAD_PADDING + HEADER_SIZE + ACCOUNT_TAIL_PADDING {
+        return Err(anyhow::anyhow!("Event queue data too short"));
+    }
+    let data = &data[ACCOUNT_HEAD_PADDING..data.len() - ACCOUNT_TAIL_PADDING];
+    let header = EventQueueHeader {
+        account_flags: read_u64(data, 0),
+        head: read_u64(data, 8),
+        count: read_u64(data, 16),
+        seq_num: read_u64(data, 24),
+    };
+    Ok((header, &data[HEADER_SIZE..]))
+}
+
+/// 待 crank 事件所属的 open orders 账户，按队列顺序去重
+///
+/// 只看前 `limit` 条未消费事件 (包括撤单产生的 Out 事件)。
+pub fn pending_owners(data: &[u8], limit: usize) -> Result<(EventQueueHeader, Vec<Pubkey>)> {
+    let (header, events) = split_queue(data)?;
+    let capacity = (events.len() / EVENT_SIZE) as u64;
+    let mut owners: Vec<Pubkey> = Vec::new();
+    if capacity == 0 {
+        return Ok((header, owners));
+    }
+    for k in 0..header.count.min(limit as u64) {
+        let index = ((header.head + k) % capacity) as usize;
+        let event = &events[index * EVENT_SIZE..(index + 1) * EVENT_SIZE];
+        let owner = Pubkey::try_from(&event[48..80])?;
+        if !owners.contains(&owner) {
+            owners.push(owner);
+        }
+    }
+    Ok((header, owners))
+}
+
+/// 解析事件队列，返回队列头和缓冲区中仍可读到的成交事件 (按序号升序)
+///
+/// 已被 crank 消费的事件仍留在环形缓冲中，按 seq_num 倒推序号后一并返回，
+/// 调用方按序号去重即可拿到连续的成交记录。
+pub fn decode_event_queue(data: &[u8]) -> Result<(EventQueueHeader, Vec<FillEvent>)> {
+    let (header, events) = split_queue(data)?;
+    let capacity = (events.len() / EVENT_SIZE) as u64;
+    if capacity == 0 {
+        return Ok((header, Vec::new()));
+    }
+
+    // 最新事件位于 head + count - 1，序号为 seq_num - 1
+    let newest = (header.head + header.count + capacity - 1) % capacity;
+    let available = header.seq_num.min(capacity);
+    let mut fills = Vec::new();
+    for k in 0..available {
+        let index = ((newest + capacity - k) % capacity) as usize;
+        let seq_num = header.seq_num - 1 - k;
+        if let Some(fill) = decode_fill(
+            &events[index * EVENT_SIZE..(index + 1) * EVENT_SIZE],
+            seq_num,
+        ) {
+            fills.push(fill);
+        }
+    }
+    fills.reverse();
+    Ok((header, fills))
+}
+
+fn decode_fill(event: &[u8], seq_num: u64) -> Option<FillEvent> {
+    let flags = event[0];
+    if flags & EVENT_FLAG_FILL == 0 {
+        return None;
+    }
+    let native_qty_released = read_u64(event, 8);
+    let native_qty_paid = read_u64(event, 16);
+    if native_qty_released == 0 && native_qty_paid == 0 {
+        return None;
+    }
+    Some(FillEvent {
+        seq_num,
+        side: if flags & EVENT_FLAG_BID != 0 {
+            BookSide::Bids
+        } else {
+            BookSide::Asks
+        },
+        maker: flags & EVENT_FLAG_MAKER != 0,
+        owner_slot: event[1],
+        fee_tier: event[2],
+        native_qty_released,
+        native_qty_paid,
+        native_fee_or_rebate: read_u64(event, 24),
+        order_id: u128::from_le_bytes(event[32..48].try_into().ok()?),
+        owner: Pubkey::try_from(&event[48..80]).ok()?,
+        client_order_id: read_u64(event, 80),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAPACITY: usize = 4;
+
+    /// 容量为 4 的事件队列，`slots[i]` 为缓冲区第 i 格事件的序号
+    fn queue(head: u64, count: u64, seq_num: u64, slots: [u64; CAPACITY]) -> Vec<u8> {
+        let mut data = vec![0u8; ACCOUNT_HEAD_PADDING];
+        for value in [0, head, count, seq_num] {
+            data.extend_from_slice(&u64::to_le_bytes(value));
+        }
+        for seq in slots {
+            let mut event = [0u8; EVENT_SIZE];
+            event[0] = EVENT_FLAG_FILL | EVENT_FLAG_BID | EVENT_FLAG_MAKER;
+            event[8..16].copy_from_slice(&1_000u64.to_le_bytes());
+            event[16..24].copy_from_slice(&2_000u64.to_le_bytes());
+            event[24..32].copy_from_slice(&10u64.to_le_bytes());
+            event[48..80].copy_from_slice(&[seq as u8; 32]);
+            event[80..88].copy_from_slice(&seq.to_le_bytes());
+            data.extend_from_slice(&event);
+        }
+        data.extend_from_slice(&[0u8; ACCOUNT_TAIL_PADDING]);
+        data
+    }
+
+    #[test]
+    fn decodes_wrapped_ring_in_sequence_order() {
+        // 最新事件 (序号 9) 回绕到缓冲区第 0 格
+        let data = queue(3, 2, 10, [9, 6, 7, 8]);
+        let (header, fills) = decode_event_queue(&data).unwrap();
+        assert_eq!(header.seq_num, 10);
+        assert_eq!(
+            fills.iter().map(|f| f.seq_num).collect::<Vec<_>>(),
+            [6, 7, 8, 9]
+        );
+        assert!(fills.iter().all(|f| f.client_order_id == f.seq_num));
+
+        let fill = &fills[0];
+        assert_eq!(fill.side, BookSide::Bids);
+        assert!(fill.maker);
+        assert_eq!(fill.native_base(), 1_000);
+        // maker 买单返佣加回 quote
+        assert_eq!(fill.native_quote(), 2_010);
+        assert_eq!(fill.price(3, 6), Some(Decimal::new(201, 5)));
+
+        // 未消费的只有序号 8 和 9
+        let (_, owners) = pending_owners(&data, 10).unwrap();
+        assert_eq!(
+            owners,
+            [
+                Pubkey::new_from_array([8; 32]),
+                Pubkey::new_from_array([9; 32])
+            ]
+        );
+    }
+
+    #[test]
+    fn young_queue_only_returns_written_events() {
+        let data = queue(0, 2, 2, [0, 1, 0, 0]);
+        let (_, fills) = decode_event_queue(&data).unwrap();
+        assert_eq!(fills.iter().map(|f| f.seq_num).collect::<Vec<_>>(), [0, 1]);
+        assert!(decode_event_queue(&[0u8; 10]).is_err());
+    }
+}
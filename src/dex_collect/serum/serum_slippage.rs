@@ -0,0 +1,268 @@
+use serde::{Deserialize, Serialize};
+
+use crate::dex_collect::fixed::{Decimal, BPS};
+use crate::dex_collect::serum::serum_depth::{Level, MarketDepth};
+
+/// 交易方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TradeSide {
+    Buy,  // 吃卖单
+    Sell, // 吃买单
+}
+
+/// 成交目标数量
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FillAmount {
+    /// 买入或卖出的 base 数量
+    Base(Decimal),
+    /// 买入时花费的 quote (含手续费)，卖出时到手的 quote (扣除手续费)
+    Quote(Decimal),
+}
+
+/// 按当前深度吃单的估算结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FillEstimate {
+    pub side: TradeSide,
+    pub requested: FillAmount,
+    pub filled_base: Decimal,
+    pub filled_quote: Decimal, // 不含手续费的成交额
+    pub fee: Decimal,          // quote 计价
+    pub net_quote: Decimal,    // 买入为总花费，卖出为到手金额
+    pub best_price: Option<Decimal>,
+    pub avg_price: Option<Decimal>,       // 不含手续费的均价
+    pub effective_price: Option<Decimal>, // 含手续费的均价
+    pub worst_price: Option<Decimal>,
+    pub levels_consumed: usize,
+    pub unfilled: Decimal,     // 深度不足时未成交的数量，单位与 requested 相同
+    pub slippage_bps: Decimal, // 均价相对最优价的滑点
+}
+
+impl FillEstimate {
+    pub fn is_complete(&self) -> bool {
+        self.unfilled.is_zero()
+    }
+}
+
+impl MarketDepth {
+    /// 吃单方向对应的盘口
+    fn taker_levels(&self, side: TradeSide) -> &[Level] {
+        match side {
+            TradeSide::Buy => &self.asks,
+            TradeSide::Sell => &self.bids,
+        }
+    }
+
+    /// 估算按当前深度吃单的均价、最差价、手续费和消耗档位
+    pub fn estimate_fill(
+        &self,
+        side: TradeSide,
+        amount: FillAmount,
+        fee_bps: Decimal,
+    ) -> FillEstimate {
+        let fee_rate = fee_bps / BPS;
+        let levels = self.taker_levels(side);
+
+        // quote 目标换算成不含手续费的成交额
+        let (mut remaining_base, mut remaining_quote) = match amount {
+            FillAmount::Base(base) => (Some(base.max(Decimal::ZERO)), None),
+            FillAmount::Quote(quote) => {
+                let quote = quote.max(Decimal::ZERO);
+                let gross = match side {
+                    // 返佣达到 100% 及以上的费率没有意义，按无法成交处理
+                    TradeSide::Buy if fee_rate > Decimal::NEGATIVE_ONE => {
+                        quote / (Decimal::ONE + fee_rate)
+                    }
+                    TradeSide::Buy => Decimal::ZERO,
+                    TradeSide::Sell if fee_rate < Decimal::ONE => quote / (Decimal::ONE - fee_rate),
+                    TradeSide::Sell => Decimal::ZERO,
+                };
+                (None, Some(gross))
+            }
+        };
+
+        let mut filled_base = Decimal::ZERO;
+        let mut filled_quote = Decimal::ZERO;
+        let mut worst_price = None;
+        let mut levels_consumed = 0;
+        for level in levels {
+            if level.price <= Decimal::ZERO || level.size <= Decimal::ZERO {
+                continue;
+            }
+            let level_quote = level.price * level.size;
+            let (base, quote) = match (remaining_base, remaining_quote) {
+                (Some(rest), _) if rest.is_zero() => break,
+                (_, Some(rest)) if rest.is_zero() => break,
+                (Some(rest), _) if rest < level.size => (rest, rest * level.price),
+                (_, Some(rest)) if rest < level_quote => (rest / level.price, rest),
+                _ => (level.size, level_quote),
+            };
+            filled_base += base;
+            filled_quote += quote;
+            remaining_base = remaining_base.map(|r| r - base);
+            remaining_quote = remaining_quote.map(|r| r - quote);
+            worst_price = Some(level.price);
+            levels_consumed += 1;
+        }
+
+        let fee = filled_quote * fee_rate;
+        let net_quote = match side {
+            TradeSide::Buy => filled_quote + fee,
+            TradeSide::Sell => filled_quote - fee,
+        };
+        let unfilled = match amount {
+            FillAmount::Base(_) => remaining_base.unwrap_or_default(),
+            // 按目标口径 (含/扣手续费) 折算未成交的 quote
+            FillAmount::Quote(quote) => (quote.max(Decimal::ZERO) - net_quote).max(Decimal::ZERO),
+        };
+
+        let best_price = levels.first().map(|l| l.price);
+        let avg_price = (!filled_base.is_zero()).then(|| filled_quote / filled_base);
+        let effective_price = (!filled_base.is_zero()).then(|| net_quote / filled_base);
+        let slippage_bps = match (best_price, avg_price) {
+            (Some(best), Some(avg)) if !best.is_zero() => slippage_bps(side, best, avg),
+            _ => Decimal::ZERO,
+        };
+
+        FillEstimate {
+            side,
+            requested: amount,
+            filled_base,
+            filled_quote,
+            fee,
+            net_quote,
+            best_price,
+            avg_price,
+            effective_price,
+            worst_price,
+            levels_consumed,
+            unfilled,
+            slippage_bps,
+        }
+    }
+
+    /// 均价滑点不超过 `max_slippage_bps` 时可成交的最大 base 数量 (不含手续费)
+    pub fn max_size_within_slippage(&self, side: TradeSide, max_slippage_bps: Decimal) -> Decimal {
+        let levels = self.taker_levels(side);
+        let Some(best) = levels.first().map(|l| l.price) else {
+            return Decimal::ZERO;
+        };
+        let tolerance = max_slippage_bps.max(Decimal::ZERO) / BPS;
+        // 均价上/下限
+        let limit = match side {
+            TradeSide::Buy => best * (Decimal::ONE + tolerance),
+            TradeSide::Sell => best * (Decimal::ONE - tolerance),
+        };
+
+        let mut base = Decimal::ZERO;
+        let mut quote = Decimal::ZERO;
+        for level in levels {
+            if level.size <= Decimal::ZERO {
+                continue;
+            }
+            let within = match side {
+                TradeSide::Buy => level.price <= limit,
+                TradeSide::Sell => level.price >= limit,
+            };
+            if within {
+                base += level.size;
+                quote += level.price * level.size;
+                continue;
+            }
+
+            // 该档只能吃一部分: (quote + p*x) / (base + x) 恰好等于限价
+            let partial = match side {
+                TradeSide::Buy => (limit * base - quote) / (level.price - limit),
+                TradeSide::Sell => (quote - limit * base) / (limit - level.price),
+            };
+            base += partial.max(Decimal::ZERO).min(level.size);
+            break;
+        }
+        base
+    }
+}
+
+/// 均价相对最优价的不利偏离 (bps)
+fn slippage_bps(side: TradeSide, best: Decimal, avg: Decimal) -> Decimal {
+    let diff = match side {
+        TradeSide::Buy => avg - best,
+        TradeSide::Sell => best - avg,
+    };
+    diff / best * BPS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dex_collect::stamp::SnapshotStamp;
+
+    fn level(price: i64, size: i64) -> Level {
+        Level {
+            price: Decimal::from(price),
+            size: Decimal::from(size),
+            total: Decimal::from(size),
+        }
+    }
+
+    fn depth() -> MarketDepth {
+        MarketDepth {
+            bids: vec![level(99, 1), level(98, 2)],
+            asks: vec![level(100, 1), level(102, 2)],
+            spread: Decimal::ONE,
+            total_bid_size: Decimal::from(3),
+            total_ask_size: Decimal::from(3),
+            stamp: SnapshotStamp::new(1, "test"),
+        }
+    }
+
+    #[test]
+    fn buy_walks_levels_and_charges_fee() {
+        let fill = depth().estimate_fill(
+            TradeSide::Buy,
+            FillAmount::Base(Decimal::TWO),
+            BPS / Decimal::from(100),
+        );
+        assert!(fill.is_complete());
+        assert_eq!(fill.filled_quote, Decimal::from(202));
+        assert_eq!(fill.fee, Decimal::new(202, 2));
+        assert_eq!(fill.net_quote, Decimal::new(20402, 2));
+        assert_eq!(fill.levels_consumed, 2);
+        assert_eq!(fill.worst_price, Some(Decimal::from(102)));
+        assert_eq!(fill.slippage_bps, Decimal::from(100));
+    }
+
+    #[test]
+    fn quote_target_is_fee_inclusive_and_reports_unfilled() {
+        let depth = depth();
+        let sell = depth.estimate_fill(
+            TradeSide::Sell,
+            FillAmount::Quote(Decimal::from(99)),
+            Decimal::ZERO,
+        );
+        assert!(sell.is_complete());
+        assert_eq!(sell.filled_base, Decimal::ONE);
+
+        let buy = depth.estimate_fill(
+            TradeSide::Buy,
+            FillAmount::Base(Decimal::from(5)),
+            Decimal::ZERO,
+        );
+        assert_eq!(buy.filled_base, Decimal::from(3));
+        assert_eq!(buy.unfilled, Decimal::TWO);
+
+        // 返佣达到 100% 时不成交
+        let rebate =
+            depth.estimate_fill(TradeSide::Buy, FillAmount::Quote(Decimal::from(100)), -BPS);
+        assert!(rebate.filled_base.is_zero());
+    }
+
+    #[test]
+    fn max_size_within_slippage_takes_partial_level() {
+        // 均价 (100 + 102x) / (1 + x) = 101 时 x = 1
+        let size = depth().max_size_within_slippage(TradeSide::Buy, Decimal::from(100));
+        assert_eq!(size, Decimal::TWO);
+        assert_eq!(
+            depth().max_size_within_slippage(TradeSide::Sell, Decimal::ZERO),
+            Decimal::ONE
+        );
+    }
+}
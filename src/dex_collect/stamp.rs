@@ -0,0 +1,36 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 快照时间戳
+///
+/// 记录链上数据有效的 slot 和出块时间，以及本地接收时间和数据来源，
+/// 跨市场比较和历史回放时按链上时间对齐，而不是按处理时间。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotStamp {
+    pub slot: u64,                         // 上下文 slot
+    pub block_time: Option<DateTime<Utc>>, // 出块时间 (已知时)
+    pub received_at: DateTime<Utc>,        // 本地接收时间
+    pub source: String,                    // 数据来源节点
+}
+
+impl SnapshotStamp {
+    /// 以当前时间作为接收时间创建
+    pub fn new(slot: u64, source: &str) -> Self {
+        Self {
+            slot,
+            block_time: None,
+            received_at: Utc::now(),
+            source: source.to_string(),
+        }
+    }
+
+    pub fn with_block_time(mut self, block_time: Option<DateTime<Utc>>) -> Self {
+        self.block_time = block_time;
+        self
+    }
+
+    /// 从出块到本地接收的延迟
+    pub fn latency(&self) -> Option<chrono::Duration> {
+        self.block_time.map(|t| self.received_at - t)
+    }
+}
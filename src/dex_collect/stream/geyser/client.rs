@@ -0,0 +1,235 @@
+use anyhow::Result;
+use futures::{channel::mpsc as request_mpsc, SinkExt, StreamExt};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::{collections::HashMap, time::Duration};
+use tokio::sync::mpsc;
+use tonic::{
+    codec::ProstCodec, codegen::http::uri::PathAndQuery, metadata::MetadataValue,
+    transport::Endpoint,
+};
+
+use crate::dex_collect::stamp::SnapshotStamp;
+use crate::dex_collect::stream::geyser::proto::{
+    subscribe_update::UpdateOneof, CommitmentLevel, SubscribeRequest,
+    SubscribeRequestFilterAccounts, SubscribeRequestFilterTransactions, SubscribeRequestPing,
+    SubscribeUpdate, SubscribeUpdateTransaction, SUBSCRIBE_PATH,
+};
+use crate::dex_collect::stream::update::{MarketUpdate, MarketUpdateStream, UpdateDecoder};
+
+/// 首次重连等待，成功订阅后重置为该值
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+
+/// Yellowstone gRPC 接入配置
+#[derive(Debug, Clone)]
+pub struct GeyserConfig {
+    pub endpoint: String,
+    pub x_token: Option<String>, // 服务商鉴权 token
+    pub commitment: CommitmentLevel,
+    pub program_ids: Vec<Pubkey>, // 按 owner 订阅账户，并订阅涉及这些程序的交易
+    pub include_transactions: bool,
+    pub forward_untracked: bool, // 是否转发解码器未跟踪的账户
+    pub connect_timeout: Duration,
+    pub max_reconnect_delay: Duration,
+    pub channel_size: usize,
+}
+
+impl GeyserConfig {
+    pub fn new(endpoint: &str) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+            x_token: None,
+            commitment: CommitmentLevel::Processed,
+            program_ids: Vec::new(),
+            include_transactions: false,
+            forward_untracked: false,
+            connect_timeout: Duration::from_secs(10),
+            max_reconnect_delay: Duration::from_secs(30),
+            channel_size: 4096,
+        }
+    }
+}
+
+/// 通过 Yellowstone gRPC 订阅账户和交易，返回与 websocket 后端相同的更新流
+///
+/// 账户按解码器中跟踪的地址和配置的程序 owner 订阅，数据交给同一个
+/// [`UpdateDecoder`] 解码；断线后按指数退避重连并重新发送订阅请求。
+pub fn subscribe(decoder: UpdateDecoder, config: GeyserConfig) -> MarketUpdateStream {
+    let (tx, rx) = mpsc::channel(config.channel_size);
+    let task = tokio::spawn(run(decoder, config, tx));
+    MarketUpdateStream::new(rx, task)
+}
+
+/// 构造订阅请求
+pub fn build_request(decoder: &UpdateDecoder, config: &GeyserConfig) -> SubscribeRequest {
+    let mut accounts = HashMap::new();
+    let tracked: Vec<String> = decoder.accounts().iter().map(|k| k.to_string()).collect();
+    if !tracked.is_empty() {
+        accounts.insert(
+            "tracked".to_string(),
+            SubscribeRequestFilterAccounts {
+                account: tracked,
+                owner: Vec::new(),
+            },
+        );
+    }
+    let programs: Vec<String> = config.program_ids.iter().map(|k| k.to_string()).collect();
+    if !programs.is_empty() {
+        accounts.insert(
+            "programs".to_string(),
+            SubscribeRequestFilterAccounts {
+                account: Vec::new(),
+                owner: programs.clone(),
+            },
+        );
+    }
+
+    let mut transactions = HashMap::new();
+    if config.include_transactions && !programs.is_empty() {
+        transactions.insert(
+            "programs".to_string(),
+            SubscribeRequestFilterTransactions {
+                vote: Some(false),
+                failed: Some(false),
+                account_include: programs,
+                ..SubscribeRequestFilterTransactions::default()
+            },
+        );
+    }
+
+    SubscribeRequest {
+        accounts,
+        transactions,
+        commitment: Some(config.commitment as i32),
+        ..SubscribeRequest::default()
+    }
+}
+
+async fn run(mut decoder: UpdateDecoder, config: GeyserConfig, tx: mpsc::Sender<MarketUpdate>) {
+    let mut reconnect_delay = INITIAL_RECONNECT_DELAY;
+    loop {
+        let mut subscribed = false;
+        match run_stream(&mut decoder, &config, &tx, &mut subscribed).await {
+            Ok(()) => return, // 消费者已关闭
+            Err(e) => log::warn!("gRPC 订阅中断 ({}): {}", config.endpoint, e),
+        }
+        if subscribed {
+            reconnect_delay = INITIAL_RECONNECT_DELAY;
+        }
+        if tx.is_closed() {
+            return;
+        }
+        tokio::time::sleep(reconnect_delay).await;
+        reconnect_delay = (reconnect_delay * 2).min(config.max_reconnect_delay);
+    }
+}
+
+/// 建立一次 gRPC 连接并持续转发更新
+///
+/// 消费者关闭时返回 Ok，连接断开时返回 Err 以触发重连；订阅建立后把 `subscribed` 置为 true。
+async fn run_stream(
+    decoder: &mut UpdateDecoder,
+    config: &GeyserConfig,
+    tx: &mpsc::Sender<MarketUpdate>,
+    subscribed: &mut bool,
+) -> Result<()> {
+    let channel = Endpoint::from_shared(config.endpoint.clone())?
+        .connect_timeout(config.connect_timeout)
+        .connect()
+        .await?;
+    let mut grpc = tonic::client::Grpc::new(channel);
+    grpc.ready().await?;
+
+    // 请求流保持打开，用于回应服务端的 ping
+    let (mut requests, request_rx) = request_mpsc::unbounded::<SubscribeRequest>();
+    requests.send(build_request(decoder, config)).await?;
+
+    let mut request = tonic::Request::new(request_rx);
+    if let Some(token) = &config.x_token {
+        request
+            .metadata_mut()
+            .insert("x-token", MetadataValue::try_from(token.as_str())?);
+    }
+    let mut updates = grpc
+        .streaming(
+            request,
+            PathAndQuery::from_static(SUBSCRIBE_PATH),
+            ProstCodec::<SubscribeRequest, SubscribeUpdate>::default(),
+        )
+        .await?
+        .into_inner();
+    *subscribed = true;
+    log::info!("gRPC 已订阅 ({})", config.endpoint);
+
+    while let Some(update) = updates.next().await {
+        let update = match update?.update_oneof {
+            Some(update) => update,
+            None => continue,
+        };
+        let market_update = match update {
+            UpdateOneof::Account(account) => {
+                let stamp = SnapshotStamp::new(account.slot, &config.endpoint);
+                let Some(info) = account.account else {
+                    continue;
+                };
+                let (Ok(pubkey), Ok(owner)) = (
+                    Pubkey::try_from(info.pubkey.as_slice()),
+                    Pubkey::try_from(info.owner.as_slice()),
+                ) else {
+                    continue;
+                };
+                if !config.forward_untracked && !decoder.is_tracked(&pubkey) {
+                    continue;
+                }
+                decoder.decode(&pubkey, &owner, &info.data, stamp)
+            }
+            UpdateOneof::Transaction(transaction) => {
+                decode_transaction(transaction, &config.endpoint)
+            }
+            UpdateOneof::Ping(_) => {
+                requests
+                    .send(SubscribeRequest {
+                        ping: Some(SubscribeRequestPing { id: 1 }),
+                        ..SubscribeRequest::default()
+                    })
+                    .await?;
+                None
+            }
+            UpdateOneof::Slot(_) | UpdateOneof::Pong(_) => None,
+        };
+
+        if let Some(market_update) = market_update {
+            if tx.send(market_update).await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+
+    Err(anyhow::anyhow!("gRPC stream closed"))
+}
+
+fn decode_transaction(update: SubscribeUpdateTransaction, source: &str) -> Option<MarketUpdate> {
+    let info = update.transaction?;
+    let signature = Signature::try_from(info.signature.as_slice()).ok()?;
+    let accounts = info
+        .transaction
+        .and_then(|t| t.message)
+        .map(|m| {
+            m.account_keys
+                .iter()
+                .filter_map(|k| Pubkey::try_from(k.as_slice()).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    let (logs, failed) = info
+        .meta
+        .map(|meta| (meta.log_messages, meta.err.is_some()))
+        .unwrap_or_default();
+
+    Some(MarketUpdate::Transaction {
+        signature,
+        accounts,
+        logs,
+        failed,
+        stamp: SnapshotStamp::new(update.slot, source),
+    })
+}
@@ -0,0 +1,257 @@
+//! 本地 Yellowstone gRPC 模拟服务
+//!
+//! 只实现 Subscribe 方法：记录收到的订阅请求，并把 [`MockGeyser::push`]
+//! 推入的更新广播给所有订阅者，用于在没有真实节点时离线调试 gRPC 接入。
+
+use anyhow::Result;
+use futures::{Stream, StreamExt};
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+use tokio::{net::TcpListener, sync::broadcast, task::JoinHandle};
+use tonic::{
+    body::BoxBody,
+    codec::ProstCodec,
+    codegen::{empty_body, http, Body, BoxFuture, StdError},
+    server::{Grpc, NamedService, StreamingService},
+    transport::Server,
+    Status, Streaming,
+};
+
+use crate::dex_collect::stream::geyser::proto::{
+    subscribe_update::UpdateOneof, SubscribeRequest, SubscribeUpdate, SubscribeUpdateAccount,
+    SubscribeUpdateAccountInfo, SubscribeUpdatePing, SUBSCRIBE_PATH,
+};
+
+type UpdateStream = Pin<Box<dyn Stream<Item = Result<SubscribeUpdate, Status>> + Send>>;
+
+/// Geyser 模拟服务
+#[derive(Clone)]
+pub struct MockGeyser {
+    updates: broadcast::Sender<SubscribeUpdate>,
+    requests: Arc<Mutex<Vec<SubscribeRequest>>>,
+}
+
+impl MockGeyser {
+    pub fn new() -> Self {
+        let (updates, _) = broadcast::channel(1024);
+        Self {
+            updates,
+            requests: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// 向所有订阅者推送一条更新，返回收到的订阅者数量
+    pub fn push(&self, update: SubscribeUpdate) -> usize {
+        self.updates.send(update).unwrap_or(0)
+    }
+
+    /// 推送账户更新
+    pub fn push_account(&self, pubkey: &Pubkey, owner: &Pubkey, data: Vec<u8>, slot: u64) -> usize {
+        self.push(SubscribeUpdate {
+            filters: Vec::new(),
+            update_oneof: Some(UpdateOneof::Account(SubscribeUpdateAccount {
+                account: Some(SubscribeUpdateAccountInfo {
+                    pubkey: pubkey.to_bytes().to_vec(),
+                    owner: owner.to_bytes().to_vec(),
+                    data,
+                    ..SubscribeUpdateAccountInfo::default()
+                }),
+                slot,
+                is_startup: false,
+            })),
+        })
+    }
+
+    /// 推送 ping，客户端应回复 ping 请求
+    pub fn push_ping(&self) -> usize {
+        self.push(SubscribeUpdate {
+            filters: Vec::new(),
+            update_oneof: Some(UpdateOneof::Ping(SubscribeUpdatePing {})),
+        })
+    }
+
+    /// 当前订阅者数量
+    pub fn subscribers(&self) -> usize {
+        self.updates.receiver_count()
+    }
+
+    /// 已收到的订阅请求 (包括 ping)
+    pub fn received_requests(&self) -> Vec<SubscribeRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// 在指定地址启动服务，端口为 0 时自动分配，返回实际地址
+    pub async fn spawn(&self, addr: SocketAddr) -> Result<(SocketAddr, JoinHandle<()>)> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let incoming = futures::stream::unfold(listener, |listener| async move {
+            let conn = listener.accept().await.map(|(stream, _)| stream);
+            Some((conn, listener))
+        });
+
+        let service = GeyserService(self.clone());
+        let task = tokio::spawn(async move {
+            if let Err(e) = Server::builder()
+                .add_service(service)
+                .serve_with_incoming(incoming)
+                .await
+            {
+                log::warn!("模拟 Geyser 服务退出: {}", e);
+            }
+        });
+        Ok((local_addr, task))
+    }
+}
+
+impl Default for MockGeyser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct SubscribeSvc(MockGeyser);
+
+impl StreamingService<SubscribeRequest> for SubscribeSvc {
+    type Response = SubscribeUpdate;
+    type ResponseStream = UpdateStream;
+    type Future = BoxFuture<tonic::Response<Self::ResponseStream>, Status>;
+
+    fn call(&mut self, request: tonic::Request<Streaming<SubscribeRequest>>) -> Self::Future {
+        let mock = self.0.clone();
+        Box::pin(async move {
+            // 先订阅广播，避免丢失客户端连上后立即推送的更新
+            let updates = mock.updates.subscribe();
+
+            let mut incoming = request.into_inner();
+            let requests = mock.requests.clone();
+            tokio::spawn(async move {
+                while let Some(Ok(request)) = incoming.next().await {
+                    requests.lock().unwrap().push(request);
+                }
+            });
+
+            let stream = futures::stream::unfold(updates, |mut updates| async move {
+                loop {
+                    match updates.recv().await {
+                        Ok(update) => return Some((Ok(update), updates)),
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            });
+            Ok(tonic::Response::new(Box::pin(stream) as UpdateStream))
+        })
+    }
+}
+
+#[derive(Clone)]
+struct GeyserService(MockGeyser);
+
+impl NamedService for GeyserService {
+    const NAME: &'static str = "geyser.Geyser";
+}
+
+impl<B> tonic::codegen::Service<http::Request<B>> for GeyserService
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        if req.uri().path() == SUBSCRIBE_PATH {
+            let svc = SubscribeSvc(self.0.clone());
+            return Box::pin(async move {
+                let mut grpc =
+                    Grpc::new(ProstCodec::<SubscribeUpdate, SubscribeRequest>::default());
+                Ok(grpc.streaming(svc, req).await)
+            });
+        }
+
+        // 其他方法返回 UNIMPLEMENTED
+        Box::pin(async move {
+            Ok(http::Response::builder()
+                .status(200)
+                .header("grpc-status", "12")
+                .header("content-type", "application/grpc")
+                .body(empty_body())
+                .unwrap())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dex_collect::stream::geyser::client::{self, GeyserConfig};
+    use crate::dex_collect::stream::update::{MarketUpdate, UpdateDecoder};
+    use std::time::Duration;
+
+    fn token_account(amount: u64) -> Vec<u8> {
+        let mut data = vec![0u8; 165];
+        data[64..72].copy_from_slice(&amount.to_le_bytes());
+        data
+    }
+
+    #[tokio::test]
+    async fn subscribe_decodes_accounts_and_answers_ping() -> Result<()> {
+        let mock = MockGeyser::new();
+        let (addr, _server) = mock.spawn("127.0.0.1:0".parse()?).await?;
+
+        let (pool, base_vault, quote_vault) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let mut decoder = UpdateDecoder::default();
+        decoder.track_pool(pool, base_vault, quote_vault);
+        let mut updates =
+            client::subscribe(decoder, GeyserConfig::new(&format!("http://{}", addr)));
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while mock.subscribers() == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await?;
+        mock.push_account(&base_vault, &anchor_spl::token::ID, token_account(100), 1);
+        mock.push_account(&quote_vault, &anchor_spl::token::ID, token_account(250), 1);
+
+        let update = tokio::time::timeout(Duration::from_secs(5), updates.next())
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("stream ended"))?;
+        let MarketUpdate::Pool {
+            pool: updated,
+            base_reserve,
+            quote_reserve,
+            ..
+        } = update
+        else {
+            panic!("unexpected update {:?}", update);
+        };
+        assert_eq!((updated, base_reserve, quote_reserve), (pool, 100, 250));
+
+        mock.push_ping();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !mock.received_requests().iter().any(|r| r.ping.is_some()) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await?;
+        // 第一条是订阅请求本身
+        assert!(mock.received_requests()[0].accounts.contains_key("tracked"));
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+//! Yellowstone `geyser.proto` 中用到的消息子集
+//!
+//! 字段编号与上游保持一致，未用到的字段和 oneof 分支省略，解码时会被忽略。
+
+use std::collections::HashMap;
+
+/// Subscribe 方法路径
+pub const SUBSCRIBE_PATH: &str = "/geyser.Geyser/Subscribe";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum CommitmentLevel {
+    Processed = 0,
+    Confirmed = 1,
+    Finalized = 2,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeRequest {
+    #[prost(map = "string, message", tag = "1")]
+    pub accounts: HashMap<String, SubscribeRequestFilterAccounts>,
+    #[prost(map = "string, message", tag = "2")]
+    pub slots: HashMap<String, SubscribeRequestFilterSlots>,
+    #[prost(map = "string, message", tag = "3")]
+    pub transactions: HashMap<String, SubscribeRequestFilterTransactions>,
+    #[prost(enumeration = "CommitmentLevel", optional, tag = "6")]
+    pub commitment: Option<i32>,
+    #[prost(message, optional, tag = "9")]
+    pub ping: Option<SubscribeRequestPing>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeRequestFilterAccounts {
+    #[prost(string, repeated, tag = "2")]
+    pub account: Vec<String>,
+    #[prost(string, repeated, tag = "3")]
+    pub owner: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeRequestFilterSlots {
+    #[prost(bool, optional, tag = "1")]
+    pub filter_by_commitment: Option<bool>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeRequestFilterTransactions {
+    #[prost(bool, optional, tag = "1")]
+    pub vote: Option<bool>,
+    #[prost(bool, optional, tag = "2")]
+    pub failed: Option<bool>,
+    #[prost(string, repeated, tag = "3")]
+    pub account_include: Vec<String>,
+    #[prost(string, repeated, tag = "4")]
+    pub account_exclude: Vec<String>,
+    #[prost(string, optional, tag = "5")]
+    pub signature: Option<String>,
+    #[prost(string, repeated, tag = "6")]
+    pub account_required: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeRequestPing {
+    #[prost(int32, tag = "1")]
+    pub id: i32,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeUpdate {
+    #[prost(string, repeated, tag = "1")]
+    pub filters: Vec<String>,
+    #[prost(oneof = "subscribe_update::UpdateOneof", tags = "2, 3, 4, 6, 9")]
+    pub update_oneof: Option<subscribe_update::UpdateOneof>,
+}
+
+pub mod subscribe_update {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum UpdateOneof {
+        #[prost(message, tag = "2")]
+        Account(super::SubscribeUpdateAccount),
+        #[prost(message, tag = "3")]
+        Slot(super::SubscribeUpdateSlot),
+        #[prost(message, tag = "4")]
+        Transaction(super::SubscribeUpdateTransaction),
+        #[prost(message, tag = "6")]
+        Ping(super::SubscribeUpdatePing),
+        #[prost(message, tag = "9")]
+        Pong(super::SubscribeUpdatePong),
+    }
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeUpdateAccount {
+    #[prost(message, optional, tag = "1")]
+    pub account: Option<SubscribeUpdateAccountInfo>,
+    #[prost(uint64, tag = "2")]
+    pub slot: u64,
+    #[prost(bool, tag = "3")]
+    pub is_startup: bool,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeUpdateAccountInfo {
+    #[prost(bytes = "vec", tag = "1")]
+    pub pubkey: Vec<u8>,
+    #[prost(uint64, tag = "2")]
+    pub lamports: u64,
+    #[prost(bytes = "vec", tag = "3")]
+    pub owner: Vec<u8>,
+    #[prost(bool, tag = "4")]
+    pub executable: bool,
+    #[prost(uint64, tag = "5")]
+    pub rent_epoch: u64,
+    #[prost(bytes = "vec", tag = "6")]
+    pub data: Vec<u8>,
+    #[prost(uint64, tag = "7")]
+    pub write_version: u64,
+    #[prost(bytes = "vec", optional, tag = "8")]
+    pub txn_signature: Option<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeUpdateSlot {
+    #[prost(uint64, tag = "1")]
+    pub slot: u64,
+    #[prost(uint64, optional, tag = "2")]
+    pub parent: Option<u64>,
+    #[prost(enumeration = "CommitmentLevel", tag = "3")]
+    pub status: i32,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeUpdateTransaction {
+    #[prost(message, optional, tag = "1")]
+    pub transaction: Option<SubscribeUpdateTransactionInfo>,
+    #[prost(uint64, tag = "2")]
+    pub slot: u64,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeUpdateTransactionInfo {
+    #[prost(bytes = "vec", tag = "1")]
+    pub signature: Vec<u8>,
+    #[prost(bool, tag = "2")]
+    pub is_vote: bool,
+    #[prost(message, optional, tag = "3")]
+    pub transaction: Option<Transaction>,
+    #[prost(message, optional, tag = "4")]
+    pub meta: Option<TransactionStatusMeta>,
+    #[prost(uint64, tag = "5")]
+    pub index: u64,
+}
+
+/// solana.storage.ConfirmedBlock.Transaction
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Transaction {
+    #[prost(bytes = "vec", repeated, tag = "1")]
+    pub signatures: Vec<Vec<u8>>,
+    #[prost(message, optional, tag = "2")]
+    pub message: Option<Message>,
+}
+
+/// solana.storage.ConfirmedBlock.Message
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Message {
+    #[prost(bytes = "vec", repeated, tag = "2")]
+    pub account_keys: Vec<Vec<u8>>,
+}
+
+/// solana.storage.ConfirmedBlock.TransactionStatusMeta
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TransactionStatusMeta {
+    #[prost(message, optional, tag = "1")]
+    pub err: Option<TransactionError>,
+    #[prost(uint64, tag = "2")]
+    pub fee: u64,
+    #[prost(string, repeated, tag = "6")]
+    pub log_messages: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TransactionError {
+    #[prost(bytes = "vec", tag = "1")]
+    pub err: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeUpdatePing {}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeUpdatePong {
+    #[prost(int32, tag = "1")]
+    pub id: i32,
+}
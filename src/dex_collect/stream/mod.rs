@@ -0,0 +1,4 @@
+#[cfg(feature = "geyser")]
+pub mod geyser;
+pub mod update;
+pub mod websocket;
@@ -0,0 +1,281 @@
+use futures::Stream;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use crate::dex_collect::record::{AccountRecord, RecorderHandle};
+use crate::dex_collect::serum::serum_depth::{decode_book_side, BookScale, Level};
+use crate::dex_collect::serum::serum_events::{decode_event_queue, FillEvent};
+use crate::dex_collect::stamp::SnapshotStamp;
+
+/// 订单簿方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookSide {
+    Bids,
+    Asks,
+}
+
+/// 推送的市场更新
+#[derive(Debug, Clone)]
+pub enum MarketUpdate {
+    /// 订单簿单边更新，levels 已排序并带累计数量
+    Book {
+        market: Pubkey,
+        side: BookSide,
+        levels: Vec<Level>,
+        stamp: SnapshotStamp,
+    },
+    /// 事件队列中新出现的成交，按序号升序
+    Fills {
+        market: Pubkey,
+        fills: Vec<FillEvent>,
+        stamp: SnapshotStamp,
+    },
+    /// AMM 池储备更新 (原始数量，未按精度换算)
+    Pool {
+        pool: Pubkey,
+        base_reserve: u64,
+        quote_reserve: u64,
+        stamp: SnapshotStamp,
+    },
+    /// 未注册解码方式的账户，原样转发
+    Account {
+        pubkey: Pubkey,
+        owner: Pubkey,
+        data: Vec<u8>,
+        stamp: SnapshotStamp,
+    },
+    /// 涉及被跟踪程序的交易 (目前仅 gRPC 后端提供)
+    Transaction {
+        signature: Signature,
+        accounts: Vec<Pubkey>,
+        logs: Vec<String>,
+        failed: bool,
+        stamp: SnapshotStamp,
+    },
+}
+
+impl MarketUpdate {
+    pub fn stamp(&self) -> &SnapshotStamp {
+        match self {
+            MarketUpdate::Book { stamp, .. }
+            | MarketUpdate::Fills { stamp, .. }
+            | MarketUpdate::Pool { stamp, .. }
+            | MarketUpdate::Account { stamp, .. }
+            | MarketUpdate::Transaction { stamp, .. } => stamp,
+        }
+    }
+
+    pub fn slot(&self) -> u64 {
+        self.stamp().slot
+    }
+}
+
+/// 被跟踪账户的角色
+#[derive(Debug, Clone, Copy)]
+enum AccountRole {
+    Book {
+        market: Pubkey,
+        side: BookSide,
+        scale: BookScale,
+    },
+    EventQueue {
+        market: Pubkey,
+    },
+    PoolVault {
+        pool: Pubkey,
+        is_base: bool,
+    },
+}
+
+/// SPL Token 账户中 amount 字段的偏移 (mint 32 + owner 32)
+const TOKEN_AMOUNT_OFFSET: usize = 64;
+
+/// 账户更新解码器
+///
+/// 记录每个被跟踪账户的角色，把原始账户数据解码成 [`MarketUpdate`]。
+/// websocket、gRPC 和轮询几种数据源共用同一个解码器，同一账户旧于或等于
+/// 已处理 slot 的数据会被丢弃，避免多源重复推送。
+#[derive(Debug, Clone, Default)]
+pub struct UpdateDecoder {
+    roles: HashMap<Pubkey, AccountRole>,
+    reserves: HashMap<Pubkey, (Option<u64>, Option<u64>)>,
+    last_slots: HashMap<Pubkey, u64>,
+    last_fill_seqs: HashMap<Pubkey, u64>,
+    recorder: Option<RecorderHandle>,
+}
+
+impl UpdateDecoder {
+    /// 跟踪 Serum/OpenBook 市场的 bids/asks 账户，按 `scale` 把 lots 换算成 UI 数量
+    pub fn track_book(&mut self, market: Pubkey, bids: Pubkey, asks: Pubkey, scale: BookScale) {
+        self.roles.insert(
+            bids,
+            AccountRole::Book {
+                market,
+                side: BookSide::Bids,
+                scale,
+            },
+        );
+        self.roles.insert(
+            asks,
+            AccountRole::Book {
+                market,
+                side: BookSide::Asks,
+                scale,
+            },
+        );
+    }
+
+    /// 跟踪 Serum/OpenBook 市场的事件队列，解码成交
+    pub fn track_event_queue(&mut self, market: Pubkey, event_queue: Pubkey) {
+        self.roles
+            .insert(event_queue, AccountRole::EventQueue { market });
+    }
+
+    /// 跟踪 AMM 池的两个金库账户
+    pub fn track_pool(&mut self, pool: Pubkey, base_vault: Pubkey, quote_vault: Pubkey) {
+        self.roles.insert(
+            base_vault,
+            AccountRole::PoolVault {
+                pool,
+                is_base: true,
+            },
+        );
+        self.roles.insert(
+            quote_vault,
+            AccountRole::PoolVault {
+                pool,
+                is_base: false,
+            },
+        );
+        self.reserves.insert(pool, (None, None));
+    }
+
+    /// 把被跟踪账户的原始数据交给记录器落盘
+    pub fn set_recorder(&mut self, recorder: RecorderHandle) {
+        self.recorder = Some(recorder);
+    }
+
+    /// 所有被跟踪的账户
+    pub fn accounts(&self) -> Vec<Pubkey> {
+        self.roles.keys().copied().collect()
+    }
+
+    pub fn is_tracked(&self, pubkey: &Pubkey) -> bool {
+        self.roles.contains_key(pubkey)
+    }
+
+    /// 解码一次账户更新，过期、重复或无法解码时返回 None
+    pub fn decode(
+        &mut self,
+        pubkey: &Pubkey,
+        owner: &Pubkey,
+        data: &[u8],
+        stamp: SnapshotStamp,
+    ) -> Option<MarketUpdate> {
+        if let Some(last) = self.last_slots.get(pubkey) {
+            if stamp.slot <= *last {
+                return None;
+            }
+        }
+        self.last_slots.insert(*pubkey, stamp.slot);
+
+        if let Some(recorder) = &self.recorder {
+            if self.roles.contains_key(pubkey) {
+                recorder.record(AccountRecord::new(pubkey, owner, data, &stamp));
+            }
+        }
+
+        match self.roles.get(pubkey).copied() {
+            Some(AccountRole::Book {
+                market,
+                side,
+                scale,
+            }) => {
+                let levels = decode_book_side(data, side == BookSide::Bids, &scale).ok()?;
+                Some(MarketUpdate::Book {
+                    market,
+                    side,
+                    levels,
+                    stamp,
+                })
+            }
+            Some(AccountRole::EventQueue { market }) => {
+                let (header, fills) = decode_event_queue(data).ok()?;
+                // 首次看到队列时只记下当前序号，队列里的旧成交不推送
+                let Some(last) = self.last_fill_seqs.get(&market).copied() else {
+                    self.last_fill_seqs
+                        .insert(market, header.seq_num.saturating_sub(1));
+                    return None;
+                };
+                // 只推送未处理过的序号
+                let fills: Vec<FillEvent> =
+                    fills.into_iter().filter(|f| f.seq_num > last).collect();
+                self.last_fill_seqs.insert(market, fills.last()?.seq_num);
+                Some(MarketUpdate::Fills {
+                    market,
+                    fills,
+                    stamp,
+                })
+            }
+            Some(AccountRole::PoolVault { pool, is_base }) => {
+                let bytes = data.get(TOKEN_AMOUNT_OFFSET..TOKEN_AMOUNT_OFFSET + 8)?;
+                let amount = u64::from_le_bytes(bytes.try_into().ok()?);
+                let reserves = self.reserves.entry(pool).or_default();
+                if is_base {
+                    reserves.0 = Some(amount);
+                } else {
+                    reserves.1 = Some(amount);
+                }
+                // 两个金库都收到过数据后才推送
+                match *reserves {
+                    (Some(base_reserve), Some(quote_reserve)) => Some(MarketUpdate::Pool {
+                        pool,
+                        base_reserve,
+                        quote_reserve,
+                        stamp,
+                    }),
+                    _ => None,
+                }
+            }
+            None => Some(MarketUpdate::Account {
+                pubkey: *pubkey,
+                owner: *owner,
+                data: data.to_vec(),
+                stamp,
+            }),
+        }
+    }
+}
+
+/// 市场更新流
+///
+/// 由后台任务写入，丢弃时自动停止后台任务。
+pub struct MarketUpdateStream {
+    rx: mpsc::Receiver<MarketUpdate>,
+    task: JoinHandle<()>,
+}
+
+impl MarketUpdateStream {
+    pub(crate) fn new(rx: mpsc::Receiver<MarketUpdate>, task: JoinHandle<()>) -> Self {
+        Self { rx, task }
+    }
+}
+
+impl Stream for MarketUpdateStream {
+    type Item = MarketUpdate;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+impl Drop for MarketUpdateStream {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,236 @@
+use anyhow::Result;
+use futures::{stream::select_all, StreamExt};
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client::{
+    nonblocking::pubsub_client::PubsubClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+};
+use solana_sdk::{account::Account, commitment_config::CommitmentConfig, pubkey::Pubkey};
+use std::{str::FromStr, time::Duration};
+use tokio::sync::mpsc;
+
+use crate::dex_collect::rpc::client::RpcHandle;
+use crate::dex_collect::stamp::SnapshotStamp;
+use crate::dex_collect::stream::update::{MarketUpdate, MarketUpdateStream, UpdateDecoder};
+
+/// websocket 订阅配置
+#[derive(Debug, Clone)]
+pub struct StreamConfig {
+    pub ws_url: String,
+    pub commitment: CommitmentConfig,
+    pub program_ids: Vec<Pubkey>,      // 额外做 programSubscribe 的程序
+    pub stale_after: Duration, // 超过该时间连 slot 通知也没有，视为连接可能不健康，补一次轮询
+    pub max_stale_rounds: usize, // 连续多少次无消息后重连
+    pub poll_interval: Duration, // 断线期间的轮询间隔
+    pub max_reconnect_delay: Duration, // 重连等待上限
+    pub channel_size: usize,
+}
+
+impl StreamConfig {
+    pub fn new(ws_url: &str) -> Self {
+        Self {
+            ws_url: ws_url.to_string(),
+            commitment: CommitmentConfig::confirmed(),
+            program_ids: Vec::new(),
+            stale_after: Duration::from_secs(5),
+            max_stale_rounds: 3,
+            poll_interval: Duration::from_secs(1),
+            max_reconnect_delay: Duration::from_secs(30),
+            channel_size: 1024,
+        }
+    }
+
+    /// 由 RPC 句柄的 http 地址推导 websocket 地址
+    pub fn from_rpc(rpc: &RpcHandle) -> Self {
+        Self::new(&ws_url_from_http(&rpc.url()))
+    }
+}
+
+/// http(s) 地址转换为 ws(s) 地址
+pub fn ws_url_from_http(url: &str) -> String {
+    if let Some(rest) = url.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else if let Some(rest) = url.strip_prefix("http://") {
+        format!("ws://{}", rest)
+    } else {
+        url.to_string()
+    }
+}
+
+/// 订阅解码器中所有被跟踪的账户，返回市场更新流
+///
+/// 后台任务负责 accountSubscribe/programSubscribe，断线后自动重连并重新订阅；
+/// slotSubscribe 作为连接存活信号，账户长时间不变不会触发重连。
+/// 连接无消息或断开期间改用 RPC 批量轮询，保证消费者仍能收到更新。
+pub fn subscribe(
+    rpc: RpcHandle,
+    decoder: UpdateDecoder,
+    config: StreamConfig,
+) -> MarketUpdateStream {
+    let (tx, rx) = mpsc::channel(config.channel_size);
+    let task = tokio::spawn(run(rpc, decoder, config, tx));
+    MarketUpdateStream::new(rx, task)
+}
+
+async fn run(
+    rpc: RpcHandle,
+    mut decoder: UpdateDecoder,
+    config: StreamConfig,
+    tx: mpsc::Sender<MarketUpdate>,
+) {
+    let mut reconnect_delay = config.poll_interval;
+    loop {
+        let mut subscribed = false;
+        match run_socket(&rpc, &mut decoder, &config, &tx, &mut subscribed).await {
+            Ok(()) => return, // 消费者已关闭
+            Err(e) => log::warn!("websocket 订阅中断 ({}): {}", config.ws_url, e),
+        }
+        // 成功订阅过说明节点可用，重连等待从头开始退避
+        if subscribed {
+            reconnect_delay = config.poll_interval;
+        }
+
+        // 断线期间轮询，直到重连等待结束
+        let deadline = tokio::time::Instant::now() + reconnect_delay;
+        while tokio::time::Instant::now() < deadline {
+            if let Err(e) = poll_once(&rpc, &mut decoder, &tx).await {
+                log::warn!("轮询回退失败: {}", e);
+            }
+            if tx.is_closed() {
+                return;
+            }
+            tokio::time::sleep(config.poll_interval).await;
+        }
+        reconnect_delay = (reconnect_delay * 2).min(config.max_reconnect_delay);
+    }
+}
+
+/// 通过 RPC 批量获取所有被跟踪账户并推送
+async fn poll_once(
+    rpc: &RpcHandle,
+    decoder: &mut UpdateDecoder,
+    tx: &mpsc::Sender<MarketUpdate>,
+) -> Result<()> {
+    let accounts = decoder.accounts();
+    if accounts.is_empty() {
+        return Ok(());
+    }
+    let batch = rpc.get_accounts_batch(&accounts, None).await?;
+    for pubkey in &accounts {
+        if let Some(account) = batch.get(pubkey) {
+            if let Some(update) =
+                decoder.decode(pubkey, &account.owner, &account.data, batch.stamp())
+            {
+                if tx.send(update).await.is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 建立一次 websocket 连接并持续转发更新
+///
+/// 消费者关闭时返回 Ok，连接断开或长时间无消息时返回 Err 以触发重连；
+/// 订阅全部建立后把 `subscribed` 置为 true。
+async fn run_socket(
+    rpc: &RpcHandle,
+    decoder: &mut UpdateDecoder,
+    config: &StreamConfig,
+    tx: &mpsc::Sender<MarketUpdate>,
+    subscribed: &mut bool,
+) -> Result<()> {
+    let client = PubsubClient::new(&config.ws_url).await?;
+    let account_config = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        commitment: Some(config.commitment),
+        ..RpcAccountInfoConfig::default()
+    };
+
+    let mut streams = Vec::new();
+    for pubkey in decoder.accounts() {
+        let (stream, _unsubscribe) = client
+            .account_subscribe(&pubkey, Some(account_config.clone()))
+            .await?;
+        streams.push(
+            stream
+                .map(move |response| (pubkey, response.context.slot, response.value))
+                .boxed(),
+        );
+    }
+    for program_id in &config.program_ids {
+        let program_config = RpcProgramAccountsConfig {
+            account_config: account_config.clone(),
+            ..RpcProgramAccountsConfig::default()
+        };
+        let (stream, _unsubscribe) = client
+            .program_subscribe(program_id, Some(program_config))
+            .await?;
+        streams.push(
+            stream
+                .filter_map(|response| async move {
+                    let pubkey = Pubkey::from_str(&response.value.pubkey).ok()?;
+                    Some((pubkey, response.context.slot, response.value.account))
+                })
+                .boxed(),
+        );
+    }
+    if streams.is_empty() {
+        return Err(anyhow::anyhow!("No accounts or programs to subscribe"));
+    }
+    let (mut slots, _unsubscribe) = client.slot_subscribe().await?;
+    *subscribed = true;
+    log::info!(
+        "websocket 已订阅 {} 个流 ({})",
+        streams.len(),
+        config.ws_url
+    );
+
+    // 先用一次轮询补齐订阅建立前的状态
+    poll_once(rpc, decoder, tx).await?;
+
+    let mut merged = select_all(streams);
+    let mut stale_rounds = 0;
+    loop {
+        tokio::select! {
+            account = merged.next() => match account {
+                Some((pubkey, slot, ui_account)) => {
+                    stale_rounds = 0;
+                    let stamp = SnapshotStamp::new(slot, &config.ws_url);
+                    if let Some(update) = decode_ui_account(decoder, &pubkey, stamp, &ui_account) {
+                        if tx.send(update).await.is_err() {
+                            return Ok(());
+                        }
+                    }
+                }
+                None => return Err(anyhow::anyhow!("Subscription stream closed")),
+            },
+            // slot 通知持续到达说明连接正常，账户没有变化不需要重连
+            slot = slots.next() => match slot {
+                Some(_) => stale_rounds = 0,
+                None => return Err(anyhow::anyhow!("Slot subscription closed")),
+            },
+            _ = tokio::time::sleep(config.stale_after) => {
+                stale_rounds += 1;
+                if stale_rounds >= config.max_stale_rounds {
+                    return Err(anyhow::anyhow!("No messages for {} rounds", stale_rounds));
+                }
+                poll_once(rpc, decoder, tx).await?;
+            }
+        }
+        if tx.is_closed() {
+            return Ok(());
+        }
+    }
+}
+
+fn decode_ui_account(
+    decoder: &mut UpdateDecoder,
+    pubkey: &Pubkey,
+    stamp: SnapshotStamp,
+    ui_account: &UiAccount,
+) -> Option<MarketUpdate> {
+    let account: Account = ui_account.decode()?;
+    decoder.decode(pubkey, &account.owner, &account.data, stamp)
+}
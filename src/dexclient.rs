@@ -1,15 +1,185 @@
+use anchor_spl::associated_token::{
+    get_associated_token_address, spl_associated_token_account::instruction as ata_instruction,
+};
+use anchor_spl::token::spl_token;
 use anyhow::Result;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{
+    instruction::Instruction,
+    signature::{read_keypair_file, Keypair, Signature},
+    signer::Signer,
+    system_instruction,
+    transaction::Transaction,
+};
 
 use solana_sdk::account::Account;
-use std::str::FromStr;
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
 
+use crate::dex_collect::fixed::{self, Decimal};
 use crate::dex_collect::serum::serum_client::SerumPriceFetcher;
- 
+use crate::dex_collect::serum::serum_depth::MarketState;
+use crate::dex_collect::serum::serum_events;
+use crate::executer::serum::{
+    self, MarketAccounts, NewOrderParams, OpenOrder, OpenOrders, OrderType, SelfTradeBehavior,
+    OPEN_ORDERS_MARKET_OFFSET, OPEN_ORDERS_OWNER_OFFSET, OPEN_ORDERS_SIZE,
+};
+use crate::executer::trade::trade::{self, RaydiumPool, SwapMode, SwapPlan};
+use crate::strategy::quote::Liquidity;
+
+/// SPL Mint 账户中 decimals 字段的偏移
+const MINT_DECIMALS_OFFSET: usize = 44;
+/// SPL Token 账户中 amount 字段的偏移
+const TOKEN_AMOUNT_OFFSET: usize = 64;
+/// 单笔交易最多打包的撤单指令数，受交易大小限制
+pub const MAX_CANCELS_PER_TRANSACTION: usize = 12;
 
 /// DEX 交互结构体
 pub struct DexClient {
     price_fetcher: SerumPriceFetcher,
+    payer: Option<Arc<Keypair>>, // 签名和支付手续费的钱包，只读使用时可为空
+    markets: Mutex<HashMap<Pubkey, MarketAccounts>>, // 市场地址 -> 下单账户
+    open_orders: Mutex<HashMap<Pubkey, Pubkey>>, // 市场地址 -> 自己的 OpenOrders 账户
+    next_client_id: AtomicU64,   // 自动分配的客户端订单号，从启动时刻的微秒时间戳开始递增
+}
+
+/// 下单选项
+#[derive(Debug, Clone, Copy)]
+pub struct OrderOptions {
+    pub order_type: OrderType,
+    pub self_trade: SelfTradeBehavior,
+    pub client_order_id: u64, // 0 时自动分配
+}
+
+impl Default for OrderOptions {
+    fn default() -> Self {
+        Self {
+            order_type: OrderType::Limit,
+            self_trade: SelfTradeBehavior::default(),
+            client_order_id: 0,
+        }
+    }
+}
+
+/// 确认后的下单结果
+#[derive(Debug, Clone)]
+pub struct PlacedOrder {
+    pub signature: Signature,
+    pub client_order_id: u64,
+    pub order_id: Option<u128>, // 订单已完全成交或被撤时为 None
+    pub open_orders: Pubkey,
+}
+
+/// 已确认的交易及其费用 (lamports)
+#[derive(Debug, Clone, Copy)]
+pub struct SentTransaction {
+    pub signature: Signature,
+    pub fee: u64,
+}
+
+/// 事件队列中属于自己 OpenOrders 账户的成交
+#[derive(Debug, Clone)]
+pub struct OwnFills {
+    pub market: MarketAccounts,
+    pub last_seq: u64, // 已读到的最新事件序号，下次查询从其后开始
+    pub fills: Vec<serum_events::FillEvent>,
+}
+
+/// 撤单目标
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CancelTarget {
+    OrderId(u128),
+    ClientId(u64),
+}
+
+/// 撤单结果，已按确认后的 OpenOrders 核对
+#[derive(Debug, Clone, Default)]
+pub struct CancelReport {
+    pub signatures: Vec<Signature>,
+    pub cancelled: Vec<OpenOrder>,    // 确认已从 OpenOrders 移除
+    pub remaining: Vec<OpenOrder>,    // 交易确认但订单仍在
+    pub not_found: Vec<CancelTarget>, // 发送前已不在 OpenOrders 中，可能已成交
+    pub errors: Vec<String>,          // 发送失败的分片，其中的订单按核对结果计入 remaining
+}
+
+impl CancelReport {
+    /// 全部目标都已撤掉或本就不存在
+    pub fn is_complete(&self) -> bool {
+        self.remaining.is_empty() && self.errors.is_empty()
+    }
+
+    /// 按撤单前的 OpenOrders 找出目标挂单，找不到的计入 `not_found`，同一挂单只撤一次
+    fn resolve(
+        &mut self,
+        before: &OpenOrders,
+        targets: &[CancelTarget],
+    ) -> Vec<(CancelTarget, OpenOrder)> {
+        let mut resolved: Vec<(CancelTarget, OpenOrder)> = Vec::new();
+        for target in targets {
+            let order = match target {
+                CancelTarget::OrderId(id) => before.find_by_order_id(*id),
+                CancelTarget::ClientId(id) => before.find_by_client_id(*id),
+            };
+            let Some(order) = order else {
+                self.not_found.push(*target);
+                continue;
+            };
+            if resolved.iter().any(|(_, o)| o.order_id == order.order_id) {
+                continue;
+            }
+            resolved.push((*target, order.clone()));
+        }
+        resolved
+    }
+
+    /// 按确认后的 OpenOrders 核对已请求撤销的挂单
+    fn reconcile(&mut self, requested: Vec<OpenOrder>, after: &OpenOrders) {
+        for order in requested {
+            if after.find_by_order_id(order.order_id).is_some() {
+                self.remaining.push(order);
+            } else {
+                self.cancelled.push(order);
+            }
+        }
+    }
+
+    /// 单笔撤单的结果: 未找到或未撤掉时报错
+    fn into_single(self) -> Result<Signature> {
+        if let Some(error) = self.errors.first() {
+            return Err(anyhow::anyhow!("Cancel failed: {}", error));
+        }
+        if let Some(target) = self.not_found.first() {
+            return Err(anyhow::anyhow!("Order not found: {:?}", target));
+        }
+        if let Some(order) = self.remaining.first() {
+            return Err(anyhow::anyhow!(
+                "Order still open after cancel: {}",
+                order.order_id
+            ));
+        }
+        self.signatures
+            .last()
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("No cancel sent"))
+    }
+}
+
+impl Default for DexClient {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl DexClient {
@@ -17,7 +187,43 @@ impl DexClient {
     pub fn new() -> Self {
         Self {
             price_fetcher: SerumPriceFetcher::new(),
+            payer: None,
+            markets: Mutex::new(HashMap::new()),
+            open_orders: Mutex::new(HashMap::new()),
+            next_client_id: AtomicU64::new(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_micros() as u64)
+                    .unwrap_or(1),
+            ),
+        }
+    }
+
+    /// 设置交易签名钱包
+    pub fn with_payer(mut self, payer: Keypair) -> Self {
+        self.payer = Some(Arc::new(payer));
+        self
+    }
+
+    /// 从 keypair 文件加载签名钱包
+    pub fn from_keypair_file(path: &str) -> Result<Self> {
+        let payer = read_keypair_file(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read keypair {}: {}", path, e))?;
+        Ok(Self::new().with_payer(payer))
+    }
+
+    /// 调用方未指定 (为 0) 时分配一个非 0 的客户端订单号，用于确认后查找订单
+    fn client_order_id(&self, requested: u64) -> u64 {
+        if requested != 0 {
+            return requested;
         }
+        self.next_client_id.fetch_add(1, Ordering::Relaxed).max(1)
+    }
+
+    fn payer(&self) -> Result<&Keypair> {
+        self.payer
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("No payer keypair configured"))
     }
     /// 创建市场账户
     pub async fn create_market_account(&self, market_address: &str) -> Result<Account> {
@@ -43,35 +249,619 @@ impl DexClient {
     }
 
     /// 下限价单
+    ///
+    /// 价格和数量按市场 lot 换算，买单价格向下、卖单价格向上取整，数量向下取整。
+    /// 没有 OpenOrders 账户时在同一笔交易中创建，支付 SOL 时自动包装为 wSOL。
     pub async fn place_limit_order(
         &self,
         market_address: &str,
         side: OrderSide,
-        price: f64,
-        size: f64,
-    ) -> Result<()> {
-        let _market_pubkey = Pubkey::from_str(market_address)?;
+        price: Decimal,
+        size: Decimal,
+        options: OrderOptions,
+    ) -> Result<PlacedOrder> {
+        let market_pubkey = Pubkey::from_str(market_address)?;
+        let payer = self.payer()?;
+        let owner = payer.pubkey();
+        let market = self.market_accounts(&market_pubkey).await?;
+        let client_order_id = self.client_order_id(options.client_order_id);
 
-        println!("准备下单:");
-        println!("市场: {}", market_address);
-        println!("方向: {:?}", side);
-        println!("价格: {}", price);
-        println!("数量: {}", size);
+        let limit_price = fixed::price_to_lots(
+            price,
+            market.base_lot_size,
+            market.quote_lot_size,
+            market.base_decimals,
+            market.quote_decimals,
+            side == OrderSide::Sell,
+        )
+        .filter(|lots| *lots > 0)
+        .ok_or_else(|| anyhow::anyhow!("Price {} below one tick", price))?;
+        let max_base_qty = fixed::size_to_lots(size, market.base_lot_size, market.base_decimals)
+            .filter(|lots| *lots > 0)
+            .ok_or_else(|| anyhow::anyhow!("Size {} below one lot", size))?;
+        let params = NewOrderParams::new(
+            &market,
+            side,
+            limit_price,
+            max_base_qty,
+            options.order_type,
+            options.self_trade,
+            client_order_id,
+        );
 
-        // 这里添加实际的下单逻辑
-        // 需要构建相应的指令和交易
+        let mut instructions = Vec::new();
+        let mut signers: Vec<Keypair> = Vec::new();
 
-        Ok(())
+        let open_orders = match self.open_orders_account(&market).await? {
+            Some(open_orders) => open_orders,
+            None => {
+                let account = Keypair::new();
+                let lamports = self
+                    .price_fetcher
+                    .rpc()
+                    .call(|client| async move {
+                        client
+                            .get_minimum_balance_for_rent_exemption(OPEN_ORDERS_SIZE)
+                            .await
+                    })
+                    .await?;
+                instructions.push(system_instruction::create_account(
+                    &owner,
+                    &account.pubkey(),
+                    lamports,
+                    OPEN_ORDERS_SIZE as u64,
+                    &market.program_id,
+                ));
+                instructions.push(serum::init_open_orders(&market, &account.pubkey(), &owner));
+                let pubkey = account.pubkey();
+                signers.push(account);
+                pubkey
+            }
+        };
+
+        let payer_mint = market.payer_mint(&side);
+        let payer_account = get_associated_token_address(&owner, &payer_mint);
+        let required = params.required_funds(&market);
+        let balance = self.token_balance(&payer_account).await?;
+        if payer_mint == spl_token::native_mint::ID {
+            instructions.push(ata_instruction::create_associated_token_account_idempotent(
+                &owner,
+                &owner,
+                &payer_mint,
+                &spl_token::ID,
+            ));
+            if balance < required {
+                instructions.push(system_instruction::transfer(
+                    &owner,
+                    &payer_account,
+                    required - balance,
+                ));
+                instructions.push(spl_token::instruction::sync_native(
+                    &spl_token::ID,
+                    &payer_account,
+                )?);
+            }
+        } else if balance < required {
+            return Err(anyhow::anyhow!(
+                "Insufficient balance in {}: {} < {}",
+                payer_account,
+                balance,
+                required
+            ));
+        }
+
+        instructions.push(serum::new_order_v3(
+            &market,
+            &open_orders,
+            &payer_account,
+            &owner,
+            &params,
+        ));
+
+        let extra: Vec<&Keypair> = signers.iter().collect();
+        let signature = self.send_transaction(&instructions, &extra).await?;
+        self.open_orders
+            .lock()
+            .unwrap()
+            .insert(market.market, open_orders);
+        log::info!(
+            "下单确认 {} {:?} {}@{} client_id={} sig={}",
+            market_address,
+            side,
+            size,
+            price,
+            client_order_id,
+            signature
+        );
+
+        // 读取不早于交易所在 slot 的 OpenOrders，避免节点落后读到下单前的状态
+        let slot = self.confirmed_slot(&[signature]).await?;
+        let order_id = self
+            .load_open_orders_at(&open_orders, slot)
+            .await?
+            .find_by_client_id(client_order_id)
+            .map(|o| o.order_id);
+        Ok(PlacedOrder {
+            signature,
+            client_order_id,
+            order_id,
+            open_orders,
+        })
+    }
+
+    /// 读取市场的下单账户和 mint 精度，结果会缓存
+    pub async fn market_accounts(&self, market: &Pubkey) -> Result<MarketAccounts> {
+        if let Some(accounts) = self.markets.lock().unwrap().get(market) {
+            return Ok(accounts.clone());
+        }
+        let rpc = self.price_fetcher.rpc();
+        let account = rpc.get_account(market).await?;
+        let state = MarketState::from_bytes(&account.data)?;
+        let mints = [state.base_mint, state.quote_mint];
+        let batch = rpc.get_accounts_batch(&mints, None).await?;
+        let decimals = |mint: &Pubkey| -> Result<u32> {
+            batch
+                .require(mint)?
+                .data
+                .get(MINT_DECIMALS_OFFSET)
+                .map(|d| *d as u32)
+                .ok_or_else(|| anyhow::anyhow!("Invalid mint account {}", mint))
+        };
+        let accounts = MarketAccounts::new(
+            account.owner,
+            *market,
+            &state,
+            decimals(&state.base_mint)?,
+            decimals(&state.quote_mint)?,
+        );
+        self.markets
+            .lock()
+            .unwrap()
+            .insert(*market, accounts.clone());
+        Ok(accounts)
     }
 
-    /// 取消订单
-    pub async fn cancel_order(&self, market_address: &str, order_id: &str) -> Result<()> {
-        let _market_pubkey = Pubkey::from_str(market_address)?;
-        println!("取消订单: {}", order_id);
+    /// 查找钱包在市场上的 OpenOrders 账户，结果会缓存
+    pub async fn open_orders_account(&self, market: &MarketAccounts) -> Result<Option<Pubkey>> {
+        if let Some(open_orders) = self.open_orders.lock().unwrap().get(&market.market) {
+            return Ok(Some(*open_orders));
+        }
+        let owner = self.payer()?.pubkey();
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![
+                RpcFilterType::DataSize(OPEN_ORDERS_SIZE as u64),
+                RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                    OPEN_ORDERS_MARKET_OFFSET,
+                    market.market.as_ref(),
+                )),
+                RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                    OPEN_ORDERS_OWNER_OFFSET,
+                    owner.as_ref(),
+                )),
+            ]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let program_id = market.program_id;
+        let accounts = self
+            .price_fetcher
+            .rpc()
+            .call(|client| {
+                let config = config.clone();
+                async move {
+                    client
+                        .get_program_accounts_with_config(&program_id, config)
+                        .await
+                }
+            })
+            .await?;
+        let open_orders = accounts.first().map(|(pubkey, _)| *pubkey);
+        if let Some(open_orders) = open_orders {
+            self.open_orders
+                .lock()
+                .unwrap()
+                .insert(market.market, open_orders);
+        }
+        Ok(open_orders)
+    }
 
-        // 这里添加取消订单的逻辑
+    /// 读取并解码 OpenOrders 账户
+    pub async fn load_open_orders(&self, open_orders: &Pubkey) -> Result<OpenOrders> {
+        self.load_open_orders_at(open_orders, None).await
+    }
 
-        Ok(())
+    /// 读取 OpenOrders，数据不早于 `min_context_slot`
+    async fn load_open_orders_at(
+        &self,
+        open_orders: &Pubkey,
+        min_context_slot: Option<u64>,
+    ) -> Result<OpenOrders> {
+        let batch = self
+            .price_fetcher
+            .rpc()
+            .get_accounts_batch(&[*open_orders], min_context_slot)
+            .await?;
+        OpenOrders::from_bytes(&batch.require(open_orders)?.data)
+    }
+
+    /// 已确认交易所在的最大 slot，查不到状态时为 None
+    async fn confirmed_slot(&self, signatures: &[Signature]) -> Result<Option<u64>> {
+        if signatures.is_empty() {
+            return Ok(None);
+        }
+        let statuses = self
+            .price_fetcher
+            .rpc()
+            .call(|client| async move { client.get_signature_statuses(signatures).await })
+            .await?;
+        Ok(statuses.value.iter().flatten().map(|s| s.slot).max())
+    }
+
+    /// 代币账户余额 (原生单位)，账户不存在时为 0
+    async fn token_balance(&self, account: &Pubkey) -> Result<u64> {
+        let batch = self
+            .price_fetcher
+            .rpc()
+            .get_accounts_batch(&[*account], None)
+            .await?;
+        Ok(batch
+            .get(account)
+            .and_then(|a| a.data.get(TOKEN_AMOUNT_OFFSET..TOKEN_AMOUNT_OFFSET + 8))
+            .map(|d| u64::from_le_bytes(d.try_into().unwrap()))
+            .unwrap_or(0))
+    }
+
+    /// 用最新 blockhash 签名交易，付款账户总是第一个签名者
+    async fn sign_transaction(
+        &self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<Transaction> {
+        let payer = self.payer()?;
+        let blockhash = self
+            .price_fetcher
+            .rpc()
+            .call(|client| async move { client.get_latest_blockhash().await })
+            .await?;
+        let mut all_signers: Vec<&Keypair> = vec![payer];
+        all_signers.extend_from_slice(signers);
+        Ok(Transaction::new_signed_with_payer(
+            instructions,
+            Some(&payer.pubkey()),
+            &all_signers,
+            blockhash,
+        ))
+    }
+
+    /// 签名并发送交易，等待确认
+    async fn send_transaction(
+        &self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<Signature> {
+        let transaction = &self.sign_transaction(instructions, signers).await?;
+        self.price_fetcher
+            .rpc()
+            .call(|client| async move { client.send_and_confirm_transaction(transaction).await })
+            .await
+    }
+
+    /// 签名并发送交易，等待确认，同时返回按消息计算的交易费用
+    ///
+    /// 计算费用多一次 RPC，只用于需要累计费用的 crank 和结算。
+    async fn send_with_fee(
+        &self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<SentTransaction> {
+        let rpc = self.price_fetcher.rpc();
+        let transaction = &self.sign_transaction(instructions, signers).await?;
+        let fee = rpc
+            .call(|client| async move { client.get_fee_for_message(transaction.message()).await })
+            .await?;
+        let signature = rpc
+            .call(|client| async move { client.send_and_confirm_transaction(transaction).await })
+            .await?;
+        Ok(SentTransaction { signature, fee })
+    }
+
+    /// 读取 Raydium AMM v4 池子及其关联市场的账户
+    pub async fn raydium_pool(&self, amm: &Pubkey) -> Result<RaydiumPool> {
+        let account = self.price_fetcher.rpc().get_account(amm).await?;
+        let (market, _) = trade::amm_market(&account.data)?;
+        let market = self.market_accounts(&market).await?;
+        RaydiumPool::from_bytes(account.owner, *amm, &account.data, market)
+    }
+
+    /// 读取 Raydium 池子当前储备，金库和 OpenOrders 在同一批次中读取
+    pub async fn raydium_liquidity(&self, pool: &RaydiumPool) -> Result<Liquidity> {
+        let keys = [pool.amm, pool.coin_vault, pool.pc_vault, pool.open_orders];
+        let batch = self
+            .price_fetcher
+            .rpc()
+            .get_accounts_batch(&keys, None)
+            .await?;
+        let amm = batch.require(&pool.amm)?;
+        // 待提取收益随交换变化，按同一批次的 AmmInfo 重新解码
+        let pool = RaydiumPool::from_bytes(amm.owner, pool.amm, &amm.data, pool.market.clone())?;
+        let vault = |key: &Pubkey| -> Result<u64> {
+            batch
+                .require(key)?
+                .data
+                .get(TOKEN_AMOUNT_OFFSET..TOKEN_AMOUNT_OFFSET + 8)
+                .map(|d| u64::from_le_bytes(d.try_into().unwrap()))
+                .ok_or_else(|| anyhow::anyhow!("Invalid token account {}", key))
+        };
+        let open_orders = OpenOrders::from_bytes(&batch.require(&pool.open_orders)?.data)?;
+        Ok(pool.liquidity(
+            vault(&pool.coin_vault)?,
+            vault(&pool.pc_vault)?,
+            &open_orders,
+        ))
+    }
+
+    /// 在 Raydium 上执行一笔交换
+    ///
+    /// 输入和输出使用钱包的关联代币账户，不存在时创建；输入为 SOL 时按需包装。
+    pub async fn raydium_swap(&self, pool: &RaydiumPool, plan: &SwapPlan) -> Result<Signature> {
+        let owner = self.payer()?.pubkey();
+        let (mint_in, mint_out) = pool.mints(plan.side);
+        let source = get_associated_token_address(&owner, &mint_in);
+        let destination = get_associated_token_address(&owner, &mint_out);
+
+        let mut instructions: Vec<Instruction> = [mint_in, mint_out]
+            .iter()
+            .map(|mint| {
+                ata_instruction::create_associated_token_account_idempotent(
+                    &owner,
+                    &owner,
+                    mint,
+                    &spl_token::ID,
+                )
+            })
+            .collect();
+        let balance = self.token_balance(&source).await?;
+        if mint_in == spl_token::native_mint::ID {
+            if balance < plan.amount_in {
+                instructions.push(system_instruction::transfer(
+                    &owner,
+                    &source,
+                    plan.amount_in - balance,
+                ));
+                instructions.push(spl_token::instruction::sync_native(
+                    &spl_token::ID,
+                    &source,
+                )?);
+            }
+        } else if plan.mode == SwapMode::BaseIn && balance < plan.amount_in {
+            return Err(anyhow::anyhow!(
+                "Insufficient balance in {}: {} < {}",
+                source,
+                balance,
+                plan.amount_in
+            ));
+        }
+        instructions.push(plan.instruction(pool, &source, &destination, &owner)?);
+
+        let signature = self.send_transaction(&instructions, &[]).await?;
+        log::info!(
+            "Raydium 交换 {} {:?} in={} out={} sig={}",
+            pool.amm,
+            plan.side,
+            plan.amount_in,
+            plan.amount_out,
+            signature
+        );
+        Ok(signature)
+    }
+
+    /// 把 OpenOrders 中的可用资金结算回钱包，没有 OpenOrders 账户时返回 None
+    ///
+    /// base/quote 关联代币账户不存在时在同一笔交易中创建，SOL 结算后保留为 wSOL。
+    pub async fn settle_funds(&self, market_address: &str) -> Result<Option<SentTransaction>> {
+        let market = self
+            .market_accounts(&Pubkey::from_str(market_address)?)
+            .await?;
+        let Some(open_orders) = self.open_orders_account(&market).await? else {
+            return Ok(None);
+        };
+        let owner = self.payer()?.pubkey();
+        let base_wallet = get_associated_token_address(&owner, &market.base_mint);
+        let quote_wallet = get_associated_token_address(&owner, &market.quote_mint);
+        let mut instructions: Vec<Instruction> = [market.base_mint, market.quote_mint]
+            .iter()
+            .map(|mint| {
+                ata_instruction::create_associated_token_account_idempotent(
+                    &owner,
+                    &owner,
+                    mint,
+                    &spl_token::ID,
+                )
+            })
+            .collect();
+        instructions.push(serum::settle_funds(
+            &market,
+            &open_orders,
+            &owner,
+            &base_wallet,
+            &quote_wallet,
+        )?);
+        let sent = self.send_with_fee(&instructions, &[]).await?;
+        log::info!("结算资金 {} sig={}", market_address, sent.signature);
+        Ok(Some(sent))
+    }
+
+    /// 事件队列中待处理的事件数和前 `limit` 条事件涉及的 OpenOrders 账户
+    pub async fn pending_events(
+        &self,
+        market_address: &str,
+        limit: usize,
+    ) -> Result<(u64, Vec<Pubkey>)> {
+        let market = self
+            .market_accounts(&Pubkey::from_str(market_address)?)
+            .await?;
+        let account = self
+            .price_fetcher
+            .rpc()
+            .get_account(&market.event_queue)
+            .await?;
+        let (header, owners) = serum_events::pending_owners(&account.data, limit)?;
+        Ok((header.count, owners))
+    }
+
+    /// 读取事件队列中序号大于 `after` 的自己的成交
+    ///
+    /// `after` 为 None 时只返回当前序号，队列里的旧成交不计入；
+    /// 还没有 OpenOrders 账户时成交为空。
+    pub async fn own_fills(&self, market_address: &str, after: Option<u64>) -> Result<OwnFills> {
+        let market = self
+            .market_accounts(&Pubkey::from_str(market_address)?)
+            .await?;
+        let open_orders = self.open_orders_account(&market).await?;
+        let account = self
+            .price_fetcher
+            .rpc()
+            .get_account(&market.event_queue)
+            .await?;
+        let (header, fills) = serum_events::decode_event_queue(&account.data)?;
+        let fills = match (after, open_orders) {
+            (Some(last), Some(open_orders)) => fills
+                .into_iter()
+                .filter(|f| f.seq_num > last && f.owner == open_orders)
+                .collect(),
+            _ => Vec::new(),
+        };
+        Ok(OwnFills {
+            market,
+            last_seq: after.map_or(header.seq_num.saturating_sub(1), |last| {
+                last.max(header.seq_num.saturating_sub(1))
+            }),
+            fills,
+        })
+    }
+
+    /// 消费事件队列，`open_orders` 为待处理事件涉及的账户
+    pub async fn consume_events(
+        &self,
+        market_address: &str,
+        open_orders: &[Pubkey],
+        limit: u16,
+    ) -> Result<SentTransaction> {
+        let market = self
+            .market_accounts(&Pubkey::from_str(market_address)?)
+            .await?;
+        let owner = self.payer()?.pubkey();
+        let base_wallet = get_associated_token_address(&owner, &market.base_mint);
+        let quote_wallet = get_associated_token_address(&owner, &market.quote_mint);
+        let instruction =
+            serum::consume_events(&market, open_orders, (&base_wallet, &quote_wallet), limit);
+        self.send_with_fee(&[instruction], &[]).await
+    }
+
+    /// 按链上订单号撤单，确认后订单仍在 OpenOrders 中时报错
+    pub async fn cancel_order(&self, market_address: &str, order_id: u128) -> Result<Signature> {
+        self.cancel_orders(market_address, &[CancelTarget::OrderId(order_id)])
+            .await?
+            .into_single()
+    }
+
+    /// 按客户端订单号撤单，确认后订单仍在 OpenOrders 中时报错
+    pub async fn cancel_order_by_client_id(
+        &self,
+        market_address: &str,
+        client_order_id: u64,
+    ) -> Result<Signature> {
+        self.cancel_orders(market_address, &[CancelTarget::ClientId(client_order_id)])
+            .await?
+            .into_single()
+    }
+
+    /// 撤掉市场上自己的全部挂单
+    pub async fn cancel_all(&self, market_address: &str) -> Result<CancelReport> {
+        let market = self
+            .market_accounts(&Pubkey::from_str(market_address)?)
+            .await?;
+        let Some(open_orders) = self.open_orders_account(&market).await? else {
+            return Ok(CancelReport::default());
+        };
+        let targets: Vec<CancelTarget> = self
+            .load_open_orders(&open_orders)
+            .await?
+            .orders
+            .iter()
+            .map(|o| CancelTarget::OrderId(o.order_id))
+            .collect();
+        self.cancel_orders(market_address, &targets).await
+    }
+
+    /// 批量撤单
+    ///
+    /// 先按当前 OpenOrders 解析目标，已不在账户中的订单记入 `not_found` 而不发送，
+    /// 避免整笔交易失败；其余按每笔交易 [`MAX_CANCELS_PER_TRANSACTION`] 条分批发送，
+    /// 全部确认后重新读取 OpenOrders 核对结果。
+    pub async fn cancel_orders(
+        &self,
+        market_address: &str,
+        targets: &[CancelTarget],
+    ) -> Result<CancelReport> {
+        let market = self
+            .market_accounts(&Pubkey::from_str(market_address)?)
+            .await?;
+        let owner = self.payer()?.pubkey();
+        let mut report = CancelReport::default();
+        let Some(open_orders) = self.open_orders_account(&market).await? else {
+            report.not_found = targets.to_vec();
+            return Ok(report);
+        };
+
+        let before = self.load_open_orders(&open_orders).await?;
+        let resolved = report.resolve(&before, targets);
+        if resolved.is_empty() {
+            return Ok(report);
+        }
+        let mut requested = Vec::with_capacity(resolved.len());
+        let mut instructions = Vec::with_capacity(resolved.len());
+        for (target, order) in resolved {
+            instructions.push(match target {
+                CancelTarget::OrderId(_) => serum::cancel_order_v2(
+                    &market,
+                    &open_orders,
+                    &owner,
+                    order.side,
+                    order.order_id,
+                ),
+                CancelTarget::ClientId(id) => {
+                    serum::cancel_order_by_client_id_v2(&market, &open_orders, &owner, id)
+                }
+            });
+            requested.push(order);
+        }
+
+        // 单个分片失败不影响其余分片，最终以 OpenOrders 核对结果为准
+        for chunk in instructions.chunks(MAX_CANCELS_PER_TRANSACTION) {
+            match self.send_transaction(chunk, &[]).await {
+                Ok(signature) => report.signatures.push(signature),
+                Err(e) => {
+                    log::warn!("撤单交易失败 {}: {}", market_address, e);
+                    report.errors.push(e.to_string());
+                }
+            }
+        }
+
+        let slot = self.confirmed_slot(&report.signatures).await?;
+        let after = self.load_open_orders_at(&open_orders, slot).await?;
+        report.reconcile(requested, &after);
+        if !report.remaining.is_empty() {
+            log::warn!(
+                "撤单确认后仍有 {} 笔挂单 {}",
+                report.remaining.len(),
+                market_address
+            );
+        }
+        Ok(report)
     }
 
     /// 获取市场深度
@@ -92,7 +882,7 @@ impl DexClient {
         Ok(())
     }
 
-    pub async fn monitor_price(&mut self, market_pair: &str)-> Result<()> {
+    pub async fn monitor_price(&mut self, market_pair: &str) -> Result<()> {
         // 这里添加价格监控逻辑
         self.price_fetcher.monitor_price(market_pair).await?;
         Ok(())
@@ -122,20 +912,90 @@ impl DexClient {
 }
 
 /// 订单方向
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OrderSide {
     Buy,
     Sell,
 }
 
-/// 市场信息结构
-#[derive(Debug)]
-pub struct MarketInfo {
-    address: Pubkey,
-    base_mint: Pubkey,
-    quote_mint: Pubkey,
-    base_vault: Pubkey,
-    quote_vault: Pubkey,
-    bid_accounts: Vec<Pubkey>,
-    ask_accounts: Vec<Pubkey>,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unset_client_order_id_is_allocated() {
+        let client = DexClient::new();
+        assert_eq!(client.client_order_id(42), 42);
+        let first = client.client_order_id(0);
+        let second = client.client_order_id(0);
+        assert_ne!(first, 0);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn cancel_report_reconciles_open_orders() {
+        let (a, b, c) = (
+            (100u128 << 64) | 1,
+            (101u128 << 64) | 2,
+            (102u128 << 64) | 3,
+        );
+        let before = OpenOrders::from_bytes(&serum::open_orders_fixture(&[
+            (0, a, 11, OrderSide::Buy),
+            (1, b, 12, OrderSide::Buy),
+            (2, c, 13, OrderSide::Sell),
+        ]))
+        .unwrap();
+        let targets = [
+            CancelTarget::OrderId(a),
+            CancelTarget::ClientId(12),
+            CancelTarget::ClientId(11), // 与第一个目标是同一笔挂单
+            CancelTarget::ClientId(99),
+        ];
+
+        let mut report = CancelReport::default();
+        let resolved = report.resolve(&before, &targets);
+        let ids: Vec<u128> = resolved.iter().map(|(_, o)| o.order_id).collect();
+        assert_eq!(ids, vec![a, b]);
+        assert_eq!(resolved[1].0, CancelTarget::ClientId(12));
+        assert_eq!(report.not_found, vec![CancelTarget::ClientId(99)]);
+
+        // 确认后 a 已撤掉，b 仍在
+        let after = OpenOrders::from_bytes(&serum::open_orders_fixture(&[
+            (1, b, 12, OrderSide::Buy),
+            (2, c, 13, OrderSide::Sell),
+        ]))
+        .unwrap();
+        let requested = resolved.into_iter().map(|(_, o)| o).collect();
+        report.reconcile(requested, &after);
+        assert_eq!(report.cancelled.len(), 1);
+        assert_eq!(report.cancelled[0].order_id, a);
+        assert_eq!(report.remaining.len(), 1);
+        assert_eq!(report.remaining[0].order_id, b);
+        assert!(!report.is_complete());
+        assert!(report.into_single().is_err());
+    }
+
+    #[test]
+    fn verified_cancel_completes_single_report() {
+        let a = (100u128 << 64) | 1;
+        let before =
+            OpenOrders::from_bytes(&serum::open_orders_fixture(&[(3, a, 7, OrderSide::Sell)]))
+                .unwrap();
+        let after = OpenOrders::from_bytes(&serum::open_orders_fixture(&[])).unwrap();
+
+        let mut report = CancelReport::default();
+        let resolved = report.resolve(&before, &[CancelTarget::ClientId(7)]);
+        report.reconcile(resolved.into_iter().map(|(_, o)| o).collect(), &after);
+        report.signatures.push(Signature::default());
+        assert!(report.is_complete());
+        assert_eq!(report.into_single().unwrap(), Signature::default());
+
+        // 发送前已不在 OpenOrders 中的订单报未找到
+        let mut report = CancelReport::default();
+        assert!(report
+            .resolve(&after, &[CancelTarget::OrderId(a)])
+            .is_empty());
+        assert!(report.is_complete());
+        assert!(report.into_single().is_err());
+    }
 }
@@ -0,0 +1,280 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::task::JoinHandle;
+
+use crate::dexclient::DexClient;
+
+/// crank 任务配置
+#[derive(Debug, Clone)]
+pub struct CrankConfig {
+    pub markets: Vec<String>,          // 市场地址
+    pub interval: Duration,            // 检查间隔
+    pub event_threshold: u64,          // 待处理事件数达到该值才 crank
+    pub consume_limit: u16,            // 单笔 ConsumeEvents 最多处理的事件数
+    pub max_open_orders: usize,        // 单笔交易最多携带的 OpenOrders 账户数，受交易大小限制
+    pub settle_base_threshold: u64,    // 自己的 OpenOrders 中可用 base 达到该原生数量时结算
+    pub settle_quote_threshold: u64,   // 可用 quote 达到该原生数量时结算
+    pub max_fee_lamports: Option<u64>, // 累计交易费上限，超过后停止发送
+}
+
+impl Default for CrankConfig {
+    fn default() -> Self {
+        Self {
+            markets: Vec::new(),
+            interval: Duration::from_secs(2),
+            event_threshold: 8,
+            consume_limit: 32,
+            max_open_orders: 10,
+            settle_base_threshold: 1,
+            settle_quote_threshold: 1,
+            max_fee_lamports: None,
+        }
+    }
+}
+
+impl CrankConfig {
+    /// 需要 crank 时返回本次携带的 OpenOrders 账户数
+    ///
+    /// 账户数超限时只带前面的账户，事件按队列顺序处理，后面的留给下一轮。
+    fn crank_accounts(&self, queue_depth: u64, owners: usize) -> Option<usize> {
+        (queue_depth >= self.event_threshold && owners > 0)
+            .then(|| owners.min(self.max_open_orders))
+    }
+
+    /// 单笔 ConsumeEvents 预计处理的事件数
+    fn events_per_crank(&self, queue_depth: u64) -> u64 {
+        queue_depth.min(self.consume_limit as u64)
+    }
+
+    /// 可用 base 或 quote 达到阈值时需要结算
+    fn settle_due(&self, base_free: u64, quote_free: u64) -> bool {
+        (base_free > 0 && base_free >= self.settle_base_threshold)
+            || (quote_free > 0 && quote_free >= self.settle_quote_threshold)
+    }
+
+    /// 累计费用达到上限后停止发送
+    fn over_budget(&self, fee_lamports: u64) -> bool {
+        self.max_fee_lamports.is_some_and(|max| fee_lamports >= max)
+    }
+}
+
+/// 单个市场的 crank 和结算统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CrankStats {
+    pub market: String,
+    pub cranks: u64,
+    pub events_consumed: u64, // 按发送前队列长度估计
+    pub settles: u64,
+    pub failures: u64,
+    pub fee_lamports: u64,
+    pub last_queue_depth: u64,
+}
+
+/// 后台 crank 任务
+///
+/// 定期检查配置市场的事件队列深度，超过阈值时发送 ConsumeEvents；
+/// 自己的 OpenOrders 可用资金超过阈值时发送 SettleFunds，并累计交易费用。
+pub struct Cranker {
+    client: Arc<DexClient>,
+    config: CrankConfig,
+    stats: Mutex<BTreeMap<String, CrankStats>>,
+}
+
+impl Cranker {
+    pub fn new(client: Arc<DexClient>, config: CrankConfig) -> Self {
+        let stats = config
+            .markets
+            .iter()
+            .map(|market| {
+                let stats = CrankStats {
+                    market: market.clone(),
+                    ..Default::default()
+                };
+                (market.clone(), stats)
+            })
+            .collect();
+        Self {
+            client,
+            config,
+            stats: Mutex::new(stats),
+        }
+    }
+
+    /// 所有市场的统计
+    pub fn stats(&self) -> Vec<CrankStats> {
+        self.stats.lock().unwrap().values().cloned().collect()
+    }
+
+    /// 累计交易费用 (lamports)
+    pub fn total_fees(&self) -> u64 {
+        self.stats
+            .lock()
+            .unwrap()
+            .values()
+            .map(|s| s.fee_lamports)
+            .sum()
+    }
+
+    fn update(&self, market: &str, f: impl FnOnce(&mut CrankStats)) {
+        if let Some(stats) = self.stats.lock().unwrap().get_mut(market) {
+            f(stats);
+        }
+    }
+
+    /// 检查所有市场一轮
+    pub async fn run_once(&self) {
+        for market in &self.config.markets {
+            if self.config.over_budget(self.total_fees()) {
+                log::warn!("crank 费用超出预算 {} lamports，跳过", self.total_fees());
+                return;
+            }
+            self.crank_market(market).await;
+            self.settle_market(market).await;
+        }
+    }
+
+    async fn crank_market(&self, market: &str) {
+        let (depth, owners) = match self
+            .client
+            .pending_events(market, self.config.consume_limit as usize)
+            .await
+        {
+            Ok(pending) => pending,
+            Err(e) => {
+                log::warn!("读取事件队列失败 {}: {}", market, e);
+                self.update(market, |s| s.failures += 1);
+                return;
+            }
+        };
+        self.update(market, |s| s.last_queue_depth = depth);
+        let Some(accounts) = self.config.crank_accounts(depth, owners.len()) else {
+            return;
+        };
+        let owners = &owners[..accounts];
+        match self
+            .client
+            .consume_events(market, owners, self.config.consume_limit)
+            .await
+        {
+            Ok(sent) => {
+                log::info!(
+                    "crank {} 队列 {} 事件 sig={}",
+                    market,
+                    depth,
+                    sent.signature
+                );
+                let consumed = self.config.events_per_crank(depth);
+                self.update(market, |s| {
+                    s.cranks += 1;
+                    s.events_consumed += consumed;
+                    s.fee_lamports += sent.fee;
+                });
+            }
+            Err(e) => {
+                log::warn!("crank 失败 {}: {}", market, e);
+                self.update(market, |s| s.failures += 1);
+            }
+        }
+    }
+
+    async fn settle_market(&self, market: &str) {
+        let open_orders = async {
+            let accounts = self.client.market_accounts(&market.parse()?).await?;
+            match self.client.open_orders_account(&accounts).await? {
+                Some(open_orders) => Ok(Some(self.client.load_open_orders(&open_orders).await?)),
+                None => Ok::<_, anyhow::Error>(None),
+            }
+        }
+        .await;
+        let open_orders = match open_orders {
+            Ok(Some(open_orders)) => open_orders,
+            Ok(None) => return,
+            Err(e) => {
+                log::warn!("读取 OpenOrders 失败 {}: {}", market, e);
+                self.update(market, |s| s.failures += 1);
+                return;
+            }
+        };
+        if !self
+            .config
+            .settle_due(open_orders.base_free, open_orders.quote_free)
+        {
+            return;
+        }
+
+        match self.client.settle_funds(market).await {
+            Ok(Some(sent)) => self.update(market, |s| {
+                s.settles += 1;
+                s.fee_lamports += sent.fee;
+            }),
+            Ok(None) => {}
+            Err(e) => {
+                log::warn!("结算失败 {}: {}", market, e);
+                self.update(market, |s| s.failures += 1);
+            }
+        }
+    }
+
+    /// 启动后台 crank 任务
+    pub fn spawn_cranker(self: &Arc<Self>) -> JoinHandle<()> {
+        let cranker = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(cranker.config.interval);
+            loop {
+                interval.tick().await;
+                cranker.run_once().await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cranks_only_at_threshold_with_capped_accounts() {
+        let config = CrankConfig {
+            event_threshold: 8,
+            consume_limit: 32,
+            max_open_orders: 3,
+            ..Default::default()
+        };
+        assert_eq!(config.crank_accounts(7, 2), None);
+        assert_eq!(config.crank_accounts(8, 0), None);
+        assert_eq!(config.crank_accounts(8, 2), Some(2));
+        assert_eq!(config.crank_accounts(100, 5), Some(3));
+
+        assert_eq!(config.events_per_crank(8), 8);
+        assert_eq!(config.events_per_crank(100), 32);
+    }
+
+    #[test]
+    fn settles_when_either_side_reaches_threshold() {
+        let config = CrankConfig {
+            settle_base_threshold: 10,
+            settle_quote_threshold: 0,
+            ..Default::default()
+        };
+        assert!(!config.settle_due(9, 0));
+        assert!(config.settle_due(10, 0));
+        // 阈值为 0 时也要求有可用资金
+        assert!(!config.settle_due(0, 0));
+        assert!(config.settle_due(0, 1));
+    }
+
+    #[test]
+    fn stops_at_fee_budget() {
+        let config = CrankConfig {
+            max_fee_lamports: Some(10_000),
+            ..Default::default()
+        };
+        assert!(!config.over_budget(9_999));
+        assert!(config.over_budget(10_000));
+        assert!(!CrankConfig::default().over_budget(u64::MAX));
+    }
+}
@@ -0,0 +1,231 @@
+use chrono::Utc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::task::JoinHandle;
+
+use crate::dex_collect::fixed::{self, Decimal};
+use crate::dex_collect::serum::serum_events::FillEvent;
+use crate::dex_collect::serum::serum_slippage::TradeSide;
+use crate::dex_collect::stream::update::BookSide;
+use crate::dexclient::{DexClient, OrderOptions, OrderSide};
+use crate::executer::pnl::PnlTracker;
+use crate::executer::serum::{self, MarketAccounts, SelfTradeBehavior};
+use crate::strategy::runtime::{
+    executor_channel, ExecutionReport, ExecutorEndpoint, ExecutorHandle, Fill, OrderCommand,
+    OrderType,
+};
+
+/// 轮询事件队列查找自己成交的默认间隔
+const FILL_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 实盘执行器
+///
+/// 在后台任务中把策略指令转成 [`DexClient`] 调用，市场名称按 `markets` 映射到市场地址。
+/// 同时定期读取各市场事件队列中属于自己 OpenOrders 的成交，回报 `Filled` 并累计盈亏。
+pub struct LiveExecutor {
+    client: DexClient,
+    markets: HashMap<String, String>, // 市场名称 -> 市场地址
+    poll_interval: Duration,
+    fill_seqs: HashMap<String, u64>,  // 各市场已处理的最新事件序号
+    remaining: HashMap<u64, Decimal>, // 客户端订单号 -> 未成交数量
+    pnl: Arc<Mutex<PnlTracker>>,
+}
+
+impl LiveExecutor {
+    pub fn new(client: DexClient, markets: HashMap<String, String>) -> Self {
+        Self {
+            client,
+            markets,
+            poll_interval: FILL_POLL_INTERVAL,
+            fill_seqs: HashMap::new(),
+            remaining: HashMap::new(),
+            pnl: Arc::new(Mutex::new(PnlTracker::new())),
+        }
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// 实盘成交累计的盈亏，任务启动后仍可读取
+    pub fn pnl(&self) -> Arc<Mutex<PnlTracker>> {
+        Arc::clone(&self.pnl)
+    }
+
+    /// 启动后台任务，返回交给运行时的执行器句柄
+    pub fn spawn(self, buffer: usize) -> (ExecutorHandle, JoinHandle<()>) {
+        let (handle, endpoint) = executor_channel(buffer);
+        let task = tokio::spawn(self.run(endpoint));
+        (handle, task)
+    }
+
+    async fn run(mut self, mut endpoint: ExecutorEndpoint) {
+        // 第一次轮询只记下各市场当前序号，之后的成交才回报
+        let mut poll = tokio::time::interval(self.poll_interval);
+        loop {
+            let reports = tokio::select! {
+                command = endpoint.commands.recv() => match command {
+                    Some(command) => self.execute(command).await,
+                    None => return,
+                },
+                _ = poll.tick() => self.poll_fills().await,
+            };
+            for report in reports {
+                if endpoint.reports.send(report).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// 读取各市场的新成交
+    async fn poll_fills(&mut self) -> Vec<ExecutionReport> {
+        let mut reports = Vec::new();
+        let markets: Vec<(String, String)> = self.markets.clone().into_iter().collect();
+        for (name, address) in &markets {
+            let after = self.fill_seqs.get(name).copied();
+            let own = match self.client.own_fills(address, after).await {
+                Ok(own) => own,
+                Err(e) => {
+                    log::warn!("读取成交失败 {}: {}", name, e);
+                    continue;
+                }
+            };
+            self.fill_seqs.insert(name.clone(), own.last_seq);
+            for event in &own.fills {
+                let Some(fill) = self.fill(name, &own.market, event) else {
+                    continue;
+                };
+                self.pnl.lock().unwrap().on_fill(&fill);
+                reports.push(ExecutionReport::Filled(fill));
+            }
+        }
+        reports
+    }
+
+    /// 把事件队列中的成交换算成回报，更新订单剩余数量
+    fn fill(&mut self, name: &str, market: &MarketAccounts, event: &FillEvent) -> Option<Fill> {
+        let price = event.price(market.base_decimals, market.quote_decimals)?;
+        let size = event.size(market.base_decimals)?;
+        // maker 的手续费字段是返佣
+        let fee = fixed::from_raw(event.native_fee_or_rebate, market.quote_decimals)?;
+        let fee = if event.maker { -fee } else { fee };
+        let remaining = match self.remaining.get_mut(&event.client_order_id) {
+            Some(remaining) => {
+                *remaining = (*remaining - size).max(Decimal::ZERO);
+                *remaining
+            }
+            None => Decimal::ZERO,
+        };
+        if remaining.is_zero() {
+            self.remaining.remove(&event.client_order_id);
+        }
+        Some(Fill {
+            client_id: event.client_order_id,
+            market: name.to_string(),
+            side: match event.side {
+                BookSide::Bids => TradeSide::Buy,
+                BookSide::Asks => TradeSide::Sell,
+            },
+            price,
+            size,
+            fee,
+            remaining,
+            time: Utc::now(),
+        })
+    }
+
+    async fn execute(&mut self, command: OrderCommand) -> Vec<ExecutionReport> {
+        match command {
+            OrderCommand::Place(order) => {
+                let Some(address) = self.markets.get(&order.market) else {
+                    return vec![ExecutionReport::Rejected {
+                        client_id: order.client_id,
+                        reason: format!("Unknown market {}", order.market),
+                    }];
+                };
+                let side = match order.side {
+                    TradeSide::Buy => OrderSide::Buy,
+                    TradeSide::Sell => OrderSide::Sell,
+                };
+                if order.client_id == 0 {
+                    return vec![ExecutionReport::Rejected {
+                        client_id: order.client_id,
+                        reason: "Client id must be non-zero".to_string(),
+                    }];
+                }
+                let options = OrderOptions {
+                    order_type: match order.order_type {
+                        OrderType::Limit => serum::OrderType::Limit,
+                        OrderType::ImmediateOrCancel => serum::OrderType::ImmediateOrCancel,
+                        OrderType::PostOnly => serum::OrderType::PostOnly,
+                    },
+                    self_trade: SelfTradeBehavior::CancelProvide,
+                    client_order_id: order.client_id,
+                };
+                vec![match self
+                    .client
+                    .place_limit_order(address, side, order.price, order.size, options)
+                    .await
+                {
+                    Ok(_) => {
+                        self.remaining.insert(order.client_id, order.size);
+                        ExecutionReport::Accepted {
+                            client_id: order.client_id,
+                            market: order.market,
+                        }
+                    }
+                    Err(e) => ExecutionReport::Rejected {
+                        client_id: order.client_id,
+                        reason: e.to_string(),
+                    },
+                }]
+            }
+            OrderCommand::Cancel { market, client_id } => {
+                let Some(address) = self.markets.get(&market) else {
+                    return Vec::new();
+                };
+                match self
+                    .client
+                    .cancel_order_by_client_id(address, client_id)
+                    .await
+                {
+                    Ok(_) => {
+                        self.remaining.remove(&client_id);
+                        vec![ExecutionReport::Cancelled { client_id, market }]
+                    }
+                    Err(e) => {
+                        log::warn!("撤单失败 {} {}: {}", market, client_id, e);
+                        Vec::new()
+                    }
+                }
+            }
+            OrderCommand::CancelAll { market } => {
+                let Some(address) = self.markets.get(&market) else {
+                    return Vec::new();
+                };
+                match self.client.cancel_all(address).await {
+                    Ok(report) => report
+                        .cancelled
+                        .iter()
+                        .map(|o| {
+                            self.remaining.remove(&o.client_order_id);
+                            ExecutionReport::Cancelled {
+                                client_id: o.client_order_id,
+                                market: market.clone(),
+                            }
+                        })
+                        .collect(),
+                    Err(e) => {
+                        log::warn!("全部撤单失败 {}: {}", market, e);
+                        Vec::new()
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,6 @@
+pub mod crank;
+pub mod live;
+pub mod paper;
+pub mod pnl;
+pub mod serum;
+pub mod trade;
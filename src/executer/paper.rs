@@ -0,0 +1,798 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use crate::dex_collect::fixed::{Decimal, BPS};
+use crate::dex_collect::serum::serum_depth::MarketDepth;
+use crate::dex_collect::serum::serum_slippage::TradeSide;
+use crate::executer::pnl::{PnlReport, PnlTracker};
+use crate::strategy::quote::Liquidity;
+use crate::strategy::runtime::{
+    executor_channel, ExecutionReport, ExecutorEndpoint, ExecutorHandle, Fill, MarketEvent,
+    OrderCommand, OrderRequest, OrderType, Trade,
+};
+
+/// AMM 限价成交数量的二分次数
+const AMM_SEARCH_STEPS: usize = 40;
+
+/// 纸面订单状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderState {
+    Pending, // 模拟延迟中，尚未到达交易所
+    Open,
+    PartiallyFilled,
+    Filled,
+    Cancelled,
+    Rejected,
+}
+
+impl OrderState {
+    pub fn is_live(&self) -> bool {
+        matches!(
+            self,
+            OrderState::Pending | OrderState::Open | OrderState::PartiallyFilled
+        )
+    }
+}
+
+/// 纸面订单
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaperOrder {
+    pub request: OrderRequest,
+    pub state: OrderState,
+    pub filled: Decimal,
+    pub activate_at: DateTime<Utc>,
+    pub cancel_at: Option<DateTime<Utc>>,
+    pub queue_ahead: Option<Decimal>, // 排队模型下同价位排在前面的数量
+}
+
+impl PaperOrder {
+    pub fn remaining(&self) -> Decimal {
+        self.request.size - self.filled
+    }
+}
+
+/// 纸面执行配置
+#[derive(Debug, Clone)]
+pub struct PaperConfig {
+    pub latency: Duration,                  // 下单和撤单到达交易所的延迟
+    pub fee_bps: Option<Decimal>,           // 覆盖市场自身费率，None 时使用流动性中的费率
+    pub balances: HashMap<String, Decimal>, // 初始余额，代币符号 -> 数量
+    pub queue_model: bool,                  // 订单簿挂单按排队位置和成交记录撮合
+}
+
+impl Default for PaperConfig {
+    fn default() -> Self {
+        Self {
+            latency: Duration::milliseconds(400),
+            fee_bps: None,
+            balances: HashMap::new(),
+            queue_model: false,
+        }
+    }
+}
+
+/// 一次撮合的结果
+struct Execution {
+    base: Decimal,
+    notional: Decimal, // 不含手续费的 quote 成交额
+    fee: Decimal,
+    levels: Vec<(Decimal, Decimal)>, // 订单簿上吃掉的 (价格, 数量)，AMM 为空
+}
+
+/// 已被模拟成交吃掉的订单簿数量，(是否吃卖盘, 价格) -> 数量
+type ConsumedLevels = BTreeMap<(bool, Decimal), Decimal>;
+
+/// 模拟交易所
+///
+/// 用最新的订单簿深度和 AMM 储备撮合订单，维护模拟余额和订单状态。
+/// 订单在延迟结束后才生效；能立即成交的部分按对手盘价格成交，
+/// 挂单在之后行情穿过限价时按限价成交。模拟成交吃掉的订单簿数量在下一次
+/// 订单簿更新前不会被重复成交，AMM 成交后本地储备随之变化。
+#[derive(Debug, Clone)]
+pub struct PaperExchange {
+    config: PaperConfig,
+    liquidity: HashMap<String, Liquidity>,
+    consumed: HashMap<String, ConsumedLevels>, // 收到新的订单簿前已被模拟成交吃掉的数量
+    balances: HashMap<String, Decimal>,
+    orders: BTreeMap<u64, PaperOrder>,
+    pnl: PnlTracker,
+}
+
+impl PaperExchange {
+    pub fn new(config: PaperConfig) -> Self {
+        Self {
+            balances: config.balances.clone(),
+            config,
+            liquidity: HashMap::new(),
+            consumed: HashMap::new(),
+            orders: BTreeMap::new(),
+            pnl: PnlTracker::new(),
+        }
+    }
+
+    pub fn balances(&self) -> &HashMap<String, Decimal> {
+        &self.balances
+    }
+
+    pub fn orders(&self) -> impl Iterator<Item = &PaperOrder> {
+        self.orders.values()
+    }
+
+    pub fn order(&self, client_id: u64) -> Option<&PaperOrder> {
+        self.orders.get(&client_id)
+    }
+
+    pub fn liquidity(&self, market: &str) -> Option<&Liquidity> {
+        self.liquidity.get(market)
+    }
+
+    /// 以各市场当前中间价为标记价格的盈亏报告
+    pub fn pnl_report(&self) -> PnlReport {
+        let marks = self
+            .liquidity
+            .iter()
+            .filter_map(|(market, liquidity)| Some((market.clone(), liquidity.mid()?)))
+            .collect();
+        self.pnl.report(&marks)
+    }
+
+    /// 更新市场流动性并撮合
+    pub fn on_market_event(
+        &mut self,
+        event: &MarketEvent,
+        now: DateTime<Utc>,
+    ) -> Vec<ExecutionReport> {
+        let mut reports = Vec::new();
+        match event {
+            MarketEvent::Book {
+                market, liquidity, ..
+            } => {
+                self.liquidity.insert(market.clone(), liquidity.clone());
+                self.consumed.remove(market);
+                self.update_queues(market);
+            }
+            MarketEvent::Trades { market, trades, .. } if self.config.queue_model => {
+                for trade in trades {
+                    reports.extend(self.match_trade(market, trade, now));
+                }
+            }
+            _ => {}
+        }
+        reports.extend(self.process(now));
+        reports
+    }
+
+    /// 同价位数量减少时，排在前面的订单视为已撤或已成交
+    fn update_queues(&mut self, market: &str) {
+        let Some(Liquidity::OrderBook { depth, .. }) = self.liquidity.get(market) else {
+            return;
+        };
+        for order in self.orders.values_mut() {
+            if order.request.market != market || !order.state.is_live() {
+                continue;
+            }
+            if let Some(ahead) = order.queue_ahead.as_mut() {
+                *ahead = (*ahead).min(level_size(depth, order.request.side, order.request.price));
+            }
+        }
+    }
+
+    /// 用一笔公开成交撮合排队中的挂单
+    ///
+    /// 成交价等于挂单价时先消耗排在前面的数量；成交价穿过挂单价说明该价位已被吃完。
+    /// 多笔挂单按价格优先、下单先后分配同一笔成交，分到的数量合计不超过成交量。
+    fn match_trade(
+        &mut self,
+        market: &str,
+        trade: &Trade,
+        now: DateTime<Utc>,
+    ) -> Vec<ExecutionReport> {
+        let fee_rate = self.fee_bps(market) / BPS;
+        let mut orders: Vec<(Decimal, u64)> = self
+            .orders
+            .values()
+            .filter(|o| {
+                o.request.market == market
+                    && matches!(o.state, OrderState::Open | OrderState::PartiallyFilled)
+                    && o.queue_ahead.is_some()
+                    && o.request.side != trade.taker_side
+            })
+            .map(|o| (o.request.price, o.request.client_id))
+            .collect();
+        // 买单高价优先，卖单低价优先
+        if trade.taker_side == TradeSide::Sell {
+            orders.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        } else {
+            orders.sort();
+        }
+
+        let mut left = trade.size;
+        let mut reports = Vec::new();
+        for (_, id) in orders {
+            if left <= Decimal::ZERO {
+                break;
+            }
+            let Some(order) = self.orders.get_mut(&id) else {
+                continue;
+            };
+            let limit = order.request.price;
+            let through = match order.request.side {
+                TradeSide::Buy => trade.price < limit,
+                TradeSide::Sell => trade.price > limit,
+            };
+            if trade.price != limit && !through {
+                continue;
+            }
+            let ahead = order.queue_ahead.unwrap_or_default();
+            // 排在前面的是公开订单，各挂单共享；分给前面挂单的数量从剩余成交量中扣除
+            let available = if through {
+                left
+            } else {
+                (left - ahead).max(Decimal::ZERO)
+            };
+            order.queue_ahead = Some(if through {
+                Decimal::ZERO
+            } else {
+                (ahead - trade.size).max(Decimal::ZERO)
+            });
+            let base = available.min(order.remaining());
+            if base <= Decimal::ZERO {
+                continue;
+            }
+            left -= base;
+            let notional = base * limit;
+            let execution = Execution {
+                base,
+                notional,
+                fee: notional * fee_rate,
+                levels: Vec::new(),
+            };
+            reports.extend(self.apply_execution(id, execution, now));
+        }
+        reports
+    }
+
+    /// 接收策略指令，生效时间为 now + latency
+    pub fn submit(&mut self, command: OrderCommand, now: DateTime<Utc>) -> Vec<ExecutionReport> {
+        let effective = now + self.config.latency;
+        match command {
+            OrderCommand::Place(request) => {
+                if request.size <= Decimal::ZERO || request.price <= Decimal::ZERO {
+                    return vec![ExecutionReport::Rejected {
+                        client_id: request.client_id,
+                        reason: "Invalid price or size".to_string(),
+                    }];
+                }
+                if self.orders.contains_key(&request.client_id) {
+                    return vec![ExecutionReport::Rejected {
+                        client_id: request.client_id,
+                        reason: "Duplicate client id".to_string(),
+                    }];
+                }
+                self.orders.insert(
+                    request.client_id,
+                    PaperOrder {
+                        request,
+                        state: OrderState::Pending,
+                        filled: Decimal::ZERO,
+                        activate_at: effective,
+                        cancel_at: None,
+                        queue_ahead: None,
+                    },
+                );
+            }
+            OrderCommand::Cancel { client_id, .. } => {
+                if let Some(order) = self.orders.get_mut(&client_id) {
+                    if order.state.is_live() {
+                        order.cancel_at.get_or_insert(effective);
+                    }
+                }
+            }
+            OrderCommand::CancelAll { market } => {
+                for order in self.orders.values_mut() {
+                    if order.request.market == market && order.state.is_live() {
+                        order.cancel_at.get_or_insert(effective);
+                    }
+                }
+            }
+        }
+        self.process(now)
+    }
+
+    /// 处理到期的下单、撤单并撮合挂单
+    pub fn process(&mut self, now: DateTime<Utc>) -> Vec<ExecutionReport> {
+        let mut reports = Vec::new();
+        let ids: Vec<u64> = self
+            .orders
+            .iter()
+            .filter(|(_, order)| order.state.is_live())
+            .map(|(id, _)| *id)
+            .collect();
+        for id in ids {
+            let Some(order) = self.orders.get(&id) else {
+                continue;
+            };
+            if order.activate_at > now {
+                continue;
+            }
+            // 撤单先于下单到达时直接撤销
+            if order.cancel_at.is_some_and(|t| t <= order.activate_at) {
+                reports.push(self.cancel(id));
+                continue;
+            }
+
+            let taker = order.state == OrderState::Pending;
+            if taker {
+                if let Err(reason) = self.check_funds(id) {
+                    self.set_state(id, OrderState::Rejected);
+                    reports.push(ExecutionReport::Rejected {
+                        client_id: id,
+                        reason,
+                    });
+                    continue;
+                }
+                let order = &self.orders[&id];
+                if order.request.order_type == OrderType::PostOnly
+                    && self.execution(order, true).is_some()
+                {
+                    self.set_state(id, OrderState::Rejected);
+                    reports.push(ExecutionReport::Rejected {
+                        client_id: id,
+                        reason: "Post-only order would cross".to_string(),
+                    });
+                    continue;
+                }
+                self.set_state(id, OrderState::Open);
+                reports.push(ExecutionReport::Accepted {
+                    client_id: id,
+                    market: self.orders[&id].request.market.clone(),
+                });
+            }
+
+            // 排队模型下挂单只由公开成交撮合，不再按订单簿穿价成交
+            if taker || self.orders[&id].queue_ahead.is_none() {
+                reports.extend(self.match_order(id, taker, now));
+            }
+            if taker {
+                self.join_queue(id);
+            }
+
+            let order = &self.orders[&id];
+            if !order.state.is_live() {
+                continue;
+            }
+            let ioc = order.request.order_type == OrderType::ImmediateOrCancel;
+            if ioc || order.cancel_at.is_some_and(|t| t <= now) {
+                reports.push(self.cancel(id));
+            }
+        }
+        reports
+    }
+
+    /// 排队模型下，挂单排在同价位现有数量之后
+    fn join_queue(&mut self, id: u64) {
+        if !self.config.queue_model {
+            return;
+        }
+        let Some(order) = self.orders.get(&id) else {
+            return;
+        };
+        let Some(Liquidity::OrderBook { depth, .. }) = self.liquidity.get(&order.request.market)
+        else {
+            return;
+        };
+        let ahead = level_size(depth, order.request.side, order.request.price);
+        if let Some(order) = self.orders.get_mut(&id) {
+            if order.state.is_live() {
+                order.queue_ahead = Some(ahead);
+            }
+        }
+    }
+
+    fn set_state(&mut self, id: u64, state: OrderState) {
+        if let Some(order) = self.orders.get_mut(&id) {
+            order.state = state;
+        }
+    }
+
+    fn cancel(&mut self, id: u64) -> ExecutionReport {
+        self.set_state(id, OrderState::Cancelled);
+        ExecutionReport::Cancelled {
+            client_id: id,
+            market: self.orders[&id].request.market.clone(),
+        }
+    }
+
+    /// 检查余额是否足以覆盖该订单和其他在途订单
+    fn check_funds(&self, id: u64) -> Result<(), String> {
+        let order = &self.orders[&id];
+        let (base, quote) = split_market(&order.request.market)
+            .ok_or_else(|| format!("Invalid market {}", order.request.market))?;
+        let (token, required) = self.required(order, base, quote);
+        let reserved: Decimal = self
+            .orders
+            .values()
+            .filter(|o| {
+                o.request.client_id != id
+                    && matches!(o.state, OrderState::Open | OrderState::PartiallyFilled)
+            })
+            .filter_map(|o| {
+                let (b, q) = split_market(&o.request.market)?;
+                let (t, amount) = self.required(o, b, q);
+                (t == token).then_some(amount)
+            })
+            .sum();
+        let available = self.balances.get(token).copied().unwrap_or_default() - reserved;
+        if available < required {
+            return Err(format!(
+                "Insufficient {} balance: required {}, available {}",
+                token, required, available
+            ));
+        }
+        Ok(())
+    }
+
+    /// 订单剩余部分需要占用的代币和数量
+    fn required<'a>(
+        &self,
+        order: &PaperOrder,
+        base: &'a str,
+        quote: &'a str,
+    ) -> (&'a str, Decimal) {
+        match order.request.side {
+            TradeSide::Buy => {
+                let fee_bps = self.fee_bps(&order.request.market);
+                let notional = order.request.price * order.remaining();
+                (quote, notional + notional * fee_bps / BPS)
+            }
+            TradeSide::Sell => (base, order.remaining()),
+        }
+    }
+
+    fn fee_bps(&self, market: &str) -> Decimal {
+        self.config
+            .fee_bps
+            .or_else(|| self.liquidity.get(market).map(|l| l.fee_bps()))
+            .unwrap_or_default()
+    }
+
+    /// 按当前流动性撮合一笔订单，成交会从本地流动性快照中扣除
+    fn match_order(&mut self, id: u64, taker: bool, now: DateTime<Utc>) -> Option<ExecutionReport> {
+        let order = self.orders.get(&id)?;
+        let execution = self.execution(order, taker)?;
+        let (market, side) = (order.request.market.clone(), order.request.side);
+        self.consume(&market, side, &execution);
+        self.apply_execution(id, execution, now)
+    }
+
+    /// 扣除模拟成交用掉的流动性，直到下一次订单簿更新
+    ///
+    /// 订单簿记录各档被吃掉的数量；AMM 直接按成交更新本地储备。
+    fn consume(&mut self, market: &str, side: TradeSide, execution: &Execution) {
+        match self.liquidity.get_mut(market) {
+            Some(Liquidity::OrderBook { .. }) => {
+                let consumed = self.consumed.entry(market.to_string()).or_default();
+                for (price, size) in &execution.levels {
+                    *consumed
+                        .entry((side == TradeSide::Buy, *price))
+                        .or_default() += *size;
+                }
+            }
+            Some(Liquidity::ConstantProduct {
+                base_reserve,
+                quote_reserve,
+                ..
+            }) => match side {
+                // 手续费留在池中
+                TradeSide::Buy => {
+                    *base_reserve -= execution.base;
+                    *quote_reserve += execution.notional + execution.fee;
+                }
+                TradeSide::Sell => {
+                    *base_reserve += execution.base;
+                    *quote_reserve -= execution.notional - execution.fee;
+                }
+            },
+            None => {}
+        }
+    }
+
+    /// 记入成交: 更新余额、订单状态和盈亏
+    fn apply_execution(
+        &mut self,
+        id: u64,
+        execution: Execution,
+        now: DateTime<Utc>,
+    ) -> Option<ExecutionReport> {
+        let order = self.orders.get(&id)?;
+        let (base, quote) = split_market(&order.request.market)?;
+        let (base, quote) = (base.to_string(), quote.to_string());
+        let side = order.request.side;
+
+        let signed = match side {
+            TradeSide::Buy => (execution.base, -execution.notional - execution.fee),
+            TradeSide::Sell => (-execution.base, execution.notional - execution.fee),
+        };
+        *self.balances.entry(base).or_default() += signed.0;
+        *self.balances.entry(quote).or_default() += signed.1;
+
+        let order = self.orders.get_mut(&id)?;
+        order.filled += execution.base;
+        order.state = if order.remaining() <= Decimal::ZERO {
+            OrderState::Filled
+        } else {
+            OrderState::PartiallyFilled
+        };
+        let fill = Fill {
+            client_id: id,
+            market: order.request.market.clone(),
+            side,
+            price: execution.notional / execution.base,
+            size: execution.base,
+            fee: execution.fee,
+            remaining: order.remaining(),
+            time: now,
+        };
+        self.pnl.on_fill(&fill);
+        Some(ExecutionReport::Filled(fill))
+    }
+
+    /// 订单剩余部分在当前流动性下可成交的数量，不可成交时返回 None
+    ///
+    /// 订单簿上吃单按各档价格成交，挂单按自身限价成交；AMM 按精确数学求
+    /// 均价不劣于限价的最大数量。
+    fn execution(&self, order: &PaperOrder, taker: bool) -> Option<Execution> {
+        let liquidity = self.liquidity.get(&order.request.market)?;
+        let fee_rate = self.fee_bps(&order.request.market) / BPS;
+        let limit = order.request.price;
+        let side = order.request.side;
+        let remaining = order.remaining();
+
+        let (base, notional, fee, levels) = match liquidity {
+            Liquidity::OrderBook { depth, .. } => {
+                let consumed = self.consumed.get(&order.request.market);
+                let levels = match side {
+                    TradeSide::Buy => &depth.asks,
+                    TradeSide::Sell => &depth.bids,
+                };
+                let mut left = remaining;
+                let mut notional = Decimal::ZERO;
+                let mut taken = Vec::new();
+                for level in levels {
+                    let crosses = match side {
+                        TradeSide::Buy => level.price <= limit,
+                        TradeSide::Sell => level.price >= limit,
+                    };
+                    if !crosses || left <= Decimal::ZERO {
+                        break;
+                    }
+                    let used = consumed
+                        .and_then(|c| c.get(&(side == TradeSide::Buy, level.price)))
+                        .copied()
+                        .unwrap_or_default();
+                    let size = (level.size - used).max(Decimal::ZERO).min(left);
+                    if size <= Decimal::ZERO {
+                        continue;
+                    }
+                    notional += size * if taker { level.price } else { limit };
+                    left -= size;
+                    taken.push((level.price, size));
+                }
+                let base = remaining - left;
+                (base, notional, notional * fee_rate, taken)
+            }
+            Liquidity::ConstantProduct { .. } => {
+                let within = |base: Decimal| {
+                    let quote = liquidity.quote_base(side, base)?;
+                    let price = quote.avg_price()?;
+                    match side {
+                        TradeSide::Buy => (price <= limit).then_some(quote),
+                        TradeSide::Sell => (price >= limit).then_some(quote),
+                    }
+                };
+                let quote = match within(remaining) {
+                    Some(quote) => quote,
+                    None => {
+                        let (mut lo, mut hi) = (Decimal::ZERO, remaining);
+                        for _ in 0..AMM_SEARCH_STEPS {
+                            let mid = (lo + hi) / Decimal::TWO;
+                            if within(mid).is_some() {
+                                lo = mid;
+                            } else {
+                                hi = mid;
+                            }
+                        }
+                        within(lo)?
+                    }
+                };
+                // 池子手续费已计入报价，换算出不含手续费的成交额
+                let notional = match side {
+                    TradeSide::Buy => quote.quote - quote.fee,
+                    TradeSide::Sell => quote.quote + quote.fee,
+                };
+                (quote.base, notional, quote.fee, Vec::new())
+            }
+        };
+        (base > Decimal::ZERO).then_some(Execution {
+            base,
+            notional,
+            fee,
+            levels,
+        })
+    }
+}
+
+/// 订单所在一侧指定价位的挂单数量
+fn level_size(depth: &MarketDepth, side: TradeSide, price: Decimal) -> Decimal {
+    let levels = match side {
+        TradeSide::Buy => &depth.bids,
+        TradeSide::Sell => &depth.asks,
+    };
+    levels
+        .iter()
+        .filter(|l| l.price == price)
+        .map(|l| l.size)
+        .sum()
+}
+
+/// "SOL/USDC" -> ("SOL", "USDC")，忽略名称中空格后的场所后缀
+fn split_market(market: &str) -> Option<(&str, &str)> {
+    let (base, quote) = market.split_once('/')?;
+    Some((base.trim(), quote.split_whitespace().next()?))
+}
+
+/// 纸面执行器
+///
+/// 在后台任务中运行 [`PaperExchange`]，接口与实盘执行器相同。
+pub struct PaperExecutor {
+    exchange: PaperExchange,
+    tick: std::time::Duration,
+}
+
+impl PaperExecutor {
+    pub fn new(config: PaperConfig) -> Self {
+        Self {
+            exchange: PaperExchange::new(config),
+            tick: std::time::Duration::from_millis(50),
+        }
+    }
+
+    /// 启动后台任务，`events` 为与策略相同的行情，任务结束时返回模拟交易所
+    pub fn spawn(
+        self,
+        events: mpsc::Receiver<MarketEvent>,
+        buffer: usize,
+    ) -> (ExecutorHandle, JoinHandle<PaperExchange>) {
+        let (handle, endpoint) = executor_channel(buffer);
+        let task = tokio::spawn(self.run(endpoint, events));
+        (handle, task)
+    }
+
+    async fn run(
+        mut self,
+        mut endpoint: ExecutorEndpoint,
+        mut events: mpsc::Receiver<MarketEvent>,
+    ) -> PaperExchange {
+        let mut tick = tokio::time::interval(self.tick);
+        loop {
+            let reports = tokio::select! {
+                command = endpoint.commands.recv() => match command {
+                    Some(command) => self.exchange.submit(command, Utc::now()),
+                    None => break,
+                },
+                Some(event) = events.recv() => self.exchange.on_market_event(&event, Utc::now()),
+                _ = tick.tick() => self.exchange.process(Utc::now()),
+            };
+            for report in reports {
+                if endpoint.reports.send(report).await.is_err() {
+                    return self.exchange;
+                }
+            }
+        }
+        self.exchange
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dex_collect::serum::serum_depth::Level;
+    use crate::dex_collect::stamp::SnapshotStamp;
+
+    const MARKET: &str = "SOL/USDC";
+
+    fn level(price: i64, size: i64) -> Level {
+        Level {
+            price: Decimal::from(price),
+            size: Decimal::from(size),
+            total: Decimal::from(size),
+        }
+    }
+
+    fn book(bids: Vec<Level>, asks: Vec<Level>, slot: u64) -> MarketEvent {
+        MarketEvent::Book {
+            market: MARKET.to_string(),
+            liquidity: Liquidity::OrderBook {
+                depth: MarketDepth {
+                    bids,
+                    asks,
+                    spread: Decimal::ZERO,
+                    total_bid_size: Decimal::ZERO,
+                    total_ask_size: Decimal::ZERO,
+                    stamp: SnapshotStamp::new(slot, "test"),
+                },
+                fee_bps: Decimal::ZERO,
+            },
+            stamp: SnapshotStamp::new(slot, "test"),
+        }
+    }
+
+    fn exchange(queue_model: bool) -> PaperExchange {
+        PaperExchange::new(PaperConfig {
+            latency: Duration::zero(),
+            balances: HashMap::from([
+                ("SOL".to_string(), Decimal::from(1_000)),
+                ("USDC".to_string(), Decimal::from(100_000)),
+            ]),
+            queue_model,
+            ..Default::default()
+        })
+    }
+
+    fn place(id: u64, side: TradeSide, price: i64, size: i64) -> OrderCommand {
+        OrderCommand::Place(OrderRequest {
+            client_id: id,
+            market: MARKET.to_string(),
+            side,
+            price: Decimal::from(price),
+            size: Decimal::from(size),
+            order_type: OrderType::Limit,
+        })
+    }
+
+    #[test]
+    fn resting_order_does_not_refill_same_snapshot() {
+        let mut exchange = exchange(false);
+        let now = Utc::now();
+        exchange.on_market_event(&book(vec![], vec![level(10, 1)], 1), now);
+        exchange.submit(place(1, TradeSide::Buy, 10, 100), now);
+        for _ in 0..100 {
+            exchange.process(now);
+        }
+        assert_eq!(exchange.order(1).unwrap().filled, Decimal::ONE);
+
+        // 新的订单簿快照到达后才能再次成交
+        exchange.on_market_event(&book(vec![], vec![level(10, 1)], 2), now);
+        assert_eq!(exchange.order(1).unwrap().filled, Decimal::TWO);
+    }
+
+    #[test]
+    fn queue_model_splits_trade_across_orders() {
+        let mut exchange = exchange(true);
+        let now = Utc::now();
+        exchange.on_market_event(&book(vec![level(9, 5)], vec![level(11, 5)], 1), now);
+        exchange.submit(place(1, TradeSide::Buy, 9, 3), now);
+        exchange.submit(place(2, TradeSide::Buy, 9, 3), now);
+
+        // 订单簿穿价不触发排队中的挂单
+        exchange.on_market_event(&book(vec![level(9, 5)], vec![level(9, 1)], 2), now);
+        assert_eq!(exchange.order(1).unwrap().filled, Decimal::ZERO);
+
+        // 前面 5 个先成交，剩下 2 个只分给先下的订单
+        let trades = MarketEvent::Trades {
+            market: MARKET.to_string(),
+            trades: vec![Trade {
+                price: Decimal::from(9),
+                size: Decimal::from(7),
+                taker_side: TradeSide::Sell,
+                time: now,
+            }],
+            stamp: SnapshotStamp::new(3, "test"),
+        };
+        exchange.on_market_event(&trades, now);
+        assert_eq!(exchange.order(1).unwrap().filled, Decimal::TWO);
+        assert_eq!(exchange.order(2).unwrap().filled, Decimal::ZERO);
+    }
+}
@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::dex_collect::fixed::Decimal;
+use crate::dex_collect::serum::serum_slippage::TradeSide;
+use crate::strategy::runtime::Fill;
+
+/// 单个市场的盈亏
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MarketPnl {
+    pub market: String,
+    pub position: Decimal,           // base 持仓，空头为负
+    pub avg_price: Decimal,          // 持仓均价
+    pub realized: Decimal,           // 已实现盈亏，未扣手续费
+    pub unrealized: Option<Decimal>, // 按标记价格计算，无标记价格时为 None
+    pub fees: Decimal,
+    pub volume: Decimal, // quote 成交额
+    pub trades: u64,
+    pub closing_trades: u64, // 减仓或平仓的成交
+    pub winning_trades: u64, // 扣除手续费后盈利的减仓成交
+}
+
+impl MarketPnl {
+    /// 扣除手续费后的净盈亏
+    pub fn net(&self) -> Decimal {
+        self.realized + self.unrealized.unwrap_or_default() - self.fees
+    }
+}
+
+/// 盈亏汇总，金额均为各市场 quote 计价
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PnlReport {
+    pub markets: Vec<MarketPnl>,
+    pub realized: Decimal,
+    pub unrealized: Decimal,
+    pub fees: Decimal,
+    pub net: Decimal,
+}
+
+/// 按成交回报累计盈亏，纸面和实盘执行器共用
+///
+/// 持仓成本按均价法计算，反向成交先平仓再按成交价开新仓。
+#[derive(Debug, Clone, Default)]
+pub struct PnlTracker {
+    markets: BTreeMap<String, MarketPnl>,
+}
+
+impl PnlTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn on_fill(&mut self, fill: &Fill) {
+        let pnl = self
+            .markets
+            .entry(fill.market.clone())
+            .or_insert_with(|| MarketPnl {
+                market: fill.market.clone(),
+                ..Default::default()
+            });
+        pnl.fees += fill.fee;
+        pnl.volume += fill.price * fill.size;
+        pnl.trades += 1;
+
+        let signed = match fill.side {
+            TradeSide::Buy => fill.size,
+            TradeSide::Sell => -fill.size,
+        };
+        if pnl.position.is_zero() || pnl.position.is_sign_positive() == signed.is_sign_positive() {
+            let held = pnl.position.abs();
+            pnl.avg_price = (pnl.avg_price * held + fill.price * fill.size) / (held + fill.size);
+            pnl.position += signed;
+            return;
+        }
+
+        let closed = fill.size.min(pnl.position.abs());
+        let direction = if pnl.position.is_sign_positive() {
+            Decimal::ONE
+        } else {
+            Decimal::NEGATIVE_ONE
+        };
+        let realized = closed * (fill.price - pnl.avg_price) * direction;
+        pnl.realized += realized;
+        pnl.closing_trades += 1;
+        if realized - fill.fee > Decimal::ZERO {
+            pnl.winning_trades += 1;
+        }
+        pnl.position += signed;
+        if pnl.position.is_zero() {
+            pnl.avg_price = Decimal::ZERO;
+        } else if fill.size > closed {
+            pnl.avg_price = fill.price;
+        }
+    }
+
+    pub fn market(&self, market: &str) -> Option<&MarketPnl> {
+        self.markets.get(market)
+    }
+
+    /// 按标记价格 (市场名称 -> 价格) 生成盈亏报告
+    pub fn report(&self, marks: &HashMap<String, Decimal>) -> PnlReport {
+        let mut report = PnlReport::default();
+        for pnl in self.markets.values() {
+            let mut pnl = pnl.clone();
+            pnl.unrealized = marks
+                .get(&pnl.market)
+                .map(|mark| pnl.position * (*mark - pnl.avg_price));
+            report.realized += pnl.realized;
+            report.unrealized += pnl.unrealized.unwrap_or_default();
+            report.fees += pnl.fees;
+            report.net += pnl.net();
+            report.markets.push(pnl);
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    const MARKET: &str = "SOL/USDC";
+
+    fn fill(side: TradeSide, price: i64, size: i64) -> Fill {
+        Fill {
+            client_id: 1,
+            market: MARKET.to_string(),
+            side,
+            price: Decimal::from(price),
+            size: Decimal::from(size),
+            fee: Decimal::ONE,
+            remaining: Decimal::ZERO,
+            time: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn position_flips_through_zero() {
+        let mut tracker = PnlTracker::new();
+        tracker.on_fill(&fill(TradeSide::Buy, 100, 2));
+        tracker.on_fill(&fill(TradeSide::Buy, 110, 2));
+        assert_eq!(
+            tracker.market(MARKET).unwrap().avg_price,
+            Decimal::from(105)
+        );
+
+        // 平掉 4 个多头，剩余 2 个按成交价开空
+        tracker.on_fill(&fill(TradeSide::Sell, 120, 6));
+        let pnl = tracker.market(MARKET).unwrap();
+        assert_eq!(pnl.position, Decimal::from(-2));
+        assert_eq!(pnl.avg_price, Decimal::from(120));
+        assert_eq!(pnl.realized, Decimal::from(60));
+
+        let marks = HashMap::from([(MARKET.to_string(), Decimal::from(110))]);
+        let report = tracker.report(&marks);
+        assert_eq!(report.unrealized, Decimal::from(20));
+        assert_eq!(report.net, Decimal::from(77));
+
+        // 空头回补后持仓归零
+        tracker.on_fill(&fill(TradeSide::Buy, 100, 2));
+        let pnl = tracker.market(MARKET).unwrap();
+        assert!(pnl.position.is_zero());
+        assert!(pnl.avg_price.is_zero());
+        assert_eq!(pnl.realized, Decimal::from(100));
+        assert_eq!(pnl.fees, Decimal::from(4));
+        assert_eq!(pnl.trades, 4);
+        assert_eq!(pnl.closing_trades, 2);
+        assert_eq!(pnl.winning_trades, 2);
+
+        // 扣除手续费后不盈利的平仓不计入盈利次数
+        tracker.on_fill(&fill(TradeSide::Buy, 100, 1));
+        tracker.on_fill(&fill(TradeSide::Sell, 100, 1));
+        let pnl = tracker.market(MARKET).unwrap();
+        assert_eq!(pnl.closing_trades, 3);
+        assert_eq!(pnl.winning_trades, 2);
+    }
+
+    #[test]
+    fn report_without_mark_has_no_unrealized() {
+        let mut tracker = PnlTracker::new();
+        tracker.on_fill(&fill(TradeSide::Sell, 100, 1));
+        let report = tracker.report(&HashMap::new());
+        assert_eq!(report.markets[0].unrealized, None);
+        assert_eq!(report.net, Decimal::NEGATIVE_ONE);
+    }
+}
@@ -0,0 +1,539 @@
+use anyhow::Result;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    sysvar,
+};
+
+use crate::dex_collect::serum::serum_depth::MarketState;
+use crate::dexclient::OrderSide;
+
+/// OpenOrders 账户大小 (含 5 字节头和 7 字节尾部填充)
+pub const OPEN_ORDERS_SIZE: usize = 3228;
+/// OpenOrders 中 market 和 owner 字段的偏移，用于 getProgramAccounts 过滤
+pub const OPEN_ORDERS_MARKET_OFFSET: usize = 13;
+pub const OPEN_ORDERS_OWNER_OFFSET: usize = 45;
+/// 每个 OpenOrders 账户最多容纳的挂单数
+pub const MAX_OPEN_ORDERS: usize = 128;
+/// 基础档位吃单费率上限，用于计算买单需要锁定的 quote
+pub const TAKER_FEE_BPS: u64 = 22;
+
+const FREE_FUNDS_OFFSET: usize = 77;
+const FREE_SLOT_BITS_OFFSET: usize = 109;
+const IS_BID_BITS_OFFSET: usize = 125;
+const ORDERS_OFFSET: usize = 141;
+const CLIENT_IDS_OFFSET: usize = ORDERS_OFFSET + 16 * MAX_OPEN_ORDERS;
+
+/// 指令编号
+const IX_NEW_ORDER_V3: u32 = 10;
+const IX_CONSUME_EVENTS: u32 = 3;
+const IX_SETTLE_FUNDS: u32 = 5;
+const IX_CANCEL_ORDER_V2: u32 = 11;
+const IX_CANCEL_ORDER_BY_CLIENT_ID_V2: u32 = 12;
+const IX_INIT_OPEN_ORDERS: u32 = 15;
+
+/// 订单类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderType {
+    Limit,
+    ImmediateOrCancel,
+    PostOnly,
+}
+
+/// 与自己的挂单撮合时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SelfTradeBehavior {
+    #[default]
+    DecrementTake, // 双方同时减量，不产生手续费
+    CancelProvide,    // 撤掉自己的挂单后继续撮合
+    AbortTransaction, // 整笔交易失败
+}
+
+/// 下单需要的市场账户和 lot 参数
+#[derive(Debug, Clone)]
+pub struct MarketAccounts {
+    pub program_id: Pubkey,
+    pub market: Pubkey,
+    pub request_queue: Pubkey,
+    pub event_queue: Pubkey,
+    pub bids: Pubkey,
+    pub asks: Pubkey,
+    pub base_vault: Pubkey,
+    pub quote_vault: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub vault_signer_nonce: u64,
+    pub base_lot_size: u64,
+    pub quote_lot_size: u64,
+    pub base_decimals: u32,
+    pub quote_decimals: u32,
+}
+
+impl MarketAccounts {
+    /// 由市场账户数据、所属程序和 mint 精度构造
+    pub fn new(
+        program_id: Pubkey,
+        market: Pubkey,
+        state: &MarketState,
+        base_decimals: u32,
+        quote_decimals: u32,
+    ) -> Self {
+        Self {
+            program_id,
+            market,
+            request_queue: state.req_queue,
+            event_queue: state.event_queue,
+            bids: state.bids,
+            asks: state.asks,
+            base_vault: state.base_vault,
+            quote_vault: state.quote_vault,
+            base_mint: state.base_mint,
+            quote_mint: state.quote_mint,
+            vault_signer_nonce: state.vault_signer_nonce,
+            base_lot_size: state.base_lot_size,
+            quote_lot_size: state.quote_lot_size,
+            base_decimals,
+            quote_decimals,
+        }
+    }
+
+    /// 金库签名 PDA，seeds 为市场地址和 nonce
+    pub fn vault_signer(&self) -> Result<Pubkey> {
+        Pubkey::create_program_address(
+            &[self.market.as_ref(), &self.vault_signer_nonce.to_le_bytes()],
+            &self.program_id,
+        )
+        .map_err(|e| anyhow::anyhow!("Invalid vault signer for {}: {}", self.market, e))
+    }
+
+    /// 下单时支付的 mint: 买单付 quote，卖单付 base
+    pub fn payer_mint(&self, side: &OrderSide) -> Pubkey {
+        match side {
+            OrderSide::Buy => self.quote_mint,
+            OrderSide::Sell => self.base_mint,
+        }
+    }
+}
+
+/// NewOrderV3 参数，价格和数量均为 lots
+#[derive(Debug, Clone)]
+pub struct NewOrderParams {
+    pub side: OrderSide,
+    pub limit_price: u64,
+    pub max_base_qty: u64,
+    pub max_quote_qty: u64, // 买单锁定的 quote 原生数量，含手续费
+    pub order_type: OrderType,
+    pub self_trade: SelfTradeBehavior,
+    pub client_order_id: u64,
+    pub limit: u16, // 单笔撮合最多处理的对手单数
+}
+
+impl NewOrderParams {
+    /// 按 lots 计算锁定数量，买单额外预留吃单手续费
+    pub fn new(
+        market: &MarketAccounts,
+        side: OrderSide,
+        limit_price: u64,
+        max_base_qty: u64,
+        order_type: OrderType,
+        self_trade: SelfTradeBehavior,
+        client_order_id: u64,
+    ) -> Self {
+        let max_quote_qty = match side {
+            OrderSide::Buy => {
+                let native =
+                    limit_price as u128 * max_base_qty as u128 * market.quote_lot_size as u128;
+                let with_fee = native * (10_000 + TAKER_FEE_BPS) as u128 / 10_000;
+                with_fee.min(u64::MAX as u128) as u64
+            }
+            OrderSide::Sell => u64::MAX,
+        };
+        Self {
+            side,
+            limit_price,
+            max_base_qty,
+            max_quote_qty,
+            order_type,
+            self_trade,
+            client_order_id,
+            limit: u16::MAX,
+        }
+    }
+
+    /// 下单需要从支付账户转入的原生数量
+    pub fn required_funds(&self, market: &MarketAccounts) -> u64 {
+        match self.side {
+            OrderSide::Buy => self.max_quote_qty,
+            OrderSide::Sell => self.max_base_qty.saturating_mul(market.base_lot_size),
+        }
+    }
+}
+
+/// 指令数据: 版本 0 + u32 指令编号
+fn instruction_data(tag: u32, capacity: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(5 + capacity);
+    data.push(0);
+    data.extend_from_slice(&tag.to_le_bytes());
+    data
+}
+
+/// NewOrderV3 指令
+///
+/// `payer` 是支付资金的代币账户，买单为 quote 账户，卖单为 base 账户。
+pub fn new_order_v3(
+    market: &MarketAccounts,
+    open_orders: &Pubkey,
+    payer: &Pubkey,
+    owner: &Pubkey,
+    params: &NewOrderParams,
+) -> Instruction {
+    let mut data = instruction_data(IX_NEW_ORDER_V3, 46);
+    let side: u32 = match params.side {
+        OrderSide::Buy => 0,
+        OrderSide::Sell => 1,
+    };
+    let self_trade: u32 = match params.self_trade {
+        SelfTradeBehavior::DecrementTake => 0,
+        SelfTradeBehavior::CancelProvide => 1,
+        SelfTradeBehavior::AbortTransaction => 2,
+    };
+    let order_type: u32 = match params.order_type {
+        OrderType::Limit => 0,
+        OrderType::ImmediateOrCancel => 1,
+        OrderType::PostOnly => 2,
+    };
+    data.extend_from_slice(&side.to_le_bytes());
+    data.extend_from_slice(&params.limit_price.to_le_bytes());
+    data.extend_from_slice(&params.max_base_qty.to_le_bytes());
+    data.extend_from_slice(&params.max_quote_qty.to_le_bytes());
+    data.extend_from_slice(&self_trade.to_le_bytes());
+    data.extend_from_slice(&order_type.to_le_bytes());
+    data.extend_from_slice(&params.client_order_id.to_le_bytes());
+    data.extend_from_slice(&params.limit.to_le_bytes());
+
+    Instruction {
+        program_id: market.program_id,
+        accounts: vec![
+            AccountMeta::new(market.market, false),
+            AccountMeta::new(*open_orders, false),
+            AccountMeta::new(market.request_queue, false),
+            AccountMeta::new(market.event_queue, false),
+            AccountMeta::new(market.bids, false),
+            AccountMeta::new(market.asks, false),
+            AccountMeta::new(*payer, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(market.base_vault, false),
+            AccountMeta::new(market.quote_vault, false),
+            AccountMeta::new_readonly(anchor_spl::token::ID, false),
+            AccountMeta::new_readonly(sysvar::rent::ID, false),
+        ],
+        data,
+    }
+}
+
+fn cancel_accounts(
+    market: &MarketAccounts,
+    open_orders: &Pubkey,
+    owner: &Pubkey,
+) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(market.market, false),
+        AccountMeta::new(market.bids, false),
+        AccountMeta::new(market.asks, false),
+        AccountMeta::new(*open_orders, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(market.event_queue, false),
+    ]
+}
+
+/// CancelOrderV2 指令，按链上订单号撤单
+pub fn cancel_order_v2(
+    market: &MarketAccounts,
+    open_orders: &Pubkey,
+    owner: &Pubkey,
+    side: OrderSide,
+    order_id: u128,
+) -> Instruction {
+    let mut data = instruction_data(IX_CANCEL_ORDER_V2, 20);
+    let side: u32 = match side {
+        OrderSide::Buy => 0,
+        OrderSide::Sell => 1,
+    };
+    data.extend_from_slice(&side.to_le_bytes());
+    data.extend_from_slice(&order_id.to_le_bytes());
+    Instruction {
+        program_id: market.program_id,
+        accounts: cancel_accounts(market, open_orders, owner),
+        data,
+    }
+}
+
+/// CancelOrderByClientIdV2 指令，按客户端订单号撤单
+pub fn cancel_order_by_client_id_v2(
+    market: &MarketAccounts,
+    open_orders: &Pubkey,
+    owner: &Pubkey,
+    client_order_id: u64,
+) -> Instruction {
+    let mut data = instruction_data(IX_CANCEL_ORDER_BY_CLIENT_ID_V2, 8);
+    data.extend_from_slice(&client_order_id.to_le_bytes());
+    Instruction {
+        program_id: market.program_id,
+        accounts: cancel_accounts(market, open_orders, owner),
+        data,
+    }
+}
+
+/// SettleFunds 指令，把 OpenOrders 中的可用资金转回钱包代币账户
+pub fn settle_funds(
+    market: &MarketAccounts,
+    open_orders: &Pubkey,
+    owner: &Pubkey,
+    base_wallet: &Pubkey,
+    quote_wallet: &Pubkey,
+) -> Result<Instruction> {
+    Ok(Instruction {
+        program_id: market.program_id,
+        accounts: vec![
+            AccountMeta::new(market.market, false),
+            AccountMeta::new(*open_orders, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(market.base_vault, false),
+            AccountMeta::new(market.quote_vault, false),
+            AccountMeta::new(*base_wallet, false),
+            AccountMeta::new(*quote_wallet, false),
+            AccountMeta::new_readonly(market.vault_signer()?, false),
+            AccountMeta::new_readonly(anchor_spl::token::ID, false),
+        ],
+        data: instruction_data(IX_SETTLE_FUNDS, 0),
+    })
+}
+
+/// ConsumeEvents 指令
+///
+/// `open_orders` 需包含待处理事件涉及的全部账户，未包含的事件会让处理提前停止；
+/// 两个手续费接收账户程序不做检查，通常传调用方自己的代币账户。
+pub fn consume_events(
+    market: &MarketAccounts,
+    open_orders: &[Pubkey],
+    fee_receivables: (&Pubkey, &Pubkey),
+    limit: u16,
+) -> Instruction {
+    // 程序要求账户按 [u64; 4] 小端字的顺序排列，与按字节排序不同
+    let mut open_orders = open_orders.to_vec();
+    open_orders.sort_by_key(aligned_key);
+    open_orders.dedup();
+    let mut accounts: Vec<AccountMeta> = open_orders
+        .iter()
+        .map(|pubkey| AccountMeta::new(*pubkey, false))
+        .collect();
+    accounts.extend([
+        AccountMeta::new(market.market, false),
+        AccountMeta::new(market.event_queue, false),
+        AccountMeta::new(*fee_receivables.0, false),
+        AccountMeta::new(*fee_receivables.1, false),
+    ]);
+    let mut data = instruction_data(IX_CONSUME_EVENTS, 2);
+    data.extend_from_slice(&limit.to_le_bytes());
+    Instruction {
+        program_id: market.program_id,
+        accounts,
+        data,
+    }
+}
+
+/// 公钥按 DEX 程序内部 `to_aligned_bytes` 的 [u64; 4] 表示
+fn aligned_key(pubkey: &Pubkey) -> [u64; 4] {
+    let bytes = pubkey.to_bytes();
+    std::array::from_fn(|i| u64::from_le_bytes(bytes[i * 8..i * 8 + 8].try_into().unwrap()))
+}
+
+/// InitOpenOrders 指令，账户需已由 system 程序创建并归属 DEX 程序
+pub fn init_open_orders(
+    market: &MarketAccounts,
+    open_orders: &Pubkey,
+    owner: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: market.program_id,
+        accounts: vec![
+            AccountMeta::new(*open_orders, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new_readonly(market.market, false),
+            AccountMeta::new_readonly(sysvar::rent::ID, false),
+        ],
+        data: instruction_data(IX_INIT_OPEN_ORDERS, 0),
+    }
+}
+
+/// OpenOrders 中的一笔挂单
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenOrder {
+    pub slot: u8,
+    pub order_id: u128,
+    pub client_order_id: u64,
+    pub side: OrderSide,
+    pub price_lots: u64, // 订单号高 64 位即价格 lots
+}
+
+/// 解码后的 OpenOrders 账户
+#[derive(Debug, Clone)]
+pub struct OpenOrders {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub base_free: u64,
+    pub base_total: u64,
+    pub quote_free: u64,
+    pub quote_total: u64,
+    pub orders: Vec<OpenOrder>,
+}
+
+impl OpenOrders {
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < OPEN_ORDERS_SIZE {
+            return Err(anyhow::anyhow!(
+                "Invalid open orders account size {}",
+                data.len()
+            ));
+        }
+        let u64_at =
+            |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+        let u128_at =
+            |offset: usize| u128::from_le_bytes(data[offset..offset + 16].try_into().unwrap());
+
+        let free_slots = u128_at(FREE_SLOT_BITS_OFFSET);
+        let is_bid = u128_at(IS_BID_BITS_OFFSET);
+        let orders = (0..MAX_OPEN_ORDERS)
+            .filter(|slot| free_slots & (1u128 << slot) == 0)
+            .map(|slot| {
+                let order_id = u128_at(ORDERS_OFFSET + slot * 16);
+                OpenOrder {
+                    slot: slot as u8,
+                    order_id,
+                    client_order_id: u64_at(CLIENT_IDS_OFFSET + slot * 8),
+                    side: if is_bid & (1u128 << slot) != 0 {
+                        OrderSide::Buy
+                    } else {
+                        OrderSide::Sell
+                    },
+                    price_lots: (order_id >> 64) as u64,
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            market: Pubkey::try_from(
+                &data[OPEN_ORDERS_MARKET_OFFSET..OPEN_ORDERS_MARKET_OFFSET + 32],
+            )?,
+            owner: Pubkey::try_from(
+                &data[OPEN_ORDERS_OWNER_OFFSET..OPEN_ORDERS_OWNER_OFFSET + 32],
+            )?,
+            base_free: u64_at(FREE_FUNDS_OFFSET),
+            base_total: u64_at(FREE_FUNDS_OFFSET + 8),
+            quote_free: u64_at(FREE_FUNDS_OFFSET + 16),
+            quote_total: u64_at(FREE_FUNDS_OFFSET + 24),
+            orders,
+        })
+    }
+
+    /// 按链上订单号查找挂单
+    pub fn find_by_order_id(&self, order_id: u128) -> Option<&OpenOrder> {
+        self.orders.iter().find(|o| o.order_id == order_id)
+    }
+
+    /// 按客户端订单号查找挂单
+    pub fn find_by_client_id(&self, client_order_id: u64) -> Option<&OpenOrder> {
+        self.orders
+            .iter()
+            .find(|o| o.client_order_id == client_order_id)
+    }
+}
+
+/// 构造 OpenOrders 账户数据，`orders` 为 (槽位, 订单号, 客户端订单号, 方向)
+#[cfg(test)]
+pub(crate) fn open_orders_fixture(orders: &[(u8, u128, u64, OrderSide)]) -> Vec<u8> {
+    let mut data = vec![0u8; OPEN_ORDERS_SIZE];
+    let mut free_slots = u128::MAX;
+    let mut is_bid = 0u128;
+    for &(slot, order_id, client_order_id, side) in orders {
+        let slot = slot as usize;
+        free_slots &= !(1u128 << slot);
+        if side == OrderSide::Buy {
+            is_bid |= 1u128 << slot;
+        }
+        let offset = ORDERS_OFFSET + slot * 16;
+        data[offset..offset + 16].copy_from_slice(&order_id.to_le_bytes());
+        let offset = CLIENT_IDS_OFFSET + slot * 8;
+        data[offset..offset + 8].copy_from_slice(&client_order_id.to_le_bytes());
+    }
+    data[FREE_SLOT_BITS_OFFSET..FREE_SLOT_BITS_OFFSET + 16]
+        .copy_from_slice(&free_slots.to_le_bytes());
+    data[IS_BID_BITS_OFFSET..IS_BID_BITS_OFFSET + 16].copy_from_slice(&is_bid.to_le_bytes());
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(first_word: [u8; 8]) -> Pubkey {
+        let mut bytes = [0u8; 32];
+        bytes[..8].copy_from_slice(&first_word);
+        Pubkey::new_from_array(bytes)
+    }
+
+    #[test]
+    fn decodes_open_orders_and_finds_by_client_id() {
+        let bid = (2050u128 << 64) | 7;
+        let ask = (2060u128 << 64) | 8;
+        let data =
+            open_orders_fixture(&[(0, bid, 11, OrderSide::Buy), (5, ask, 12, OrderSide::Sell)]);
+        let open_orders = OpenOrders::from_bytes(&data).unwrap();
+        assert_eq!(open_orders.orders.len(), 2);
+
+        let order = open_orders.find_by_client_id(12).unwrap();
+        assert_eq!(order.order_id, ask);
+        assert_eq!(order.slot, 5);
+        assert_eq!(order.side, OrderSide::Sell);
+        assert_eq!(order.price_lots, 2060);
+        assert_eq!(
+            open_orders.find_by_order_id(bid).map(|o| o.client_order_id),
+            Some(11)
+        );
+        // 空槽位的客户端订单号也是 0，不能被匹配
+        assert!(open_orders.find_by_client_id(0).is_none());
+        assert!(open_orders.find_by_client_id(13).is_none());
+    }
+
+    #[test]
+    fn consume_events_sorts_by_aligned_words() {
+        // 按字节 a < b，按小端 u64 则 b < a
+        let a = key([1, 0, 0, 0, 0, 0, 0, 2]);
+        let b = key([2, 0, 0, 0, 0, 0, 0, 1]);
+        assert!(a < b);
+        assert!(aligned_key(&b) < aligned_key(&a));
+
+        let market = MarketAccounts {
+            program_id: Pubkey::new_unique(),
+            market: Pubkey::new_unique(),
+            request_queue: Pubkey::default(),
+            event_queue: Pubkey::new_unique(),
+            bids: Pubkey::default(),
+            asks: Pubkey::default(),
+            base_vault: Pubkey::default(),
+            quote_vault: Pubkey::default(),
+            base_mint: Pubkey::default(),
+            quote_mint: Pubkey::default(),
+            vault_signer_nonce: 0,
+            base_lot_size: 1,
+            quote_lot_size: 1,
+            base_decimals: 6,
+            quote_decimals: 6,
+        };
+        let fee = Pubkey::new_unique();
+        let ix = consume_events(&market, &[a, b, a], (&fee, &fee), 16);
+        let keys: Vec<Pubkey> = ix.accounts.iter().take(2).map(|m| m.pubkey).collect();
+        assert_eq!(keys, vec![b, a]);
+        assert_eq!(ix.accounts.len(), 6);
+    }
+}
@@ -0,0 +1,374 @@
+use anyhow::Result;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey,
+    pubkey::Pubkey,
+};
+
+use crate::dex_collect::fixed::{self, Decimal, BPS};
+use crate::dex_collect::serum::serum_slippage::TradeSide;
+use crate::executer::serum::{MarketAccounts, OpenOrders};
+use crate::strategy::quote::Liquidity;
+
+/// Raydium AMM v4 程序
+pub const RAYDIUM_AMM_V4: Pubkey = pubkey!("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8");
+/// AMM 权限 PDA 的种子
+const AMM_AUTHORITY_SEED: &[u8] = b"amm authority";
+/// AmmInfo 账户大小
+pub const AMM_INFO_SIZE: usize = 752;
+
+/// AmmInfo 字段偏移
+const COIN_DECIMALS_OFFSET: usize = 32;
+const PC_DECIMALS_OFFSET: usize = 40;
+const SWAP_FEE_NUMERATOR_OFFSET: usize = 176;
+const SWAP_FEE_DENOMINATOR_OFFSET: usize = 184;
+const NEED_TAKE_PNL_COIN_OFFSET: usize = 192;
+const NEED_TAKE_PNL_PC_OFFSET: usize = 200;
+const COIN_VAULT_OFFSET: usize = 336;
+const PC_VAULT_OFFSET: usize = 368;
+const COIN_MINT_OFFSET: usize = 400;
+const PC_MINT_OFFSET: usize = 432;
+const OPEN_ORDERS_OFFSET: usize = 496;
+const MARKET_OFFSET: usize = 528;
+const MARKET_PROGRAM_OFFSET: usize = 560;
+const TARGET_ORDERS_OFFSET: usize = 592;
+
+/// 指令编号
+const IX_SWAP_BASE_IN: u8 = 9;
+const IX_SWAP_BASE_OUT: u8 = 11;
+
+/// 最大输入反推时的二分次数
+const BISECT_ITERATIONS: usize = 64;
+
+/// 解码后的 AMM v4 池子，附带其关联的 Serum/OpenBook 市场账户
+#[derive(Debug, Clone)]
+pub struct RaydiumPool {
+    pub program_id: Pubkey,
+    pub amm: Pubkey,
+    pub authority: Pubkey,
+    pub open_orders: Pubkey,
+    pub target_orders: Pubkey,
+    pub coin_vault: Pubkey,
+    pub pc_vault: Pubkey,
+    pub coin_mint: Pubkey,
+    pub pc_mint: Pubkey,
+    pub coin_decimals: u32,
+    pub pc_decimals: u32,
+    pub need_take_pnl_coin: u64, // 计算储备时需从金库余额中扣除
+    pub need_take_pnl_pc: u64,
+    pub fee_bps: Decimal,
+    pub market: MarketAccounts,
+}
+
+/// 读取 AmmInfo 中 AMM 关联的市场地址和市场程序
+pub fn amm_market(data: &[u8]) -> Result<(Pubkey, Pubkey)> {
+    if data.len() < AMM_INFO_SIZE {
+        return Err(anyhow::anyhow!("Invalid AMM account size {}", data.len()));
+    }
+    Ok((
+        Pubkey::try_from(&data[MARKET_OFFSET..MARKET_OFFSET + 32])?,
+        Pubkey::try_from(&data[MARKET_PROGRAM_OFFSET..MARKET_PROGRAM_OFFSET + 32])?,
+    ))
+}
+
+impl RaydiumPool {
+    /// 由 AmmInfo 账户数据和已解码的市场账户构造
+    pub fn from_bytes(
+        program_id: Pubkey,
+        amm: Pubkey,
+        data: &[u8],
+        market: MarketAccounts,
+    ) -> Result<Self> {
+        let (market_address, market_program) = amm_market(data)?;
+        if market_address != market.market || market_program != market.program_id {
+            return Err(anyhow::anyhow!(
+                "Market {} does not belong to AMM {}",
+                market.market,
+                amm
+            ));
+        }
+        let u64_at =
+            |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+        let pubkey_at = |offset: usize| Pubkey::try_from(&data[offset..offset + 32]);
+
+        let numerator = u64_at(SWAP_FEE_NUMERATOR_OFFSET);
+        let denominator = u64_at(SWAP_FEE_DENOMINATOR_OFFSET);
+        if denominator == 0 {
+            return Err(anyhow::anyhow!("Invalid swap fee for AMM {}", amm));
+        }
+        let (authority, _) = Pubkey::find_program_address(&[AMM_AUTHORITY_SEED], &program_id);
+
+        Ok(Self {
+            program_id,
+            amm,
+            authority,
+            open_orders: pubkey_at(OPEN_ORDERS_OFFSET)?,
+            target_orders: pubkey_at(TARGET_ORDERS_OFFSET)?,
+            coin_vault: pubkey_at(COIN_VAULT_OFFSET)?,
+            pc_vault: pubkey_at(PC_VAULT_OFFSET)?,
+            coin_mint: pubkey_at(COIN_MINT_OFFSET)?,
+            pc_mint: pubkey_at(PC_MINT_OFFSET)?,
+            coin_decimals: u64_at(COIN_DECIMALS_OFFSET) as u32,
+            pc_decimals: u64_at(PC_DECIMALS_OFFSET) as u32,
+            need_take_pnl_coin: u64_at(NEED_TAKE_PNL_COIN_OFFSET),
+            need_take_pnl_pc: u64_at(NEED_TAKE_PNL_PC_OFFSET),
+            fee_bps: Decimal::from(numerator) * BPS / Decimal::from(denominator),
+            market,
+        })
+    }
+
+    /// 输入和输出 mint: 买入 base 时付 quote (pc)，卖出时付 base (coin)
+    pub fn mints(&self, side: TradeSide) -> (Pubkey, Pubkey) {
+        match side {
+            TradeSide::Buy => (self.pc_mint, self.coin_mint),
+            TradeSide::Sell => (self.coin_mint, self.pc_mint),
+        }
+    }
+
+    /// 池子的实际储备: 金库余额扣除待提取收益，加上挂在订单簿 OpenOrders 中的资金
+    ///
+    /// `coin_vault`/`pc_vault` 为两个金库的原生余额，手续费取池子自身费率。
+    pub fn liquidity(&self, coin_vault: u64, pc_vault: u64, open_orders: &OpenOrders) -> Liquidity {
+        let coin = coin_vault.saturating_sub(self.need_take_pnl_coin) as u128
+            + open_orders.base_total as u128;
+        let pc = pc_vault.saturating_sub(self.need_take_pnl_pc) as u128
+            + open_orders.quote_total as u128;
+        Liquidity::ConstantProduct {
+            base_reserve: Decimal::from(coin) / Decimal::from(10u64.pow(self.coin_decimals)),
+            quote_reserve: Decimal::from(pc) / Decimal::from(10u64.pow(self.pc_decimals)),
+            fee_bps: self.fee_bps,
+        }
+    }
+
+    /// 按池子费率重建报价用的流动性，只接受恒定乘积储备
+    fn priced(&self, liquidity: &Liquidity) -> Option<Liquidity> {
+        match liquidity {
+            Liquidity::ConstantProduct {
+                base_reserve,
+                quote_reserve,
+                ..
+            } => Some(Liquidity::ConstantProduct {
+                base_reserve: *base_reserve,
+                quote_reserve: *quote_reserve,
+                fee_bps: self.fee_bps,
+            }),
+            Liquidity::OrderBook { .. } => None,
+        }
+    }
+
+    /// 输入和输出代币的精度
+    fn decimals(&self, side: TradeSide) -> (u32, u32) {
+        match side {
+            TradeSide::Buy => (self.pc_decimals, self.coin_decimals),
+            TradeSide::Sell => (self.coin_decimals, self.pc_decimals),
+        }
+    }
+
+    fn accounts(
+        &self,
+        user_source: &Pubkey,
+        user_destination: &Pubkey,
+        owner: &Pubkey,
+    ) -> Result<Vec<AccountMeta>> {
+        Ok(vec![
+            AccountMeta::new_readonly(anchor_spl::token::ID, false),
+            AccountMeta::new(self.amm, false),
+            AccountMeta::new_readonly(self.authority, false),
+            AccountMeta::new(self.open_orders, false),
+            AccountMeta::new(self.target_orders, false),
+            AccountMeta::new(self.coin_vault, false),
+            AccountMeta::new(self.pc_vault, false),
+            AccountMeta::new_readonly(self.market.program_id, false),
+            AccountMeta::new(self.market.market, false),
+            AccountMeta::new(self.market.bids, false),
+            AccountMeta::new(self.market.asks, false),
+            AccountMeta::new(self.market.event_queue, false),
+            AccountMeta::new(self.market.base_vault, false),
+            AccountMeta::new(self.market.quote_vault, false),
+            AccountMeta::new_readonly(self.market.vault_signer()?, false),
+            AccountMeta::new(*user_source, false),
+            AccountMeta::new(*user_destination, false),
+            AccountMeta::new_readonly(*owner, true),
+        ])
+    }
+}
+
+/// SwapBaseIn 指令: 精确输入，输出低于 `minimum_amount_out` 时交易失败
+pub fn swap_base_in(
+    pool: &RaydiumPool,
+    user_source: &Pubkey,
+    user_destination: &Pubkey,
+    owner: &Pubkey,
+    amount_in: u64,
+    minimum_amount_out: u64,
+) -> Result<Instruction> {
+    let mut data = Vec::with_capacity(17);
+    data.push(IX_SWAP_BASE_IN);
+    data.extend_from_slice(&amount_in.to_le_bytes());
+    data.extend_from_slice(&minimum_amount_out.to_le_bytes());
+    Ok(Instruction {
+        program_id: pool.program_id,
+        accounts: pool.accounts(user_source, user_destination, owner)?,
+        data,
+    })
+}
+
+/// SwapBaseOut 指令: 精确输出，所需输入超过 `max_amount_in` 时交易失败
+pub fn swap_base_out(
+    pool: &RaydiumPool,
+    user_source: &Pubkey,
+    user_destination: &Pubkey,
+    owner: &Pubkey,
+    max_amount_in: u64,
+    amount_out: u64,
+) -> Result<Instruction> {
+    let mut data = Vec::with_capacity(17);
+    data.push(IX_SWAP_BASE_OUT);
+    data.extend_from_slice(&max_amount_in.to_le_bytes());
+    data.extend_from_slice(&amount_out.to_le_bytes());
+    Ok(Instruction {
+        program_id: pool.program_id,
+        accounts: pool.accounts(user_source, user_destination, owner)?,
+        data,
+    })
+}
+
+/// 交换方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapMode {
+    BaseIn,  // 精确输入，限制最小输出
+    BaseOut, // 精确输出，限制最大输入
+}
+
+/// 按本地报价和滑点容忍度计算的交换参数，数量为原生单位
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwapPlan {
+    pub side: TradeSide,
+    pub mode: SwapMode,
+    pub amount_in: u64,  // BaseIn 为精确输入，BaseOut 为最大输入
+    pub amount_out: u64, // BaseIn 为最小输出，BaseOut 为精确输出
+    pub expected_in: Decimal,
+    pub expected_out: Decimal,
+}
+
+impl SwapPlan {
+    /// 生成对应的 Raydium 指令
+    pub fn instruction(
+        &self,
+        pool: &RaydiumPool,
+        user_source: &Pubkey,
+        user_destination: &Pubkey,
+        owner: &Pubkey,
+    ) -> Result<Instruction> {
+        match self.mode {
+            SwapMode::BaseIn => swap_base_in(
+                pool,
+                user_source,
+                user_destination,
+                owner,
+                self.amount_in,
+                self.amount_out,
+            ),
+            SwapMode::BaseOut => swap_base_out(
+                pool,
+                user_source,
+                user_destination,
+                owner,
+                self.amount_in,
+                self.amount_out,
+            ),
+        }
+    }
+}
+
+fn to_raw(amount: Decimal, decimals: u32, round_up: bool) -> Option<u64> {
+    fixed::size_to_lots(amount, 1, decimals).and_then(|raw| {
+        let exact = fixed::from_raw(raw, decimals) == Some(amount);
+        if round_up && !exact {
+            raw.checked_add(1)
+        } else {
+            Some(raw)
+        }
+    })
+}
+
+/// 精确输入 `amount_in` (UI 单位) 的交换参数
+///
+/// `liquidity` 为 [`RaydiumPool::liquidity`] 得到的储备，报价总按池子费率计算；
+/// 最小输出为预期输出扣除 `slippage_bps` 后向下取整。
+pub fn plan_swap_base_in(
+    pool: &RaydiumPool,
+    liquidity: &Liquidity,
+    side: TradeSide,
+    amount_in: Decimal,
+    slippage_bps: Decimal,
+) -> Option<SwapPlan> {
+    let (in_decimals, out_decimals) = pool.decimals(side);
+    let liquidity = &pool.priced(liquidity)?;
+    let raw_in = to_raw(amount_in, in_decimals, false).filter(|raw| *raw > 0)?;
+    let amount_in = fixed::from_raw(raw_in, in_decimals)?;
+    let quote = liquidity.quote_exact_in(side, amount_in)?;
+    let expected_out = match side {
+        TradeSide::Buy => quote.base,
+        TradeSide::Sell => quote.quote,
+    };
+    let minimum = expected_out * (Decimal::ONE - slippage_bps / BPS);
+    Some(SwapPlan {
+        side,
+        mode: SwapMode::BaseIn,
+        amount_in: raw_in,
+        amount_out: to_raw(minimum.max(Decimal::ZERO), out_decimals, false)?,
+        expected_in: amount_in,
+        expected_out,
+    })
+}
+
+/// 精确输出 `amount_out` (UI 单位) 的交换参数，最大输入为预期输入加上 `slippage_bps` 后向上取整
+///
+/// `liquidity` 同 [`plan_swap_base_in`]。买入时输出为 base，直接按数量报价；
+/// 卖出时输出为 quote，按报价二分反推所需 base。
+pub fn plan_swap_base_out(
+    pool: &RaydiumPool,
+    liquidity: &Liquidity,
+    side: TradeSide,
+    amount_out: Decimal,
+    slippage_bps: Decimal,
+) -> Option<SwapPlan> {
+    let (in_decimals, out_decimals) = pool.decimals(side);
+    let liquidity = &pool.priced(liquidity)?;
+    let raw_out = to_raw(amount_out, out_decimals, false).filter(|raw| *raw > 0)?;
+    let amount_out = fixed::from_raw(raw_out, out_decimals)?;
+    let expected_in = match side {
+        TradeSide::Buy => liquidity.quote_base(side, amount_out)?.quote,
+        TradeSide::Sell => base_for_quote(liquidity, amount_out)?,
+    };
+    let maximum = expected_in * (Decimal::ONE + slippage_bps / BPS);
+    Some(SwapPlan {
+        side,
+        mode: SwapMode::BaseOut,
+        amount_in: to_raw(maximum, in_decimals, true)?,
+        amount_out: raw_out,
+        expected_in,
+        expected_out: amount_out,
+    })
+}
+
+/// 卖出得到 `quote_out` 所需的最少 base
+fn base_for_quote(liquidity: &Liquidity, quote_out: Decimal) -> Option<Decimal> {
+    let proceeds = |base: Decimal| liquidity.quote_base(TradeSide::Sell, base).map(|q| q.quote);
+    let mut high = Decimal::ONE;
+    while proceeds(high).is_some_and(|q| q < quote_out) {
+        high = high.checked_mul(Decimal::TWO)?;
+    }
+    proceeds(high)?;
+    let mut low = Decimal::ZERO;
+    for _ in 0..BISECT_ITERATIONS {
+        let mid = (low + high) / Decimal::TWO;
+        if proceeds(mid).is_some_and(|q| q >= quote_out) {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+    Some(high)
+}
@@ -1,3 +1,9 @@
+pub mod dex_collect;
+pub mod dexclient;
+pub mod executer;
+pub mod monitor;
+pub mod strategy;
+
 // use anchor_lang::prelude::*;
 // use anchor_spl::{
 //     token::{self, Mint, Token, TokenAccount},
@@ -0,0 +1,351 @@
+use chrono::{DateTime, TimeZone, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// K线周期
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Interval {
+    S1,
+    M1,
+    M5,
+    H1,
+    D1,
+}
+
+impl Interval {
+    pub const ALL: [Interval; 5] = [
+        Interval::S1,
+        Interval::M1,
+        Interval::M5,
+        Interval::H1,
+        Interval::D1,
+    ];
+
+    /// 周期秒数
+    pub fn seconds(&self) -> i64 {
+        match self {
+            Interval::S1 => 1,
+            Interval::M1 => 60,
+            Interval::M5 => 300,
+            Interval::H1 => 3_600,
+            Interval::D1 => 86_400,
+        }
+    }
+
+    /// 时间所在周期的起始时间 (unix 秒)
+    fn bucket(&self, time: DateTime<Utc>) -> i64 {
+        time.timestamp().div_euclid(self.seconds()) * self.seconds()
+    }
+}
+
+/// OHLCV K线
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Candle {
+    pub open_time: DateTime<Utc>,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    pub volume: Decimal, // 成交量，仅由成交累计
+    pub trades: u32,     // 成交笔数
+    #[serde(skip)]
+    first_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    last_at: Option<DateTime<Utc>>,
+}
+
+impl Candle {
+    fn new(open_time: DateTime<Utc>, price: Decimal) -> Self {
+        Self {
+            open_time,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: Decimal::ZERO,
+            trades: 0,
+            first_at: None,
+            last_at: None,
+        }
+    }
+
+    /// 无数据周期的平盘K线，价格沿用上一根收盘价
+    fn flat(open_time: DateTime<Utc>, price: Decimal) -> Self {
+        Self::new(open_time, price)
+    }
+
+    fn update(&mut self, price: Decimal, volume: Decimal, is_trade: bool, time: DateTime<Utc>) {
+        self.high = self.high.max(price);
+        self.low = self.low.min(price);
+        // 迟到数据按时间决定是否改写开盘/收盘
+        if self.first_at.is_none_or(|t| time < t) {
+            self.open = price;
+            self.first_at = Some(time);
+        }
+        if self.last_at.is_none_or(|t| time >= t) {
+            self.close = price;
+            self.last_at = Some(time);
+        }
+        if is_trade {
+            self.volume += volume;
+            self.trades += 1;
+        }
+    }
+}
+
+/// 单个市场单个周期的K线序列
+#[derive(Debug, Clone)]
+struct CandleSeries {
+    interval: Interval,
+    candles: BTreeMap<i64, Candle>,
+    max_len: usize,
+}
+
+impl CandleSeries {
+    fn new(interval: Interval, max_len: usize) -> Self {
+        Self {
+            interval,
+            candles: BTreeMap::new(),
+            max_len,
+        }
+    }
+
+    fn add(&mut self, price: Decimal, volume: Decimal, is_trade: bool, time: DateTime<Utc>) {
+        let bucket = self.interval.bucket(time);
+        // 早于保留窗口的迟到数据直接丢弃
+        if self.candles.len() >= self.max_len {
+            if let Some(oldest) = self.candles.keys().next() {
+                if bucket < *oldest {
+                    return;
+                }
+            }
+        }
+
+        let Some(open_time) = Utc.timestamp_opt(bucket, 0).single() else {
+            return;
+        };
+        self.candles
+            .entry(bucket)
+            .or_insert_with(|| Candle::new(open_time, price))
+            .update(price, volume, is_trade, time);
+
+        while self.candles.len() > self.max_len {
+            self.candles.pop_first();
+        }
+    }
+
+    /// 最近 n 根K线，截止到 `now` 所在周期，空周期以上一根收盘价补齐
+    ///
+    /// `n` 超过保留根数时按保留根数返回。
+    fn last(&self, n: usize, now: DateTime<Utc>) -> Vec<Candle> {
+        let n = n.min(self.max_len);
+        if n == 0 {
+            return Vec::new();
+        }
+        let step = self.interval.seconds();
+        let end = self.interval.bucket(now);
+        let start = end - step * (n as i64 - 1);
+
+        // 起点之前最近一根的收盘价作为补齐价格
+        let mut last_close = self
+            .candles
+            .range(..start)
+            .next_back()
+            .map(|(_, c)| c.close);
+
+        let mut result = Vec::with_capacity(n);
+        let mut bucket = start;
+        while bucket <= end {
+            match self.candles.get(&bucket) {
+                Some(candle) => {
+                    last_close = Some(candle.close);
+                    result.push(candle.clone());
+                }
+                None => {
+                    if let (Some(close), Some(open_time)) =
+                        (last_close, Utc.timestamp_opt(bucket, 0).single())
+                    {
+                        result.push(Candle::flat(open_time, close));
+                    }
+                }
+            }
+            bucket += step;
+        }
+        result
+    }
+}
+
+/// K线聚合器
+///
+/// 按市场和周期维护 1s/1m/5m/1h/1d K线。中间价只更新 OHLC，成交同时累计成交量；
+/// 迟到数据会写入对应周期，查询时空周期以平盘K线补齐。
+#[derive(Debug, Clone)]
+pub struct CandleAggregator {
+    series: HashMap<(String, Interval), CandleSeries>,
+    max_len: usize,
+}
+
+impl CandleAggregator {
+    /// `max_len` 为每个周期保留的最大K线数
+    pub fn new(max_len: usize) -> Self {
+        Self {
+            series: HashMap::new(),
+            max_len,
+        }
+    }
+
+    fn add(
+        &mut self,
+        market: &str,
+        price: Decimal,
+        volume: Decimal,
+        is_trade: bool,
+        time: DateTime<Utc>,
+    ) {
+        for interval in Interval::ALL {
+            self.series
+                .entry((market.to_string(), interval))
+                .or_insert_with(|| CandleSeries::new(interval, self.max_len))
+                .add(price, volume, is_trade, time);
+        }
+    }
+
+    /// 记录中间价
+    pub fn add_price(&mut self, market: &str, price: Decimal, time: DateTime<Utc>) {
+        self.add(market, price, Decimal::ZERO, false, time);
+    }
+
+    /// 记录一笔成交
+    pub fn add_trade(&mut self, market: &str, price: Decimal, size: Decimal, time: DateTime<Utc>) {
+        self.add(market, price, size, true, time);
+    }
+
+    /// 查询市场最近 n 根K线 (按时间升序)
+    pub fn last_candles(&self, market: &str, interval: Interval, n: usize) -> Vec<Candle> {
+        self.last_candles_at(market, interval, n, Utc::now())
+    }
+
+    /// 截止到指定时间的最近 n 根K线，用于回放
+    pub fn last_candles_at(
+        &self,
+        market: &str,
+        interval: Interval,
+        n: usize,
+        now: DateTime<Utc>,
+    ) -> Vec<Candle> {
+        self.series
+            .get(&(market.to_string(), interval))
+            .map(|series| series.last(n, now))
+            .unwrap_or_default()
+    }
+
+    /// 已有K线的市场
+    pub fn markets(&self) -> Vec<String> {
+        let mut markets: Vec<String> = self.series.keys().map(|(m, _)| m.clone()).collect();
+        markets.sort();
+        markets.dedup();
+        markets
+    }
+}
+
+impl Default for CandleAggregator {
+    fn default() -> Self {
+        Self::new(1440)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn t0() -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_040, 0).unwrap() // 整分钟
+    }
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        t0() + Duration::seconds(seconds)
+    }
+
+    #[test]
+    fn empty_intervals_are_filled_with_flat_candles() {
+        let mut series = CandleSeries::new(Interval::M1, 10);
+        series.add(Decimal::from(10), Decimal::ONE, true, at(10));
+        series.add(Decimal::from(12), Decimal::ZERO, false, at(125));
+
+        let candles = series.last(3, at(150));
+        let times: Vec<DateTime<Utc>> = candles.iter().map(|c| c.open_time).collect();
+        assert_eq!(times, vec![at(0), at(60), at(120)]);
+        assert_eq!(candles[0].volume, Decimal::ONE);
+        assert_eq!(candles[0].trades, 1);
+
+        // 空周期沿用上一根收盘价，没有成交量
+        let flat = &candles[1];
+        assert_eq!(
+            (flat.open, flat.high),
+            (Decimal::from(10), Decimal::from(10))
+        );
+        assert_eq!(
+            (flat.low, flat.close),
+            (Decimal::from(10), Decimal::from(10))
+        );
+        assert_eq!((flat.volume, flat.trades), (Decimal::ZERO, 0));
+        assert_eq!(candles[2].close, Decimal::from(12));
+
+        // 起点之前的收盘价用于补齐窗口开头
+        let candles = series.last(2, at(245));
+        assert_eq!(candles.len(), 2);
+        assert_eq!(candles[1].open_time, at(240));
+        assert_eq!(candles[1].open, Decimal::from(12));
+
+        // 第一根K线之前没有价格可补
+        assert_eq!(series.last(3, at(60)).len(), 2);
+    }
+
+    #[test]
+    fn late_data_rewrites_open_and_close_by_time() {
+        let mut series = CandleSeries::new(Interval::M1, 10);
+        series.add(Decimal::from(11), Decimal::ONE, true, at(30));
+        series.add(Decimal::from(9), Decimal::ONE, true, at(5));
+        series.add(Decimal::from(15), Decimal::ZERO, false, at(20));
+
+        let candle = &series.last(1, at(59))[0];
+        assert_eq!(candle.open, Decimal::from(9));
+        assert_eq!(candle.close, Decimal::from(11));
+        assert_eq!(candle.high, Decimal::from(15));
+        assert_eq!(candle.low, Decimal::from(9));
+        assert_eq!(candle.volume, Decimal::TWO);
+        assert_eq!(candle.trades, 2);
+
+        series.add(Decimal::from(13), Decimal::ZERO, false, at(30));
+        assert_eq!(series.last(1, at(59))[0].close, Decimal::from(13));
+    }
+
+    #[test]
+    fn retention_drops_oldest_and_rejects_older_late_data() {
+        let mut series = CandleSeries::new(Interval::M1, 3);
+        for minute in 0..4 {
+            series.add(
+                Decimal::from(10 + minute),
+                Decimal::ZERO,
+                false,
+                at(minute * 60),
+            );
+        }
+        assert_eq!(series.candles.len(), 3);
+        assert_eq!(series.candles.keys().next(), Some(&at(60).timestamp()));
+
+        // 早于保留窗口的迟到数据被丢弃，窗口内的照常写入
+        series.add(Decimal::from(1), Decimal::ONE, true, at(30));
+        assert_eq!(series.candles.len(), 3);
+        assert!(!series.candles.contains_key(&t0().timestamp()));
+        series.add(Decimal::from(1), Decimal::ONE, true, at(90));
+        assert_eq!(series.candles[&at(60).timestamp()].low, Decimal::ONE);
+
+        // 查询根数不超过保留根数
+        let candles = series.last(10, at(200));
+        assert_eq!(candles.len(), 3);
+        assert_eq!(candles[0].open_time, at(60));
+    }
+}
@@ -0,0 +1,456 @@
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::{Decimal, MathematicalOps};
+use serde::{Deserialize, Serialize};
+use std::{cmp::Reverse, collections::HashMap, fmt};
+
+use crate::dex_collect::fixed::BPS;
+use crate::dex_collect::serum::serum_depth::MarketDepth;
+use crate::dex_collect::stamp::SnapshotStamp;
+
+/// (价格, 数量) 档位列表
+type Levels = Vec<(Decimal, Decimal)>;
+
+/// 交易场所
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Venue {
+    Serum,
+    OpenBook,
+    Raydium,
+    Orca,
+    Other(String),
+}
+
+impl fmt::Display for Venue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Venue::Serum => write!(f, "serum"),
+            Venue::OpenBook => write!(f, "openbook"),
+            Venue::Raydium => write!(f, "raydium"),
+            Venue::Orca => write!(f, "orca"),
+            Venue::Other(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// 带来源场所的价格档位
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VenueLevel {
+    pub venue: Venue,
+    pub price: Decimal,
+    pub size: Decimal,
+}
+
+/// AMM 合成档位的价格步长和档数
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AmmLevelConfig {
+    pub step_bps: Decimal, // 相邻档位边际价格的间隔
+    pub count: usize,      // 每边档数
+}
+
+impl Default for AmmLevelConfig {
+    fn default() -> Self {
+        Self {
+            step_bps: Decimal::from(10),
+            count: 20,
+        }
+    }
+}
+
+/// 单个场所的最新深度
+#[derive(Debug, Clone)]
+struct VenueBook {
+    bids: Levels,
+    asks: Levels,
+    stamp: SnapshotStamp,
+}
+
+/// 跨场所最优买卖价
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConsolidatedBbo {
+    pub best_bid: Option<VenueLevel>,
+    pub best_ask: Option<VenueLevel>,
+    pub spread: Option<Decimal>,
+    pub crossed: bool, // 最优买价高于最优卖价
+    pub locked: bool,  // 最优买价等于最优卖价
+}
+
+/// 单个市场的跨场所合并订单簿
+///
+/// 各场所的深度独立保存，查询时合并；超过 `max_age` 未更新的场所不参与合并。
+/// 保存的档位价格已计入各场所的吃单手续费 (买价下调、卖价上调)，订单簿和
+/// AMM 的档位可以直接比较。
+#[derive(Debug, Clone)]
+pub struct ConsolidatedBook {
+    books: HashMap<Venue, VenueBook>,
+    max_age: Duration,
+    amm_levels: AmmLevelConfig,
+}
+
+impl ConsolidatedBook {
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            books: HashMap::new(),
+            max_age,
+            amm_levels: AmmLevelConfig::default(),
+        }
+    }
+
+    pub fn with_amm_levels(mut self, amm_levels: AmmLevelConfig) -> Self {
+        self.amm_levels = amm_levels;
+        self
+    }
+
+    /// 更新某个场所的深度，买卖盘为未计手续费的 (价格, 数量)
+    pub fn update(
+        &mut self,
+        venue: Venue,
+        bids: Levels,
+        asks: Levels,
+        fee_bps: Decimal,
+        stamp: SnapshotStamp,
+    ) {
+        let fee = fee_bps / BPS;
+        let with_fee = |levels: Levels, factor: Decimal| -> Levels {
+            levels
+                .into_iter()
+                .map(|(price, size)| (price * factor, size))
+                .collect()
+        };
+        self.books.insert(
+            venue,
+            VenueBook {
+                bids: with_fee(bids, Decimal::ONE - fee),
+                asks: with_fee(asks, Decimal::ONE + fee),
+                stamp,
+            },
+        );
+    }
+
+    /// 使用订单簿市场的深度快照更新，`fee_bps` 为吃单费率
+    pub fn update_depth(&mut self, venue: Venue, depth: &MarketDepth, fee_bps: Decimal) {
+        self.update(
+            venue,
+            depth.bids.iter().map(|l| (l.price, l.size)).collect(),
+            depth.asks.iter().map(|l| (l.price, l.size)).collect(),
+            fee_bps,
+            depth.stamp.clone(),
+        );
+    }
+
+    /// 使用恒定乘积 AMM 的储备更新，按配置的价格步长合成档位
+    pub fn update_amm(
+        &mut self,
+        venue: Venue,
+        base_reserve: Decimal,
+        quote_reserve: Decimal,
+        fee_bps: Decimal,
+        stamp: SnapshotStamp,
+    ) {
+        let (bids, asks) = amm_levels(base_reserve, quote_reserve, &self.amm_levels);
+        self.update(venue, bids, asks, fee_bps, stamp);
+    }
+
+    pub fn remove(&mut self, venue: &Venue) {
+        self.books.remove(venue);
+    }
+
+    /// 参与合并的场所
+    pub fn venues(&self, now: DateTime<Utc>) -> Vec<Venue> {
+        let mut venues: Vec<Venue> = self.fresh_books(now).map(|(v, _)| v.clone()).collect();
+        venues.sort();
+        venues
+    }
+
+    fn fresh_books(&self, now: DateTime<Utc>) -> impl Iterator<Item = (&Venue, &VenueBook)> {
+        let max_age = self.max_age;
+        self.books
+            .iter()
+            .filter(move |(_, book)| now - book.stamp.received_at <= max_age)
+    }
+
+    /// 合并后的买盘，价格从高到低
+    pub fn bids(&self, now: DateTime<Utc>) -> Vec<VenueLevel> {
+        let mut levels = self.merge(now, |book| &book.bids);
+        levels.sort_by_key(|l| (Reverse(l.price), l.venue.clone()));
+        levels
+    }
+
+    /// 合并后的卖盘，价格从低到高
+    pub fn asks(&self, now: DateTime<Utc>) -> Vec<VenueLevel> {
+        let mut levels = self.merge(now, |book| &book.asks);
+        levels.sort_by_key(|l| (l.price, l.venue.clone()));
+        levels
+    }
+
+    fn merge(&self, now: DateTime<Utc>, side: impl Fn(&VenueBook) -> &Levels) -> Vec<VenueLevel> {
+        self.fresh_books(now)
+            .flat_map(|(venue, book)| {
+                side(book)
+                    .iter()
+                    .filter(|(price, size)| *price > Decimal::ZERO && *size > Decimal::ZERO)
+                    .map(move |(price, size)| VenueLevel {
+                        venue: venue.clone(),
+                        price: *price,
+                        size: *size,
+                    })
+            })
+            .collect()
+    }
+
+    /// 跨场所最优买卖价，并标记是否交叉
+    pub fn bbo(&self, now: DateTime<Utc>) -> ConsolidatedBbo {
+        let best_bid = self.bids(now).into_iter().next();
+        let best_ask = self.asks(now).into_iter().next();
+        let spread = match (&best_bid, &best_ask) {
+            (Some(bid), Some(ask)) => Some(ask.price - bid.price),
+            _ => None,
+        };
+        ConsolidatedBbo {
+            crossed: spread.is_some_and(|s| s < Decimal::ZERO),
+            locked: spread.is_some_and(|s| s.is_zero()),
+            best_bid,
+            best_ask,
+            spread,
+        }
+    }
+}
+
+/// 按市场维护合并订单簿
+#[derive(Debug, Clone)]
+pub struct ConsolidatedAggregator {
+    books: HashMap<String, ConsolidatedBook>,
+    max_age: Duration,
+    amm_levels: AmmLevelConfig,
+}
+
+impl ConsolidatedAggregator {
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            books: HashMap::new(),
+            max_age,
+            amm_levels: AmmLevelConfig::default(),
+        }
+    }
+
+    /// 设置之后新建的合并订单簿使用的 AMM 档位配置
+    pub fn set_amm_levels(&mut self, amm_levels: AmmLevelConfig) {
+        self.amm_levels = amm_levels;
+    }
+
+    /// 市场的合并订单簿，不存在时创建
+    pub fn book_mut(&mut self, market: &str) -> &mut ConsolidatedBook {
+        let max_age = self.max_age;
+        let amm_levels = &self.amm_levels;
+        self.books
+            .entry(market.to_string())
+            .or_insert_with(|| ConsolidatedBook::new(max_age).with_amm_levels(amm_levels.clone()))
+    }
+
+    pub fn book(&self, market: &str) -> Option<&ConsolidatedBook> {
+        self.books.get(market)
+    }
+
+    /// 市场当前的跨场所最优买卖价
+    pub fn bbo(&self, market: &str) -> Option<ConsolidatedBbo> {
+        self.book(market).map(|book| book.bbo(Utc::now()))
+    }
+
+    /// 当前最优买卖价交叉的市场
+    pub fn crossed_markets(&self) -> Vec<(String, ConsolidatedBbo)> {
+        let now = Utc::now();
+        let mut crossed: Vec<(String, ConsolidatedBbo)> = self
+            .books
+            .iter()
+            .map(|(market, book)| (market.clone(), book.bbo(now)))
+            .filter(|(_, bbo)| bbo.crossed)
+            .collect();
+        crossed.sort_by(|a, b| a.0.cmp(&b.0));
+        crossed
+    }
+}
+
+impl Default for ConsolidatedAggregator {
+    fn default() -> Self {
+        Self::new(Duration::seconds(30))
+    }
+}
+
+/// 恒定乘积池合成档位，价格未计手续费
+///
+/// 以当前边际价格为起点，每隔 `step_bps` 取一档，每档数量为边际价格移动到该价位
+/// 需要成交的 base，档位价格为该段的均价。
+pub fn amm_levels(
+    base_reserve: Decimal,
+    quote_reserve: Decimal,
+    config: &AmmLevelConfig,
+) -> (Levels, Levels) {
+    let (x, y) = (base_reserve, quote_reserve);
+    let step = config.step_bps / BPS;
+    if x <= Decimal::ZERO || y <= Decimal::ZERO || step <= Decimal::ZERO {
+        return (Vec::new(), Vec::new());
+    }
+    // 边际价格变为当前的 r 倍时的储备: x / sqrt(r), y * sqrt(r)
+    let reserves_at = |r: Decimal| -> Option<(Decimal, Decimal)> {
+        let root = r.sqrt()?;
+        Some((x.checked_div(root)?, y.checked_mul(root)?))
+    };
+
+    let mut bids = Vec::with_capacity(config.count);
+    let mut asks = Vec::with_capacity(config.count);
+    let (mut ask_prev, mut bid_prev) = ((x, y), (x, y));
+    for i in 1..=config.count {
+        let Some(offset) = step.checked_mul(Decimal::from(i)) else {
+            break;
+        };
+        // 买入 base: 池中 base 减少、价格上升
+        if let Some(next) = reserves_at(Decimal::ONE + offset) {
+            let base_out = ask_prev.0 - next.0;
+            let quote_in = next.1 - ask_prev.1;
+            if base_out > Decimal::ZERO {
+                asks.push((quote_in / base_out, base_out));
+            }
+            ask_prev = next;
+        }
+
+        // 卖出 base: 池中 base 增加、价格下降
+        if offset < Decimal::ONE {
+            if let Some(next) = reserves_at(Decimal::ONE - offset) {
+                let base_in = next.0 - bid_prev.0;
+                let quote_out = bid_prev.1 - next.1;
+                if base_in > Decimal::ZERO {
+                    bids.push((quote_out / base_in, base_in));
+                }
+                bid_prev = next;
+            }
+        }
+    }
+    (bids, asks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stamp(received_at: DateTime<Utc>) -> SnapshotStamp {
+        SnapshotStamp {
+            received_at,
+            ..SnapshotStamp::new(1, "test")
+        }
+    }
+
+    fn dec(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    fn close(a: Decimal, b: Decimal) -> bool {
+        (a - b).abs() < dec("0.000000001")
+    }
+
+    /// Serum 和 Raydium 计入手续费后仍交叉，另有一个过期场所报出更高的买价
+    fn crossed_book(now: DateTime<Utc>) -> ConsolidatedBook {
+        let mut book = ConsolidatedBook::new(Duration::seconds(10));
+        let fee = Decimal::from(10);
+        book.update(
+            Venue::Serum,
+            vec![(dec("99"), dec("5"))],
+            vec![(dec("100"), dec("5"))],
+            fee,
+            stamp(now),
+        );
+        book.update(
+            Venue::Raydium,
+            vec![(dec("101"), dec("2")), (dec("100.5"), dec("3"))],
+            vec![(dec("102"), dec("2"))],
+            fee,
+            stamp(now - Duration::seconds(5)),
+        );
+        book.update(
+            Venue::Other("stale".to_string()),
+            vec![(dec("200"), dec("1"))],
+            vec![(dec("201"), dec("1"))],
+            Decimal::ZERO,
+            stamp(now - Duration::seconds(11)),
+        );
+        book
+    }
+
+    #[test]
+    fn bbo_uses_fee_adjusted_prices_and_skips_stale_venues() {
+        let now = Utc::now();
+        let book = crossed_book(now);
+        assert_eq!(book.venues(now), vec![Venue::Serum, Venue::Raydium]);
+
+        let bids = book.bids(now);
+        let prices: Vec<Decimal> = bids.iter().map(|l| l.price).collect();
+        assert_eq!(prices, vec![dec("100.899"), dec("100.3995"), dec("98.901")]);
+        assert_eq!(book.asks(now)[0].price, dec("100.100"));
+
+        let bbo = book.bbo(now);
+        assert_eq!(bbo.best_bid.unwrap().venue, Venue::Raydium);
+        assert_eq!(bbo.best_ask.unwrap().venue, Venue::Serum);
+        assert_eq!(bbo.spread, Some(dec("-0.799")));
+        assert!(bbo.crossed);
+        assert!(!bbo.locked);
+
+        // Raydium 也过期后只剩 Serum
+        let later = now + Duration::seconds(6);
+        assert_eq!(book.venues(later), vec![Venue::Serum]);
+        assert!(!book.bbo(later).crossed);
+    }
+
+    #[test]
+    fn equal_fee_adjusted_prices_are_locked() {
+        let now = Utc::now();
+        let mut book = crossed_book(now);
+        book.remove(&Venue::Raydium);
+        // 无手续费的买价正好等于 Serum 计入手续费后的卖价
+        book.update(
+            Venue::Orca,
+            vec![(dec("100.1"), dec("1"))],
+            Vec::new(),
+            Decimal::ZERO,
+            stamp(now),
+        );
+        let bbo = book.bbo(now);
+        assert_eq!(bbo.spread, Some(Decimal::ZERO));
+        assert!(bbo.locked);
+        assert!(!bbo.crossed);
+    }
+
+    #[test]
+    fn amm_levels_follow_constant_product_curve() {
+        let config = AmmLevelConfig {
+            step_bps: Decimal::from(100),
+            count: 2,
+        };
+        let (x, y) = (Decimal::from(100), Decimal::from(10_000));
+        let (bids, asks) = amm_levels(x, y, &config);
+        assert_eq!((bids.len(), asks.len()), (2, 2));
+
+        // 边际价格从 100 移到 100 * r 的均价为 100 * sqrt(r_prev * r)
+        let hundred = Decimal::from(100);
+        assert!(close(asks[0].0, hundred * dec("1.01").sqrt().unwrap()));
+        assert!(close(asks[1].0, hundred * dec("1.0302").sqrt().unwrap()));
+        assert!(close(bids[0].0, hundred * dec("0.99").sqrt().unwrap()));
+        assert!(close(bids[1].0, hundred * dec("0.9702").sqrt().unwrap()));
+
+        // 各档数量之和等于边际价格移到最后一档所需的 base
+        let bought: Decimal = asks.iter().map(|l| l.1).sum();
+        assert!(close(bought, x - x / dec("1.02").sqrt().unwrap()));
+        let sold: Decimal = bids.iter().map(|l| l.1).sum();
+        assert!(close(sold, x / dec("0.98").sqrt().unwrap() - x));
+
+        assert_eq!(
+            amm_levels(Decimal::ZERO, y, &config),
+            (Vec::new(), Vec::new())
+        );
+
+        // 合并订单簿中的 AMM 档位同样计入手续费
+        let now = Utc::now();
+        let mut book = ConsolidatedBook::new(Duration::seconds(10)).with_amm_levels(config);
+        book.update_amm(Venue::Orca, x, y, Decimal::from(30), stamp(now));
+        let bbo = book.bbo(now);
+        assert!(close(bbo.best_ask.unwrap().price, asks[0].0 * dec("1.003")));
+        assert!(close(bbo.best_bid.unwrap().price, bids[0].0 * dec("0.997")));
+    }
+}
@@ -0,0 +1,203 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::dex_collect::fixed::BPS;
+
+/// 微观结构指标配置
+#[derive(Debug, Clone)]
+pub struct MetricsConfig {
+    pub imbalance_levels: Vec<usize>, // 计算买卖不平衡的档位数
+    pub depth_bps: Vec<Decimal>,      // 统计中间价 ±X bps 内的深度
+    pub weighted_levels: usize,       // 加权中间价使用的档位数
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            imbalance_levels: vec![1, 5, 10],
+            depth_bps: vec![Decimal::from(10), Decimal::from(50), Decimal::from(100)],
+            weighted_levels: 5,
+        }
+    }
+}
+
+/// 前 N 档的买卖不平衡，取值 [-1, 1]，正数表示买盘更厚
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Imbalance {
+    pub levels: usize,
+    pub value: Decimal,
+}
+
+/// 中间价 ±bps 范围内的挂单量
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DepthBand {
+    pub bps: Decimal,
+    pub bid_size: Decimal,
+    pub ask_size: Decimal,
+}
+
+/// 订单簿快照的微观结构指标
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BookMetrics {
+    pub mid: Option<Decimal>,
+    pub microprice: Option<Decimal>, // 按对手方最优档数量加权的中间价
+    pub weighted_mid: Option<Decimal>, // 两侧前 N 档均价的中点
+    pub spread_bps: Option<Decimal>,
+    pub imbalance: Vec<Imbalance>,
+    pub depth: Vec<DepthBand>,
+    pub bid_levels: usize,
+    pub ask_levels: usize,
+    pub bid_touch_size: Decimal, // 最优买价的排队数量
+    pub ask_touch_size: Decimal, // 最优卖价的排队数量
+}
+
+impl BookMetrics {
+    /// 根据排好序的买卖盘 (价格, 数量) 计算指标
+    pub fn compute(
+        bids: &[(Decimal, Decimal)],
+        asks: &[(Decimal, Decimal)],
+        config: &MetricsConfig,
+    ) -> Self {
+        let best_bid = bids.first().copied();
+        let best_ask = asks.first().copied();
+
+        let mut metrics = BookMetrics {
+            bid_levels: bids.len(),
+            ask_levels: asks.len(),
+            bid_touch_size: best_bid.map(|(_, size)| size).unwrap_or_default(),
+            ask_touch_size: best_ask.map(|(_, size)| size).unwrap_or_default(),
+            ..BookMetrics::default()
+        };
+
+        metrics.imbalance = config
+            .imbalance_levels
+            .iter()
+            .filter_map(|&levels| {
+                let bid_size = side_size(bids, levels);
+                let ask_size = side_size(asks, levels);
+                let total = bid_size + ask_size;
+                (!total.is_zero()).then(|| Imbalance {
+                    levels,
+                    value: (bid_size - ask_size) / total,
+                })
+            })
+            .collect();
+
+        let (Some((bid, bid_size)), Some((ask, ask_size))) = (best_bid, best_ask) else {
+            return metrics;
+        };
+        let mid = (bid + ask) / Decimal::TWO;
+        metrics.mid = Some(mid);
+        if !mid.is_zero() {
+            metrics.spread_bps = Some((ask - bid) / mid * BPS);
+        }
+
+        let touch_total = bid_size + ask_size;
+        if !touch_total.is_zero() {
+            metrics.microprice = Some((bid * ask_size + ask * bid_size) / touch_total);
+        }
+
+        if let (Some(bid_avg), Some(ask_avg)) = (
+            side_average(bids, config.weighted_levels),
+            side_average(asks, config.weighted_levels),
+        ) {
+            metrics.weighted_mid = Some((bid_avg + ask_avg) / Decimal::TWO);
+        }
+
+        metrics.depth = config
+            .depth_bps
+            .iter()
+            .map(|&bps| {
+                let offset = mid * bps / BPS;
+                DepthBand {
+                    bps,
+                    bid_size: bids
+                        .iter()
+                        .filter(|(price, _)| *price >= mid - offset)
+                        .map(|(_, size)| *size)
+                        .sum(),
+                    ask_size: asks
+                        .iter()
+                        .filter(|(price, _)| *price <= mid + offset)
+                        .map(|(_, size)| *size)
+                        .sum(),
+                }
+            })
+            .collect();
+
+        metrics
+    }
+}
+
+fn side_size(levels: &[(Decimal, Decimal)], n: usize) -> Decimal {
+    levels.iter().take(n).map(|(_, size)| *size).sum()
+}
+
+/// 前 n 档按数量加权的均价
+fn side_average(levels: &[(Decimal, Decimal)], n: usize) -> Option<Decimal> {
+    let (notional, size) = levels.iter().take(n).fold(
+        (Decimal::ZERO, Decimal::ZERO),
+        |(notional, total), (price, size)| (notional + *price * *size, total + *size),
+    );
+    (!size.is_zero()).then(|| notional / size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn side(levels: &[(i64, i64)]) -> Vec<(Decimal, Decimal)> {
+        levels
+            .iter()
+            .map(|&(price, size)| (Decimal::from(price), Decimal::from(size)))
+            .collect()
+    }
+
+    #[test]
+    fn computes_touch_and_depth_metrics() {
+        let config = MetricsConfig {
+            imbalance_levels: vec![1, 2],
+            depth_bps: vec![Decimal::from(100)],
+            weighted_levels: 2,
+        };
+        let bids = side(&[(99, 3), (98, 1)]);
+        let asks = side(&[(101, 1), (102, 3)]);
+        let metrics = BookMetrics::compute(&bids, &asks, &config);
+
+        assert_eq!(metrics.mid, Some(Decimal::from(100)));
+        assert_eq!(metrics.spread_bps, Some(Decimal::from(200)));
+        // 买盘更厚，微观价格偏向卖价
+        assert_eq!(metrics.microprice, Some(Decimal::new(1005, 1)));
+        assert_eq!(metrics.weighted_mid, Some(Decimal::new(10025, 2)));
+        assert_eq!(
+            metrics.imbalance,
+            vec![
+                Imbalance {
+                    levels: 1,
+                    value: Decimal::new(5, 1)
+                },
+                Imbalance {
+                    levels: 2,
+                    value: Decimal::ZERO
+                },
+            ]
+        );
+        assert_eq!(
+            metrics.depth,
+            vec![DepthBand {
+                bps: Decimal::from(100),
+                bid_size: Decimal::from(3),
+                ask_size: Decimal::ONE,
+            }]
+        );
+    }
+
+    #[test]
+    fn one_sided_book_has_no_mid() {
+        let bids = side(&[(99, 3)]);
+        let metrics = BookMetrics::compute(&bids, &[], &MetricsConfig::default());
+        assert_eq!(metrics.mid, None);
+        assert_eq!(metrics.ask_levels, 0);
+        assert!(metrics.imbalance.iter().all(|i| i.value == Decimal::ONE));
+    }
+}
@@ -0,0 +1,5 @@
+pub mod candle;
+pub mod consolidated;
+pub mod microstructure;
+pub mod reference;
+pub mod volatility;
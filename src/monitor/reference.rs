@@ -0,0 +1,261 @@
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use std::collections::{HashMap, VecDeque};
+
+/// (交易场所, 市场)
+type Key = (String, String);
+
+/// 参考价格计算
+///
+/// 按交易场所和市场保存中间价快照和成交，计算任意窗口的 TWAP/VWAP。
+/// 超过 `retention` 的数据会被清理，窗口不应大于保留时长。
+#[derive(Debug, Clone)]
+pub struct ReferencePrices {
+    mids: HashMap<Key, VecDeque<(Decimal, DateTime<Utc>)>>,
+    fills: HashMap<Key, VecDeque<(Decimal, Decimal, DateTime<Utc>)>>,
+    retention: Duration,
+}
+
+impl ReferencePrices {
+    pub fn new(retention: Duration) -> Self {
+        Self {
+            mids: HashMap::new(),
+            fills: HashMap::new(),
+            retention,
+        }
+    }
+
+    fn key(venue: &str, market: &str) -> Key {
+        (venue.to_string(), market.to_string())
+    }
+
+    /// 记录一次中间价快照，迟到数据按时间插入
+    pub fn add_mid(&mut self, venue: &str, market: &str, price: Decimal, time: DateTime<Utc>) {
+        let retention = self.retention;
+        let samples = self.mids.entry(Self::key(venue, market)).or_default();
+        let index = samples.partition_point(|(_, t)| *t <= time);
+        samples.insert(index, (price, time));
+        prune(samples, retention, |(_, t)| *t);
+    }
+
+    /// 记录一笔成交
+    pub fn add_fill(
+        &mut self,
+        venue: &str,
+        market: &str,
+        price: Decimal,
+        size: Decimal,
+        time: DateTime<Utc>,
+    ) {
+        let retention = self.retention;
+        let fills = self.fills.entry(Self::key(venue, market)).or_default();
+        let index = fills.partition_point(|(_, _, t)| *t <= time);
+        fills.insert(index, (price, size, time));
+        prune(fills, retention, |(_, _, t)| *t);
+    }
+
+    /// 时间加权平均价
+    ///
+    /// 每个快照价格持续到下一个快照，窗口起点之前最后一个快照覆盖起点。
+    pub fn twap(
+        &self,
+        venue: &str,
+        market: &str,
+        window: Duration,
+        now: DateTime<Utc>,
+    ) -> Option<Decimal> {
+        let samples = self.mids.get(&Self::key(venue, market))?;
+        let start = now - window;
+        let first = samples
+            .partition_point(|(_, t)| *t <= start)
+            .saturating_sub(1);
+
+        let mut weighted = Decimal::ZERO;
+        let mut total_ms = 0i64;
+        let mut last_price = None;
+        let mut iter = samples.range(first..).peekable();
+        while let Some((price, time)) = iter.next() {
+            if *time > now {
+                break;
+            }
+            let from = (*time).max(start);
+            let to = iter.peek().map(|(_, t)| (*t).min(now)).unwrap_or(now);
+            let ms = (to - from).num_milliseconds();
+            if ms > 0 {
+                weighted += *price * Decimal::from(ms);
+                total_ms += ms;
+            }
+            last_price = Some(*price);
+        }
+
+        if total_ms > 0 {
+            Some(weighted / Decimal::from(total_ms))
+        } else {
+            last_price
+        }
+    }
+
+    /// 成交量加权平均价，窗口内无成交时返回 None
+    pub fn vwap(
+        &self,
+        venue: &str,
+        market: &str,
+        window: Duration,
+        now: DateTime<Utc>,
+    ) -> Option<Decimal> {
+        let fills = self.fills.get(&Self::key(venue, market))?;
+        let start = now - window;
+        let (notional, volume) = fills
+            .iter()
+            .filter(|(_, _, t)| *t > start && *t <= now)
+            .fold(
+                (Decimal::ZERO, Decimal::ZERO),
+                |(n, v), (price, size, _)| (n + *price * *size, v + *size),
+            );
+        if volume.is_zero() {
+            None
+        } else {
+            Some(notional / volume)
+        }
+    }
+
+    /// 窗口内成交量
+    pub fn volume(
+        &self,
+        venue: &str,
+        market: &str,
+        window: Duration,
+        now: DateTime<Utc>,
+    ) -> Decimal {
+        let start = now - window;
+        self.fills
+            .get(&Self::key(venue, market))
+            .map(|fills| {
+                fills
+                    .iter()
+                    .filter(|(_, _, t)| *t > start && *t <= now)
+                    .map(|(_, size, _)| *size)
+                    .sum()
+            })
+            .unwrap_or(Decimal::ZERO)
+    }
+}
+
+impl Default for ReferencePrices {
+    fn default() -> Self {
+        Self::new(Duration::days(1))
+    }
+}
+
+/// 清理超过保留时长的数据，以最新一条的时间为基准
+fn prune<T>(items: &mut VecDeque<T>, retention: Duration, time: impl Fn(&T) -> DateTime<Utc>) {
+    let Some(latest) = items.back().map(&time) else {
+        return;
+    };
+    // TWAP 需要窗口起点之前的一个快照，因此保留一条超期数据
+    while items.len() > 1 && time(&items[1]) < latest - retention {
+        items.pop_front();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VENUE: &str = "serum";
+    const MARKET: &str = "SOL/USDC";
+
+    fn secs(n: i64) -> Duration {
+        Duration::seconds(n)
+    }
+
+    #[test]
+    fn twap_carries_in_sample_before_window() {
+        let now = Utc::now();
+        let mut refs = ReferencePrices::default();
+        // 乱序写入，按时间插入
+        refs.add_mid(VENUE, MARKET, Decimal::from(20), now - secs(30));
+        refs.add_mid(VENUE, MARKET, Decimal::from(100), now + secs(10));
+        refs.add_mid(VENUE, MARKET, Decimal::from(10), now - secs(100));
+
+        // 10 覆盖窗口前 30 秒，20 覆盖后 30 秒，晚于 now 的快照不计入
+        let twap = refs.twap(VENUE, MARKET, secs(60), now);
+        assert_eq!(twap, Some(Decimal::from(15)));
+
+        // 窗口内没有快照时沿用起点之前的价格
+        assert_eq!(
+            refs.twap(VENUE, MARKET, secs(10), now),
+            Some(Decimal::from(20))
+        );
+        assert_eq!(refs.twap(VENUE, "ETH/USDC", secs(60), now), None);
+    }
+
+    #[test]
+    fn vwap_and_volume_use_fills_inside_window() {
+        let now = Utc::now();
+        let mut refs = ReferencePrices::default();
+        refs.add_fill(
+            VENUE,
+            MARKET,
+            Decimal::from(20),
+            Decimal::from(3),
+            now - secs(10),
+        );
+        refs.add_fill(
+            VENUE,
+            MARKET,
+            Decimal::from(10),
+            Decimal::ONE,
+            now - secs(50),
+        );
+        refs.add_fill(
+            VENUE,
+            MARKET,
+            Decimal::from(30),
+            Decimal::ONE,
+            now - secs(70),
+        );
+        refs.add_fill(
+            VENUE,
+            MARKET,
+            Decimal::from(40),
+            Decimal::ONE,
+            now - secs(60),
+        );
+
+        // 窗口起点不含，(10 * 1 + 20 * 3) / 4
+        let vwap = refs.vwap(VENUE, MARKET, secs(60), now);
+        assert_eq!(vwap, Some("17.5".parse().unwrap()));
+        assert_eq!(refs.volume(VENUE, MARKET, secs(60), now), Decimal::from(4));
+        assert_eq!(refs.vwap(VENUE, MARKET, secs(5), now), None);
+        assert_eq!(refs.volume(VENUE, MARKET, secs(5), now), Decimal::ZERO);
+    }
+
+    #[test]
+    fn pruning_keeps_one_sample_before_retention() {
+        let now = Utc::now();
+        let mut refs = ReferencePrices::new(secs(100));
+        for (price, age) in [(1, 300), (2, 250), (4, 50), (8, 0)] {
+            refs.add_mid(VENUE, MARKET, Decimal::from(price), now - secs(age));
+            refs.add_fill(
+                VENUE,
+                MARKET,
+                Decimal::from(price),
+                Decimal::ONE,
+                now - secs(age),
+            );
+        }
+
+        let key = ReferencePrices::key(VENUE, MARKET);
+        assert_eq!(refs.mids[&key].len(), 3);
+        assert_eq!(refs.fills[&key].len(), 3);
+
+        // 250 秒前的快照覆盖窗口前半段
+        let twap = refs.twap(VENUE, MARKET, secs(100), now);
+        assert_eq!(twap, Some(Decimal::from(3)));
+        assert_eq!(
+            refs.volume(VENUE, MARKET, secs(1_000), now),
+            Decimal::from(3)
+        );
+    }
+}
@@ -0,0 +1,231 @@
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use serde::{Deserialize, Serialize};
+
+use crate::monitor::candle::{Candle, Interval};
+
+const SECONDS_PER_YEAR: f64 = 365.0 * 86_400.0;
+
+/// 已实现波动率统计，波动率为对数收益率标准差
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VolatilityStats {
+    pub samples: usize,              // 参与计算的收益率个数
+    pub close_to_close: Option<f64>, // 每根K线
+    pub parkinson: Option<f64>,      // 每根K线，基于高低价
+    pub annualized_close_to_close: Option<f64>,
+    pub annualized_parkinson: Option<f64>,
+    pub last_return_zscore: Option<f64>, // 最新收益率相对此前收益率的 z 值
+}
+
+/// 根据K线计算已实现波动率
+pub fn realized_volatility(candles: &[Candle], interval: Interval) -> VolatilityStats {
+    let closes: Vec<Decimal> = candles.iter().map(|c| c.close).collect();
+    let returns = log_returns(&closes);
+    let close_to_close = std_dev(&returns);
+
+    let ranges: Vec<f64> = candles
+        .iter()
+        .filter_map(|c| Some(ln_ratio(c.high, c.low)?.powi(2)))
+        .collect();
+    let parkinson = (!ranges.is_empty())
+        .then(|| (ranges.iter().sum::<f64>() / (4.0 * ranges.len() as f64 * 2f64.ln())).sqrt());
+
+    let periods_per_year = SECONDS_PER_YEAR / interval.seconds() as f64;
+    VolatilityStats {
+        samples: returns.len(),
+        close_to_close,
+        parkinson,
+        annualized_close_to_close: close_to_close.map(|v| v * periods_per_year.sqrt()),
+        annualized_parkinson: parkinson.map(|v| v * periods_per_year.sqrt()),
+        last_return_zscore: last_zscore(&returns),
+    }
+}
+
+/// 相邻价格的对数收益率，跳过非正价格
+pub fn log_returns(prices: &[Decimal]) -> Vec<f64> {
+    prices
+        .windows(2)
+        .filter_map(|w| ln_ratio(w[1], w[0]))
+        .collect()
+}
+
+/// 最新收益率相对此前收益率均值和标准差的 z 值
+pub fn last_zscore(returns: &[f64]) -> Option<f64> {
+    let (last, history) = returns.split_last()?;
+    let mean = mean(history)?;
+    let std = std_dev(history)?;
+    (std > 0.0).then(|| (last - mean) / std)
+}
+
+fn ln_ratio(a: Decimal, b: Decimal) -> Option<f64> {
+    let (a, b) = (a.to_f64()?, b.to_f64()?);
+    (a > 0.0 && b > 0.0).then(|| (a / b).ln())
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+/// 样本标准差，至少需要两个样本
+fn std_dev(values: &[f64]) -> Option<f64> {
+    if values.len() < 2 {
+        return None;
+    }
+    let mean = mean(values)?;
+    let variance =
+        values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64;
+    Some(variance.sqrt())
+}
+
+/// 跳变检测配置
+#[derive(Debug, Clone)]
+pub struct JumpConfig {
+    pub sigmas: f64,        // 超过多少倍标准差视为跳变
+    pub window: Duration,   // 检测窗口 M 秒
+    pub lookback: Duration, // 估计波动率使用的历史长度
+    pub min_samples: usize, // 估计波动率至少需要的收益率个数
+}
+
+impl Default for JumpConfig {
+    fn default() -> Self {
+        Self {
+            sigmas: 5.0,
+            window: Duration::seconds(10),
+            lookback: Duration::hours(1),
+            min_samples: 20,
+        }
+    }
+}
+
+/// 检测到的价格跳变
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Jump {
+    pub from_price: Decimal,
+    pub to_price: Decimal,
+    pub from_time: DateTime<Utc>,
+    pub to_time: DateTime<Utc>,
+    pub log_return: f64,
+    pub sigmas: f64, // 相对同时长预期波动的倍数
+}
+
+impl Jump {
+    /// 是否为下跌 (闪崩)
+    pub fn is_drop(&self) -> bool {
+        self.log_return < 0.0
+    }
+}
+
+/// 检测最新价格相对窗口内价格的跳变
+///
+/// 用窗口之前 `lookback` 内的收益率估计每秒方差，按时长换算成窗口内的预期波动，
+/// 最新价格相对窗口内任一价格的变动超过 `sigmas` 倍时返回跳变。
+/// `prices` 需按时间升序。
+pub fn detect_jump(prices: &[(Decimal, DateTime<Utc>)], config: &JumpConfig) -> Option<Jump> {
+    let &(last_price, last_time) = prices.last()?;
+    let window_start = last_time - config.window;
+    let history_start = window_start - config.lookback;
+
+    // 窗口之前的历史估计每秒方差，避免跳变本身抬高波动率
+    let history: Vec<&(Decimal, DateTime<Utc>)> = prices
+        .iter()
+        .filter(|(_, t)| *t >= history_start && *t < window_start)
+        .collect();
+    let mut squared = 0.0;
+    let mut seconds = 0.0;
+    let mut samples = 0;
+    for pair in history.windows(2) {
+        let dt = (pair[1].1 - pair[0].1).num_milliseconds() as f64 / 1000.0;
+        if let (Some(r), true) = (ln_ratio(pair[1].0, pair[0].0), dt > 0.0) {
+            squared += r * r;
+            seconds += dt;
+            samples += 1;
+        }
+    }
+    if samples < config.min_samples || seconds <= 0.0 || squared <= 0.0 {
+        return None;
+    }
+    let variance_per_second = squared / seconds;
+
+    prices
+        .iter()
+        .filter(|(_, t)| *t >= window_start && *t < last_time)
+        .filter_map(|&(price, time)| {
+            let log_return = ln_ratio(last_price, price)?;
+            let dt = (last_time - time).num_milliseconds() as f64 / 1000.0;
+            let expected = (variance_per_second * dt).sqrt();
+            (expected > 0.0).then(|| Jump {
+                from_price: price,
+                to_price: last_price,
+                from_time: time,
+                to_time: last_time,
+                log_return,
+                sigmas: log_return.abs() / expected,
+            })
+        })
+        .filter(|jump| jump.sigmas >= config.sigmas)
+        .max_by(|a, b| a.sigmas.total_cmp(&b.sigmas))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::candle::CandleAggregator;
+
+    #[test]
+    fn close_to_close_from_alternating_candles() {
+        let start = DateTime::from_timestamp(1_700_000_040, 0).unwrap();
+        let mut candles = CandleAggregator::new(16);
+        for i in 0..5 {
+            let price = if i % 2 == 0 { 100 } else { 110 };
+            candles.add_price(
+                "SOL/USDC",
+                Decimal::from(price),
+                start + Duration::minutes(i),
+            );
+        }
+        let now = start + Duration::minutes(4);
+        let stats = realized_volatility(
+            &candles.last_candles_at("SOL/USDC", Interval::M1, 5, now),
+            Interval::M1,
+        );
+
+        // 收益率为 ±ln(1.1)，均值 0，样本方差 4r²/3
+        let r = 1.1f64.ln();
+        let expected = r * (4.0f64 / 3.0).sqrt();
+        assert_eq!(stats.samples, 4);
+        assert!((stats.close_to_close.unwrap() - expected).abs() < 1e-12);
+        assert_eq!(stats.parkinson, Some(0.0));
+        let annualized = expected * (SECONDS_PER_YEAR / 60.0).sqrt();
+        assert!((stats.annualized_close_to_close.unwrap() - annualized).abs() < 1e-9);
+    }
+
+    #[test]
+    fn log_returns_skip_non_positive_prices() {
+        let prices = [Decimal::ONE, Decimal::ZERO, Decimal::TWO, Decimal::from(4)];
+        let returns = log_returns(&prices);
+        assert_eq!(returns, vec![2f64.ln()]);
+        assert_eq!(last_zscore(&returns), None);
+    }
+
+    #[test]
+    fn detects_drop_against_quiet_history() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let mut prices: Vec<(Decimal, DateTime<Utc>)> = (0..35)
+            .map(|i| {
+                let price = if i % 2 == 0 { 100 } else { 101 };
+                (Decimal::from(price), start + Duration::seconds(i))
+            })
+            .collect();
+        let config = JumpConfig::default();
+
+        let mut quiet = prices.clone();
+        quiet.push((Decimal::from(101), start + Duration::seconds(35)));
+        assert_eq!(detect_jump(&quiet, &config), None);
+
+        prices.push((Decimal::from(80), start + Duration::seconds(35)));
+        let jump = detect_jump(&prices, &config).unwrap();
+        assert!(jump.is_drop());
+        assert!(jump.sigmas >= config.sigmas);
+        assert_eq!(jump.to_price, Decimal::from(80));
+    }
+}
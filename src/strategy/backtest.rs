@@ -0,0 +1,345 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use crate::dex_collect::fixed::Decimal;
+use crate::executer::paper::{PaperConfig, PaperExchange};
+use crate::executer::pnl::PnlReport;
+use crate::strategy::runtime::{ExecutionReport, Fill, MarketEvent, Strategy, StrategyHost};
+
+/// 单次回调后指令与回报来回处理的上限，防止策略无限下单撤单
+const MAX_ROUNDS: usize = 100;
+
+/// 回测配置
+#[derive(Debug, Clone)]
+pub struct BacktestConfig {
+    pub paper: PaperConfig, // 延迟、费率、初始余额，默认开启排队模型
+    pub timer: Duration,    // on_timer 的间隔，按回放时间触发
+}
+
+impl Default for BacktestConfig {
+    fn default() -> Self {
+        Self {
+            paper: PaperConfig {
+                queue_model: true,
+                ..Default::default()
+            },
+            timer: Duration::seconds(1),
+        }
+    }
+}
+
+/// 权益曲线上的一点，权益为以 quote 计价的净盈亏
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EquityPoint {
+    pub slot: u64,
+    pub time: DateTime<Utc>,
+    pub equity: Decimal,
+}
+
+/// 回测结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BacktestReport {
+    pub equity_curve: Vec<EquityPoint>,
+    pub trades: Vec<Fill>,
+    pub closing_trades: u64, // 各市场减仓或平仓成交之和，取自 `pnl`
+    pub winning_trades: u64, // 各市场扣除手续费后盈利的减仓成交之和
+    pub hit_rate: Option<Decimal>,
+    pub max_drawdown: Decimal, // 权益从高点回落的最大幅度
+    pub pnl: PnlReport,
+    pub events: usize,
+}
+
+/// 回测引擎
+///
+/// 按 slot 顺序回放记录的订单簿、池子状态和成交，驱动策略回调；
+/// 订单在 [`PaperExchange`] 中撮合，订单簿挂单使用排队模型，AMM 使用精确数学。
+pub struct Backtester<S: Strategy> {
+    host: StrategyHost<S>,
+    exchange: PaperExchange,
+    config: BacktestConfig,
+    report: BacktestReport,
+    peak: Option<Decimal>,
+}
+
+impl<S: Strategy> Backtester<S> {
+    pub fn new(strategy: S, config: BacktestConfig) -> Self {
+        Self {
+            host: StrategyHost::new(strategy),
+            exchange: PaperExchange::new(config.paper.clone()),
+            config,
+            report: BacktestReport {
+                equity_curve: Vec::new(),
+                trades: Vec::new(),
+                closing_trades: 0,
+                winning_trades: 0,
+                hit_rate: None,
+                max_drawdown: Decimal::ZERO,
+                pnl: PnlReport::default(),
+                events: 0,
+            },
+            peak: None,
+        }
+    }
+
+    /// 回放全部事件，返回报告和策略
+    pub fn run(mut self, mut events: Vec<MarketEvent>) -> (BacktestReport, S) {
+        // 同一 slot 内按接收时间排序，sort_by_key 为稳定排序
+        events.sort_by_key(|e| (e.stamp().slot, e.stamp().received_at));
+        let Some(first) = events.first() else {
+            return (self.report, self.host.into_strategy());
+        };
+
+        let start = event_time(first);
+        self.host.start(start);
+        self.pump(Vec::new(), start);
+        let mut next_timer = start + self.config.timer;
+
+        for event in &events {
+            let now = event_time(event);
+            while self.config.timer > Duration::zero() && next_timer <= now {
+                let reports = self.exchange.process(next_timer);
+                self.pump(reports, next_timer);
+                self.host.timer(next_timer);
+                self.pump(Vec::new(), next_timer);
+                next_timer += self.config.timer;
+            }
+
+            // 先让交易所看到行情，挂单按这次更新撮合后再通知策略
+            let reports = self.exchange.on_market_event(event, now);
+            self.pump(reports, now);
+            self.host.handle_event(event);
+            self.pump(Vec::new(), now);
+
+            self.record_equity(event.stamp().slot, now);
+            self.report.events += 1;
+        }
+
+        self.report.pnl = self.exchange.pnl_report();
+        self.report.closing_trades = self
+            .report
+            .pnl
+            .markets
+            .iter()
+            .map(|m| m.closing_trades)
+            .sum();
+        self.report.winning_trades = self
+            .report
+            .pnl
+            .markets
+            .iter()
+            .map(|m| m.winning_trades)
+            .sum();
+        self.report.hit_rate = (self.report.closing_trades > 0).then(|| {
+            Decimal::from(self.report.winning_trades) / Decimal::from(self.report.closing_trades)
+        });
+        (self.report, self.host.into_strategy())
+    }
+
+    /// 把回报交给策略，并把策略新发出的指令交给交易所，直到没有新的指令
+    fn pump(&mut self, reports: Vec<ExecutionReport>, now: DateTime<Utc>) {
+        let mut queue: VecDeque<ExecutionReport> = reports.into();
+        for _ in 0..MAX_ROUNDS {
+            while let Some(report) = queue.pop_front() {
+                if let ExecutionReport::Filled(fill) = &report {
+                    self.report.trades.push(fill.clone());
+                }
+                self.host.handle_report(&report);
+            }
+            let commands = self.host.take_commands();
+            if commands.is_empty() {
+                return;
+            }
+            for command in commands {
+                queue.extend(self.exchange.submit(command, now));
+            }
+        }
+        log::warn!(
+            "策略 {} 单次回调指令过多，已截断",
+            self.host.strategy().name()
+        );
+    }
+
+    /// 记录权益并更新最大回撤，同一 slot 只保留最后一点
+    fn record_equity(&mut self, slot: u64, time: DateTime<Utc>) {
+        let equity = self.exchange.pnl_report().net;
+        let peak = self.peak.map_or(equity, |peak| peak.max(equity));
+        self.peak = Some(peak);
+        self.report.max_drawdown = self.report.max_drawdown.max(peak - equity);
+
+        let point = EquityPoint { slot, time, equity };
+        match self.report.equity_curve.last_mut() {
+            Some(last) if last.slot == slot => *last = point,
+            _ => self.report.equity_curve.push(point),
+        }
+    }
+}
+
+fn event_time(event: &MarketEvent) -> DateTime<Utc> {
+    let stamp = event.stamp();
+    stamp.block_time.unwrap_or(stamp.received_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dex_collect::serum::serum_depth::{Level, MarketDepth};
+    use crate::dex_collect::serum::serum_slippage::TradeSide;
+    use crate::dex_collect::stamp::SnapshotStamp;
+    use crate::strategy::quote::Liquidity;
+    use crate::strategy::runtime::{OrderType, StrategyContext, Trade};
+    use std::collections::HashMap;
+
+    const MARKET: &str = "SOL/USDC";
+
+    /// 第一次收到订单簿时吃单买入 2 个，同时在 12 挂卖单平仓
+    #[derive(Default)]
+    struct Scripted {
+        placed: bool,
+        timers: usize,
+        fills: usize,
+    }
+
+    impl Strategy for Scripted {
+        fn name(&self) -> &str {
+            "scripted"
+        }
+
+        fn on_book_update(&mut self, ctx: &mut StrategyContext, market: &str, _: &Liquidity) {
+            if self.placed {
+                return;
+            }
+            self.placed = true;
+            let two = Decimal::TWO;
+            ctx.place_order(
+                market,
+                TradeSide::Buy,
+                Decimal::from(10),
+                two,
+                OrderType::Limit,
+            );
+            ctx.place_order(
+                market,
+                TradeSide::Sell,
+                Decimal::from(12),
+                two,
+                OrderType::Limit,
+            );
+        }
+
+        fn on_timer(&mut self, _ctx: &mut StrategyContext) {
+            self.timers += 1;
+        }
+
+        fn on_fill(&mut self, _ctx: &mut StrategyContext, _fill: &Fill) {
+            self.fills += 1;
+        }
+    }
+
+    fn level(price: i64, size: i64) -> Level {
+        Level {
+            price: Decimal::from(price),
+            size: Decimal::from(size),
+            total: Decimal::from(size),
+        }
+    }
+
+    fn stamp(slot: u64, time: DateTime<Utc>) -> SnapshotStamp {
+        SnapshotStamp::new(slot, "test").with_block_time(Some(time))
+    }
+
+    fn book(bids: Vec<Level>, asks: Vec<Level>, slot: u64, time: DateTime<Utc>) -> MarketEvent {
+        MarketEvent::Book {
+            market: MARKET.to_string(),
+            liquidity: Liquidity::OrderBook {
+                depth: MarketDepth {
+                    bids,
+                    asks,
+                    spread: Decimal::ZERO,
+                    total_bid_size: Decimal::ZERO,
+                    total_ask_size: Decimal::ZERO,
+                    stamp: stamp(slot, time),
+                },
+                fee_bps: Decimal::ZERO,
+            },
+            stamp: stamp(slot, time),
+        }
+    }
+
+    #[test]
+    fn replays_scripted_strategy_in_slot_order() {
+        let t0 = Utc::now();
+        let events = vec![
+            // 12 价位排在前面的 3 个先成交，剩余 2 个成交给挂单
+            MarketEvent::Trades {
+                market: MARKET.to_string(),
+                trades: vec![Trade {
+                    price: Decimal::from(12),
+                    size: Decimal::from(5),
+                    taker_side: TradeSide::Buy,
+                    time: t0 + Duration::seconds(3),
+                }],
+                stamp: stamp(3, t0 + Duration::seconds(3)),
+            },
+            book(vec![level(9, 5)], vec![level(10, 5), level(12, 3)], 1, t0),
+            book(
+                vec![level(7, 5)],
+                vec![level(8, 5), level(12, 3)],
+                2,
+                t0 + Duration::seconds(2),
+            ),
+        ];
+        let config = BacktestConfig {
+            paper: PaperConfig {
+                latency: Duration::zero(),
+                balances: HashMap::from([
+                    ("SOL".to_string(), Decimal::from(10)),
+                    ("USDC".to_string(), Decimal::from(1_000)),
+                ]),
+                queue_model: true,
+                ..Default::default()
+            },
+            timer: Duration::seconds(1),
+        };
+
+        let (report, strategy) = Backtester::new(Scripted::default(), config).run(events);
+
+        // 回放时间 t0 -> t0+3s，定时器在 1s、2s、3s 各触发一次
+        assert_eq!(strategy.timers, 3);
+        assert_eq!(strategy.fills, 2);
+        assert_eq!(report.events, 3);
+
+        let trades: Vec<_> = report
+            .trades
+            .iter()
+            .map(|f| (f.side, f.price, f.size))
+            .collect();
+        assert_eq!(
+            trades,
+            vec![
+                (TradeSide::Buy, Decimal::from(10), Decimal::TWO),
+                (TradeSide::Sell, Decimal::from(12), Decimal::TWO),
+            ]
+        );
+
+        // 买入后中间价 9.5 和 7.5，卖出后持仓归零、已实现 4
+        let curve: Vec<_> = report
+            .equity_curve
+            .iter()
+            .map(|p| (p.slot, p.equity))
+            .collect();
+        assert_eq!(
+            curve,
+            vec![
+                (1, Decimal::from(-1)),
+                (2, Decimal::from(-5)),
+                (3, Decimal::from(4)),
+            ]
+        );
+        assert_eq!(report.max_drawdown, Decimal::from(4));
+
+        assert_eq!(report.closing_trades, 1);
+        assert_eq!(report.winning_trades, 1);
+        assert_eq!(report.hit_rate, Some(Decimal::ONE));
+    }
+}
@@ -0,0 +1,248 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::{cmp::Reverse, collections::HashMap};
+
+use crate::dex_collect::fixed::{Decimal, BPS};
+use crate::dex_collect::serum::serum_slippage::TradeSide;
+use crate::dex_collect::stamp::SnapshotStamp;
+use crate::monitor::consolidated::Venue;
+use crate::strategy::quote::{LegQuote, Liquidity};
+
+const LAMPORTS_PER_SOL: Decimal = Decimal::from_parts(1_000_000_000, 0, 0, false, 0);
+const MICRO_LAMPORTS_PER_LAMPORT: Decimal = Decimal::from_parts(1_000_000, 0, 0, false, 0);
+/// 链上执行成本
+#[derive(Debug, Clone)]
+pub struct ExecutionCost {
+    pub signatures: u32,
+    pub lamports_per_signature: u64,
+    pub compute_units: u32,
+    pub priority_fee_micro_lamports: u64, // 每 CU 的优先费
+    pub sol_price: Decimal, // SOL 的 quote 计价，需按最新价格设置，为 0 时不计网络成本
+}
+
+impl ExecutionCost {
+    /// 交易费和优先费，quote 计价
+    pub fn in_quote(&self) -> Decimal {
+        let base_fee = Decimal::from(self.signatures) * Decimal::from(self.lamports_per_signature);
+        let priority = Decimal::from(self.compute_units)
+            * Decimal::from(self.priority_fee_micro_lamports)
+            / MICRO_LAMPORTS_PER_LAMPORT;
+        (base_fee + priority) / LAMPORTS_PER_SOL * self.sol_price
+    }
+}
+
+impl Default for ExecutionCost {
+    fn default() -> Self {
+        Self {
+            signatures: 1,
+            lamports_per_signature: 5_000,
+            compute_units: 400_000,
+            priority_fee_micro_lamports: 10_000,
+            sol_price: Decimal::ZERO,
+        }
+    }
+}
+
+/// 套利中的一条腿
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArbLeg {
+    pub venue: Venue,
+    pub quote: LegQuote,
+    pub slot: u64, // 报价所基于的快照 slot
+}
+
+/// 同一交易对跨场所的套利机会
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArbOpportunity {
+    pub pair: String,
+    pub size: Decimal, // base 数量
+    pub buy: ArbLeg,
+    pub sell: ArbLeg,
+    pub gross_profit: Decimal, // 扣除 DEX 手续费后的价差收益
+    pub dex_fees: Decimal,
+    pub network_cost: Decimal, // 交易费和优先费
+    pub expected_profit: Decimal,
+    pub profit_bps: Decimal, // 相对买入花费
+    pub detected_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+struct VenueState {
+    liquidity: Liquidity,
+    stamp: SnapshotStamp,
+}
+
+/// 跨池套利检测配置
+#[derive(Debug, Clone)]
+pub struct CrossPoolConfig {
+    pub candidate_sizes: Vec<Decimal>, // 试算的 base 数量
+    pub min_profit: Decimal,           // quote 计价的最低净利润
+    pub max_age: Duration,             // 报价超过该时长不参与计算
+    pub cost: ExecutionCost,
+}
+
+impl Default for CrossPoolConfig {
+    fn default() -> Self {
+        Self {
+            candidate_sizes: [1, 5, 10, 50, 100].into_iter().map(Decimal::from).collect(),
+            min_profit: Decimal::ZERO,
+            max_age: Duration::seconds(5),
+            cost: ExecutionCost::default(),
+        }
+    }
+}
+
+/// 跨池套利检测器
+///
+/// 保存同一交易对在各场所的最新流动性，对每个买入/卖出场所组合按候选数量
+/// 试算真实报价，扣除 DEX 手续费、交易费和优先费后输出净利润为正的机会。
+#[derive(Debug, Clone)]
+pub struct CrossPoolDetector {
+    pair: String,
+    venues: HashMap<Venue, VenueState>,
+    config: CrossPoolConfig,
+}
+
+impl CrossPoolDetector {
+    pub fn new(pair: &str, config: CrossPoolConfig) -> Self {
+        Self {
+            pair: pair.to_string(),
+            venues: HashMap::new(),
+            config,
+        }
+    }
+
+    pub fn pair(&self) -> &str {
+        &self.pair
+    }
+
+    pub fn config_mut(&mut self) -> &mut CrossPoolConfig {
+        &mut self.config
+    }
+
+    /// 更新某个场所的流动性
+    pub fn update(&mut self, venue: Venue, liquidity: Liquidity, stamp: SnapshotStamp) {
+        self.venues.insert(venue, VenueState { liquidity, stamp });
+    }
+
+    pub fn remove(&mut self, venue: &Venue) {
+        self.venues.remove(venue);
+    }
+
+    /// 检测当前所有场所组合的套利机会，按净利润从高到低排列
+    ///
+    /// 每个组合只保留净利润最高的候选数量；`now` 用于过滤过期报价并记为检测时间。
+    pub fn detect(&self, now: DateTime<Utc>) -> Vec<ArbOpportunity> {
+        let network_cost = self.config.cost.in_quote();
+        let fresh: Vec<(&Venue, &VenueState)> = self
+            .venues
+            .iter()
+            .filter(|(_, state)| now - state.stamp.received_at <= self.config.max_age)
+            .collect();
+
+        let mut opportunities = Vec::new();
+        for (buy_venue, buy_state) in &fresh {
+            for (sell_venue, sell_state) in &fresh {
+                if buy_venue == sell_venue {
+                    continue;
+                }
+                let best = self
+                    .config
+                    .candidate_sizes
+                    .iter()
+                    .filter_map(|&size| {
+                        let buy = buy_state.liquidity.quote_base(TradeSide::Buy, size)?;
+                        let sell = sell_state.liquidity.quote_base(TradeSide::Sell, size)?;
+                        let gross_profit = sell.quote - buy.quote;
+                        let expected_profit = gross_profit - network_cost;
+                        Some(ArbOpportunity {
+                            pair: self.pair.clone(),
+                            size,
+                            dex_fees: buy.fee + sell.fee,
+                            profit_bps: expected_profit
+                                .checked_div(buy.quote)
+                                .map(|r| r * BPS)
+                                .unwrap_or_default(),
+                            buy: ArbLeg {
+                                venue: (*buy_venue).clone(),
+                                quote: buy,
+                                slot: buy_state.stamp.slot,
+                            },
+                            sell: ArbLeg {
+                                venue: (*sell_venue).clone(),
+                                quote: sell,
+                                slot: sell_state.stamp.slot,
+                            },
+                            gross_profit,
+                            network_cost,
+                            expected_profit,
+                            detected_at: now,
+                        })
+                    })
+                    .filter(|opportunity| opportunity.expected_profit > self.config.min_profit)
+                    .max_by_key(|opportunity| opportunity.expected_profit);
+                opportunities.extend(best);
+            }
+        }
+        opportunities.sort_by_key(|opportunity| Reverse(opportunity.expected_profit));
+        opportunities
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(quote_reserve: i64) -> Liquidity {
+        Liquidity::ConstantProduct {
+            base_reserve: Decimal::from(1_000),
+            quote_reserve: Decimal::from(quote_reserve),
+            fee_bps: Decimal::from(30),
+        }
+    }
+
+    #[test]
+    fn picks_most_profitable_size_after_network_cost() {
+        let now = Utc::now();
+        let mut detector = CrossPoolDetector::new(
+            "SOL/USDC",
+            CrossPoolConfig {
+                candidate_sizes: [1, 5, 10, 50].into_iter().map(Decimal::from).collect(),
+                cost: ExecutionCost {
+                    sol_price: Decimal::from(100),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+        detector.update(Venue::Raydium, pool(100_000), SnapshotStamp::new(1, "test"));
+        detector.update(Venue::Orca, pool(102_000), SnapshotStamp::new(2, "test"));
+
+        // 只有 Raydium 买、Orca 卖有利可图，5 个时净利润最高
+        let opportunities = detector.detect(now);
+        assert_eq!(opportunities.len(), 1);
+        let best = &opportunities[0];
+        assert_eq!(best.buy.venue, Venue::Raydium);
+        assert_eq!(best.sell.venue, Venue::Orca);
+        assert_eq!((best.buy.slot, best.sell.slot), (1, 2));
+        assert_eq!(best.size, Decimal::from(5));
+        assert_eq!(best.detected_at, now);
+
+        // 5000 lamports 签名费 + 400k CU * 10000 micro-lamports = 9000 lamports
+        assert_eq!(best.network_cost, "0.0009".parse::<Decimal>().unwrap());
+        assert_eq!(
+            best.gross_profit,
+            best.sell.quote.quote - best.buy.quote.quote
+        );
+        assert_eq!(best.expected_profit, best.gross_profit - best.network_cost);
+        assert_eq!(best.dex_fees, best.buy.quote.fee + best.sell.quote.fee);
+        for size in [1, 10, 50].map(Decimal::from) {
+            let buy = pool(100_000).quote_base(TradeSide::Buy, size).unwrap();
+            let sell = pool(102_000).quote_base(TradeSide::Sell, size).unwrap();
+            assert!(sell.quote - buy.quote < best.gross_profit);
+        }
+
+        // 报价过期后不再参与检测
+        assert!(detector.detect(now + Duration::seconds(6)).is_empty());
+    }
+}
@@ -0,0 +1,348 @@
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+};
+
+use crate::dex_collect::fixed::{Decimal, BPS};
+use crate::dex_collect::serum::serum_slippage::TradeSide;
+use crate::strategy::quote::Liquidity;
+
+/// 图中的一条有向边: 在 `market` 上把 `from` 换成 `to`
+#[derive(Debug, Clone)]
+struct Edge {
+    market: usize,
+    from: usize,
+    to: usize,
+    side: TradeSide, // from 为 base 时卖出，from 为 quote 时买入
+    weight: f64,     // -ln(扣费后的边际汇率)
+}
+
+#[derive(Debug, Clone)]
+struct Market {
+    id: String,
+    liquidity: Liquidity,
+}
+
+/// 环路中的一步兑换
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CycleLeg {
+    pub market: String,
+    pub from: String,
+    pub to: String,
+    pub side: TradeSide,
+    pub amount_in: Decimal,
+    pub amount_out: Decimal,
+}
+
+/// 经过按数量报价确认的环路套利
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CycleOpportunity {
+    pub tokens: Vec<String>, // 首尾相同，例如 [USDC, SOL, RAY, USDC]
+    pub legs: Vec<CycleLeg>,
+    pub start_amount: Decimal,
+    pub end_amount: Decimal,
+    pub profit: Decimal, // 以起始代币计价，未扣除网络费用
+    pub profit_bps: Decimal,
+    pub marginal_rate: f64, // 按边际汇率估算的环路收益倍数
+}
+
+/// 环路搜索配置
+#[derive(Debug, Clone)]
+pub struct CycleConfig {
+    pub max_hops: usize,
+    pub min_profit_bps: Decimal,
+    pub start_amounts: HashMap<String, Vec<Decimal>>, // 起始代币 -> 试算数量
+}
+
+impl Default for CycleConfig {
+    fn default() -> Self {
+        Self {
+            max_hops: 4,
+            min_profit_bps: Decimal::ZERO,
+            start_amounts: HashMap::new(),
+        }
+    }
+}
+
+/// 代币兑换图
+///
+/// 代币为节点，每个市场或池子提供 base->quote 和 quote->base 两条边，
+/// 边权为扣除手续费后边际汇率的负对数，负权环即为潜在套利。
+#[derive(Debug, Clone, Default)]
+pub struct TokenGraph {
+    tokens: Vec<String>,
+    index: HashMap<String, usize>,
+    markets: Vec<Market>,
+    edges: Vec<Edge>,
+}
+
+impl TokenGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn token_index(&mut self, token: &str) -> usize {
+        if let Some(&i) = self.index.get(token) {
+            return i;
+        }
+        self.tokens.push(token.to_string());
+        self.index.insert(token.to_string(), self.tokens.len() - 1);
+        self.tokens.len() - 1
+    }
+
+    /// 添加或替换一个市场，`id` 相同的市场会被覆盖
+    pub fn upsert_market(&mut self, id: &str, base: &str, quote: &str, liquidity: Liquidity) {
+        let base = self.token_index(base);
+        let quote = self.token_index(quote);
+        let market = match self.markets.iter().position(|m| m.id == id) {
+            Some(i) => {
+                self.markets[i].liquidity = liquidity;
+                self.edges.retain(|e| e.market != i);
+                i
+            }
+            None => {
+                self.markets.push(Market {
+                    id: id.to_string(),
+                    liquidity,
+                });
+                self.markets.len() - 1
+            }
+        };
+
+        let (sell_rate, buy_rate) = marginal_rates(&self.markets[market].liquidity);
+        for (from, to, side, rate) in [
+            (base, quote, TradeSide::Sell, sell_rate),
+            (quote, base, TradeSide::Buy, buy_rate),
+        ] {
+            if let Some(rate) = rate.filter(|r| *r > 0.0) {
+                self.edges.push(Edge {
+                    market,
+                    from,
+                    to,
+                    side,
+                    weight: -rate.ln(),
+                });
+            }
+        }
+    }
+
+    /// 搜索并确认不超过 `max_hops` 的盈利环路，按收益率从高到低排列
+    pub fn find_cycles(&self, config: &CycleConfig) -> Vec<CycleOpportunity> {
+        let mut seen = HashSet::new();
+        let mut opportunities = Vec::new();
+        for (token, amounts) in &config.start_amounts {
+            let Some(&start) = self.index.get(token) else {
+                continue;
+            };
+            for cycle in self.negative_cycles(start, config.max_hops.max(2)) {
+                // 同一环路的不同旋转只确认一次
+                if !seen.insert((start, normalize(&cycle))) {
+                    continue;
+                }
+                let marginal_rate =
+                    (-cycle.iter().map(|&e| self.edges[e].weight).sum::<f64>()).exp();
+                let best = amounts
+                    .iter()
+                    .filter_map(|&amount| self.simulate(&cycle, amount, marginal_rate))
+                    .filter(|o| o.profit_bps > config.min_profit_bps)
+                    .max_by_key(|o| o.profit);
+                opportunities.extend(best);
+            }
+        }
+        opportunities.sort_by_key(|o| Reverse(o.profit_bps));
+        opportunities
+    }
+
+    /// 从 start 出发、步数受限的 Bellman-Ford，返回回到 start 的负权环 (边序号)
+    ///
+    /// 每一轮只保留到达各节点的最优路径，中间节点不重复。
+    fn negative_cycles(&self, start: usize, max_hops: usize) -> Vec<Vec<usize>> {
+        let n = self.tokens.len();
+        let mut layer: Vec<Option<(f64, Vec<usize>)>> = vec![None; n];
+        layer[start] = Some((0.0, Vec::new()));
+
+        let mut cycles = Vec::new();
+        for hop in 1..=max_hops {
+            let mut next: Vec<Option<(f64, Vec<usize>)>> = vec![None; n];
+            for (i, edge) in self.edges.iter().enumerate() {
+                let Some((dist, path)) = &layer[edge.from] else {
+                    continue;
+                };
+                let closes = edge.to == start;
+                if closes {
+                    // 至少两步，且不在同一市场原路返回
+                    if hop >= 2 && dist + edge.weight < -f64::EPSILON {
+                        let mut cycle = path.clone();
+                        cycle.push(i);
+                        if !is_round_trip(&self.edges, &cycle) {
+                            cycles.push(cycle);
+                        }
+                    }
+                    continue;
+                }
+                if path.iter().any(|&e| self.edges[e].from == edge.to) {
+                    continue;
+                }
+                let candidate = dist + edge.weight;
+                if next[edge.to].as_ref().is_none_or(|(d, _)| candidate < *d) {
+                    let mut path = path.clone();
+                    path.push(i);
+                    next[edge.to] = Some((candidate, path));
+                }
+            }
+            layer = next;
+        }
+        cycles
+    }
+
+    /// 按数量逐步报价确认环路，任一步流动性不足时返回 None
+    fn simulate(
+        &self,
+        cycle: &[usize],
+        start_amount: Decimal,
+        marginal_rate: f64,
+    ) -> Option<CycleOpportunity> {
+        let mut amount = start_amount;
+        let mut legs = Vec::with_capacity(cycle.len());
+        let mut tokens = vec![self.tokens[self.edges[*cycle.first()?].from].clone()];
+        for &e in cycle {
+            let edge = &self.edges[e];
+            let market = &self.markets[edge.market];
+            let quote = market.liquidity.quote_exact_in(edge.side, amount)?;
+            let amount_out = match edge.side {
+                TradeSide::Sell => quote.quote,
+                TradeSide::Buy => quote.base,
+            };
+            legs.push(CycleLeg {
+                market: market.id.clone(),
+                from: self.tokens[edge.from].clone(),
+                to: self.tokens[edge.to].clone(),
+                side: edge.side,
+                amount_in: amount,
+                amount_out,
+            });
+            tokens.push(self.tokens[edge.to].clone());
+            amount = amount_out;
+        }
+
+        let profit = amount - start_amount;
+        Some(CycleOpportunity {
+            tokens,
+            legs,
+            start_amount,
+            end_amount: amount,
+            profit,
+            profit_bps: profit.checked_div(start_amount)? * BPS,
+            marginal_rate,
+        })
+    }
+}
+
+/// 扣除手续费后的边际汇率: (base->quote, quote->base)
+fn marginal_rates(liquidity: &Liquidity) -> (Option<f64>, Option<f64>) {
+    let keep = (Decimal::ONE - liquidity.fee_bps() / BPS).to_f64();
+    match liquidity {
+        Liquidity::OrderBook { depth, .. } => {
+            let bid = depth.bids.first().and_then(|l| l.price.to_f64());
+            let ask = depth.asks.first().and_then(|l| l.price.to_f64());
+            (
+                bid.zip(keep).map(|(bid, keep)| bid * keep),
+                ask.zip(keep)
+                    .filter(|(ask, _)| *ask > 0.0)
+                    .map(|(ask, keep)| keep / ask),
+            )
+        }
+        Liquidity::ConstantProduct { .. } => {
+            let mid = liquidity
+                .mid()
+                .and_then(|m| m.to_f64())
+                .filter(|m| *m > 0.0);
+            (
+                mid.zip(keep).map(|(mid, keep)| mid * keep),
+                mid.zip(keep).map(|(mid, keep)| keep / mid),
+            )
+        }
+    }
+}
+
+/// 两步且走同一市场的来回交易不算环路
+fn is_round_trip(edges: &[Edge], cycle: &[usize]) -> bool {
+    cycle.len() == 2 && edges[cycle[0]].market == edges[cycle[1]].market
+}
+
+/// 把环路旋转到最小边序号开头，用于去重
+fn normalize(cycle: &[usize]) -> Vec<usize> {
+    let pivot = cycle
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, e)| **e)
+        .map(|(i, _)| i)
+        .unwrap_or(0);
+    cycle[pivot..]
+        .iter()
+        .chain(&cycle[..pivot])
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(base: i64, quote: i64) -> Liquidity {
+        Liquidity::ConstantProduct {
+            base_reserve: Decimal::from(base),
+            quote_reserve: Decimal::from(quote),
+            fee_bps: Decimal::from(25),
+        }
+    }
+
+    fn graph(ray_usdc_quote: i64) -> TokenGraph {
+        let mut graph = TokenGraph::new();
+        graph.upsert_market("SOL/USDC", "SOL", "USDC", pool(1_000, 100_000));
+        graph.upsert_market("RAY/SOL", "RAY", "SOL", pool(10_000, 100));
+        graph.upsert_market("RAY/USDC", "RAY", "USDC", pool(10_000, ray_usdc_quote));
+        graph
+    }
+
+    fn config() -> CycleConfig {
+        CycleConfig {
+            start_amounts: HashMap::from([(
+                "USDC".to_string(),
+                vec![Decimal::from(10), Decimal::from(100)],
+            )]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn finds_triangular_cycle_once() {
+        let cycles = graph(12_000).find_cycles(&config());
+        assert_eq!(cycles.len(), 1);
+        let cycle = &cycles[0];
+        assert_eq!(cycle.tokens, ["USDC", "SOL", "RAY", "USDC"]);
+        assert_eq!(
+            cycle.legs.iter().map(|l| l.side).collect::<Vec<_>>(),
+            [TradeSide::Buy, TradeSide::Buy, TradeSide::Sell]
+        );
+        // 按数量确认后取利润最大的试算数量
+        assert_eq!(cycle.start_amount, Decimal::from(100));
+        assert!(cycle.profit > Decimal::ZERO);
+        assert!((cycle.marginal_rate - 1.2 * 0.9975f64.powi(3)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn balanced_graph_has_no_cycles() {
+        assert!(graph(10_000).find_cycles(&config()).is_empty());
+    }
+
+    #[test]
+    fn upsert_replaces_market_edges() {
+        let mut graph = graph(12_000);
+        graph.upsert_market("RAY/USDC", "RAY", "USDC", pool(10_000, 10_000));
+        assert_eq!(graph.edges.len(), 6);
+        assert!(graph.find_cycles(&config()).is_empty());
+    }
+}
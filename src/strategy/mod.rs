@@ -0,0 +1,6 @@
+pub mod backtest;
+pub mod cross_pool;
+pub mod cycle;
+pub mod quote;
+pub mod runtime;
+pub mod sizing;
@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+
+use crate::dex_collect::fixed::{Decimal, BPS};
+use crate::dex_collect::serum::serum_depth::MarketDepth;
+use crate::dex_collect::serum::serum_slippage::{FillAmount, TradeSide};
+
+/// 可报价的流动性
+#[derive(Debug, Clone)]
+pub enum Liquidity {
+    /// 订单簿市场 (Serum/OpenBook)
+    OrderBook {
+        depth: MarketDepth,
+        fee_bps: Decimal,
+    },
+    /// 恒定乘积 AMM (Raydium/Orca)，储备为 UI 数量
+    ConstantProduct {
+        base_reserve: Decimal,
+        quote_reserve: Decimal,
+        fee_bps: Decimal,
+    },
+}
+
+/// 单笔成交报价，数量均为 UI 单位
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LegQuote {
+    pub side: TradeSide,
+    pub base: Decimal,  // 买入或卖出的 base
+    pub quote: Decimal, // 买入为含手续费的花费，卖出为扣除手续费的到手金额
+    pub fee: Decimal,   // DEX 手续费，quote 计价
+}
+
+impl LegQuote {
+    /// 含手续费的成交均价
+    pub fn avg_price(&self) -> Option<Decimal> {
+        (!self.base.is_zero()).then(|| self.quote / self.base)
+    }
+}
+
+impl Liquidity {
+    pub fn fee_bps(&self) -> Decimal {
+        match self {
+            Liquidity::OrderBook { fee_bps, .. } | Liquidity::ConstantProduct { fee_bps, .. } => {
+                *fee_bps
+            }
+        }
+    }
+
+    /// 当前中间价 (订单簿) 或边际价格 (AMM)
+    pub fn mid(&self) -> Option<Decimal> {
+        match self {
+            Liquidity::OrderBook { depth, .. } => {
+                let bid = depth.bids.first()?.price;
+                let ask = depth.asks.first()?.price;
+                Some((bid + ask) / Decimal::TWO)
+            }
+            Liquidity::ConstantProduct {
+                base_reserve,
+                quote_reserve,
+                ..
+            } => quote_reserve.checked_div(*base_reserve),
+        }
+    }
+
+    /// 买入或卖出指定 base 数量的报价，流动性不足时返回 None
+    pub fn quote_base(&self, side: TradeSide, base: Decimal) -> Option<LegQuote> {
+        if base <= Decimal::ZERO {
+            return None;
+        }
+        match self {
+            Liquidity::OrderBook { depth, fee_bps } => {
+                let fill = depth.estimate_fill(side, FillAmount::Base(base), *fee_bps);
+                fill.is_complete().then_some(LegQuote {
+                    side,
+                    base: fill.filled_base,
+                    quote: fill.net_quote,
+                    fee: fill.fee,
+                })
+            }
+            Liquidity::ConstantProduct {
+                base_reserve: x,
+                quote_reserve: y,
+                fee_bps,
+            } => {
+                let fee_rate = *fee_bps / BPS;
+                match side {
+                    // 精确输出 base，手续费从输入的 quote 中扣
+                    TradeSide::Buy => {
+                        if base >= *x || fee_rate >= Decimal::ONE {
+                            return None;
+                        }
+                        let net_in = y.checked_mul(base)?.checked_div(*x - base)?;
+                        let gross_in = net_in.checked_div(Decimal::ONE - fee_rate)?;
+                        Some(LegQuote {
+                            side,
+                            base,
+                            quote: gross_in,
+                            fee: gross_in - net_in,
+                        })
+                    }
+                    TradeSide::Sell => {
+                        let out = cp_out(*x, *y, base, fee_rate)?;
+                        let no_fee = cp_out(*x, *y, base, Decimal::ZERO)?;
+                        Some(LegQuote {
+                            side,
+                            base,
+                            quote: out,
+                            fee: no_fee - out,
+                        })
+                    }
+                }
+            }
+        }
+    }
+
+    /// 精确输入报价: 买入时输入 quote (含手续费) 换 base，卖出时输入 base 换 quote
+    pub fn quote_exact_in(&self, side: TradeSide, amount_in: Decimal) -> Option<LegQuote> {
+        if amount_in <= Decimal::ZERO {
+            return None;
+        }
+        match (self, side) {
+            (_, TradeSide::Sell) => self.quote_base(side, amount_in),
+            (Liquidity::OrderBook { depth, fee_bps }, TradeSide::Buy) => {
+                let fill = depth.estimate_fill(side, FillAmount::Quote(amount_in), *fee_bps);
+                (fill.is_complete() && !fill.filled_base.is_zero()).then_some(LegQuote {
+                    side,
+                    base: fill.filled_base,
+                    quote: fill.net_quote,
+                    fee: fill.fee,
+                })
+            }
+            (
+                Liquidity::ConstantProduct {
+                    base_reserve: x,
+                    quote_reserve: y,
+                    fee_bps,
+                },
+                TradeSide::Buy,
+            ) => {
+                let fee_rate = *fee_bps / BPS;
+                let base = cp_out(*y, *x, amount_in, fee_rate)?;
+                Some(LegQuote {
+                    side,
+                    base,
+                    quote: amount_in,
+                    fee: amount_in * fee_rate,
+                })
+            }
+        }
+    }
+}
+
+/// 恒定乘积池输入 `amount_in` 得到的输出，手续费从输入中扣除
+pub fn cp_out(
+    reserve_in: Decimal,
+    reserve_out: Decimal,
+    amount_in: Decimal,
+    fee_rate: Decimal,
+) -> Option<Decimal> {
+    if reserve_in <= Decimal::ZERO || reserve_out <= Decimal::ZERO || amount_in <= Decimal::ZERO {
+        return None;
+    }
+    let effective = amount_in.checked_mul(Decimal::ONE - fee_rate)?;
+    reserve_out
+        .checked_mul(effective)?
+        .checked_div(reserve_in.checked_add(effective)?)
+}
@@ -0,0 +1,574 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+
+use crate::dex_collect::fixed::{self, Decimal};
+use crate::dex_collect::serum::serum_depth::{Level, MarketDepth};
+use crate::dex_collect::serum::serum_slippage::TradeSide;
+use crate::dex_collect::stamp::SnapshotStamp;
+use crate::dex_collect::stream::update::{BookSide, MarketUpdate};
+use crate::strategy::quote::Liquidity;
+
+/// 市场上的一笔公开成交
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Trade {
+    pub price: Decimal,
+    pub size: Decimal,
+    pub taker_side: TradeSide,
+    pub time: DateTime<Utc>,
+}
+
+/// 归一化的市场事件，市场以名称标识 (例如 "SOL/USDC")
+#[derive(Debug, Clone)]
+pub enum MarketEvent {
+    /// 订单簿深度或 AMM 储备变化
+    Book {
+        market: String,
+        liquidity: Liquidity,
+        stamp: SnapshotStamp,
+    },
+    /// 新的公开成交
+    Trades {
+        market: String,
+        trades: Vec<Trade>,
+        stamp: SnapshotStamp,
+    },
+}
+
+impl MarketEvent {
+    pub fn stamp(&self) -> &SnapshotStamp {
+        match self {
+            MarketEvent::Book { stamp, .. } | MarketEvent::Trades { stamp, .. } => stamp,
+        }
+    }
+}
+
+/// 订单类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderType {
+    Limit,
+    ImmediateOrCancel,
+    PostOnly,
+}
+
+/// 策略发出的下单请求，AMM 市场上 price 为可接受的最差成交价
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderRequest {
+    pub client_id: u64,
+    pub market: String,
+    pub side: TradeSide,
+    pub price: Decimal,
+    pub size: Decimal, // base 数量
+    pub order_type: OrderType,
+}
+
+/// 发送给执行器的指令
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum OrderCommand {
+    Place(OrderRequest),
+    Cancel { market: String, client_id: u64 },
+    CancelAll { market: String },
+}
+
+/// 订单成交
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fill {
+    pub client_id: u64,
+    pub market: String,
+    pub side: TradeSide,
+    pub price: Decimal,
+    pub size: Decimal,
+    pub fee: Decimal,       // quote 计价
+    pub remaining: Decimal, // 订单剩余未成交数量
+    pub time: DateTime<Utc>,
+}
+
+/// 执行器回报
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ExecutionReport {
+    Accepted { client_id: u64, market: String },
+    Rejected { client_id: u64, reason: String },
+    Filled(Fill),
+    Cancelled { client_id: u64, market: String },
+}
+
+/// 运行时持有的执行器一端
+///
+/// 纸面执行器和实盘执行器都通过同一对通道接收指令、返回回报，
+/// 运行时不关心订单最终发往哪里。
+pub struct ExecutorHandle {
+    pub commands: mpsc::Sender<OrderCommand>,
+    pub reports: mpsc::Receiver<ExecutionReport>,
+}
+
+/// 执行器任务持有的一端
+pub struct ExecutorEndpoint {
+    pub commands: mpsc::Receiver<OrderCommand>,
+    pub reports: mpsc::Sender<ExecutionReport>,
+}
+
+/// 创建一对执行器通道
+pub fn executor_channel(buffer: usize) -> (ExecutorHandle, ExecutorEndpoint) {
+    let (command_tx, command_rx) = mpsc::channel(buffer);
+    let (report_tx, report_rx) = mpsc::channel(buffer);
+    (
+        ExecutorHandle {
+            commands: command_tx,
+            reports: report_rx,
+        },
+        ExecutorEndpoint {
+            commands: command_rx,
+            reports: report_tx,
+        },
+    )
+}
+
+/// 策略回调中可用的上下文: 最新行情、在途订单和下单接口
+#[derive(Debug, Clone, Default)]
+pub struct StrategyContext {
+    now: DateTime<Utc>,
+    liquidity: HashMap<String, Liquidity>,
+    open_orders: HashMap<u64, OrderRequest>,
+    pending: Vec<OrderCommand>,
+    next_client_id: u64,
+}
+
+impl StrategyContext {
+    pub fn now(&self) -> DateTime<Utc> {
+        self.now
+    }
+
+    /// 市场的最新流动性
+    pub fn liquidity(&self, market: &str) -> Option<&Liquidity> {
+        self.liquidity.get(market)
+    }
+
+    /// 已发出且尚未完结的订单
+    pub fn open_orders(&self) -> impl Iterator<Item = &OrderRequest> {
+        self.open_orders.values()
+    }
+
+    /// 下单，返回分配的 client_id，回调结束后统一发往执行器
+    pub fn place_order(
+        &mut self,
+        market: &str,
+        side: TradeSide,
+        price: Decimal,
+        size: Decimal,
+        order_type: OrderType,
+    ) -> u64 {
+        self.next_client_id += 1;
+        let request = OrderRequest {
+            client_id: self.next_client_id,
+            market: market.to_string(),
+            side,
+            price,
+            size,
+            order_type,
+        };
+        self.open_orders.insert(request.client_id, request.clone());
+        self.pending.push(OrderCommand::Place(request));
+        self.next_client_id
+    }
+
+    pub fn cancel_order(&mut self, market: &str, client_id: u64) {
+        self.pending.push(OrderCommand::Cancel {
+            market: market.to_string(),
+            client_id,
+        });
+    }
+
+    pub fn cancel_all(&mut self, market: &str) {
+        self.pending.push(OrderCommand::CancelAll {
+            market: market.to_string(),
+        });
+    }
+
+    /// 根据执行器回报更新在途订单
+    fn apply_report(&mut self, report: &ExecutionReport) {
+        match report {
+            ExecutionReport::Rejected { client_id, .. }
+            | ExecutionReport::Cancelled { client_id, .. } => {
+                self.open_orders.remove(client_id);
+            }
+            ExecutionReport::Filled(fill) if fill.remaining <= Decimal::ZERO => {
+                self.open_orders.remove(&fill.client_id);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 策略插件
+///
+/// 所有回调都有空的默认实现，策略只需实现关心的部分；
+/// 回调中通过 [`StrategyContext`] 下单或撤单。
+pub trait Strategy: Send {
+    fn name(&self) -> &str;
+
+    fn on_start(&mut self, _ctx: &mut StrategyContext) {}
+
+    /// 订单簿或 AMM 池更新，上下文中的流动性已是最新
+    fn on_book_update(
+        &mut self,
+        _ctx: &mut StrategyContext,
+        _market: &str,
+        _liquidity: &Liquidity,
+    ) {
+    }
+
+    fn on_trade(&mut self, _ctx: &mut StrategyContext, _market: &str, _trades: &[Trade]) {}
+
+    /// 按运行时配置的间隔定时触发
+    fn on_timer(&mut self, _ctx: &mut StrategyContext) {}
+
+    fn on_fill(&mut self, _ctx: &mut StrategyContext, _fill: &Fill) {}
+
+    /// 下单被接受、拒绝或撤单完成
+    fn on_order_update(&mut self, _ctx: &mut StrategyContext, _report: &ExecutionReport) {}
+}
+
+/// 同步驱动策略回调
+///
+/// 实盘运行时和回测共用，负责维护上下文并收集回调中发出的指令。
+pub struct StrategyHost<S: Strategy> {
+    strategy: S,
+    ctx: StrategyContext,
+}
+
+impl<S: Strategy> StrategyHost<S> {
+    pub fn new(strategy: S) -> Self {
+        Self {
+            strategy,
+            ctx: StrategyContext::default(),
+        }
+    }
+
+    pub fn strategy(&self) -> &S {
+        &self.strategy
+    }
+
+    pub fn context(&self) -> &StrategyContext {
+        &self.ctx
+    }
+
+    pub fn into_strategy(self) -> S {
+        self.strategy
+    }
+
+    pub fn start(&mut self, now: DateTime<Utc>) {
+        self.ctx.now = now;
+        self.strategy.on_start(&mut self.ctx);
+    }
+
+    pub fn timer(&mut self, now: DateTime<Utc>) {
+        self.ctx.now = now;
+        self.strategy.on_timer(&mut self.ctx);
+    }
+
+    /// 分发一条市场事件
+    pub fn handle_event(&mut self, event: &MarketEvent) {
+        let stamp = event.stamp();
+        self.ctx.now = stamp.block_time.unwrap_or(stamp.received_at);
+        match event {
+            MarketEvent::Book {
+                market, liquidity, ..
+            } => {
+                self.ctx.liquidity.insert(market.clone(), liquidity.clone());
+                self.strategy
+                    .on_book_update(&mut self.ctx, market, liquidity);
+            }
+            MarketEvent::Trades { market, trades, .. } => {
+                self.strategy.on_trade(&mut self.ctx, market, trades);
+            }
+        }
+    }
+
+    /// 分发一条执行器回报
+    pub fn handle_report(&mut self, report: &ExecutionReport) {
+        self.ctx.apply_report(report);
+        match report {
+            ExecutionReport::Filled(fill) => {
+                self.ctx.now = fill.time;
+                self.strategy.on_fill(&mut self.ctx, fill);
+            }
+            _ => self.strategy.on_order_update(&mut self.ctx, report),
+        }
+    }
+
+    /// 取出回调中积累的指令
+    pub fn take_commands(&mut self) -> Vec<OrderCommand> {
+        std::mem::take(&mut self.ctx.pending)
+    }
+}
+
+/// 策略运行时
+///
+/// 把市场事件、执行器回报和定时器分发给策略回调，并把策略发出的指令转给执行器。
+pub struct StrategyRuntime<S: Strategy> {
+    host: StrategyHost<S>,
+    executor: ExecutorHandle,
+    timer: std::time::Duration,
+}
+
+impl<S: Strategy> StrategyRuntime<S> {
+    /// 定时间隔必须大于 0
+    pub fn new(strategy: S, executor: ExecutorHandle, timer: std::time::Duration) -> Result<Self> {
+        if timer.is_zero() {
+            return Err(anyhow::anyhow!("Strategy timer interval must be positive"));
+        }
+        Ok(Self {
+            host: StrategyHost::new(strategy),
+            executor,
+            timer,
+        })
+    }
+
+    /// 运行到事件流结束，返回策略以便读取其状态
+    ///
+    /// 执行器退出 (回报通道关闭) 时返回错误，避免策略在没有成交回报的情况下继续下单。
+    pub async fn run(mut self, mut events: impl Stream<Item = MarketEvent> + Unpin) -> Result<S> {
+        log::info!("策略 {} 启动", self.host.strategy().name());
+        self.host.start(Utc::now());
+        self.flush().await?;
+
+        let mut timer = tokio::time::interval(self.timer);
+        timer.tick().await;
+        loop {
+            tokio::select! {
+                event = events.next() => {
+                    let Some(event) = event else { break };
+                    self.host.handle_event(&event);
+                }
+                report = self.executor.reports.recv() => {
+                    let Some(report) = report else {
+                        return Err(anyhow::anyhow!("Executor report channel closed"));
+                    };
+                    self.host.handle_report(&report);
+                }
+                _ = timer.tick() => {
+                    self.host.timer(Utc::now());
+                }
+            }
+            self.flush().await?;
+        }
+
+        log::info!("策略 {} 停止", self.host.strategy().name());
+        Ok(self.host.into_strategy())
+    }
+
+    /// 把回调中积累的指令发往执行器
+    async fn flush(&mut self) -> Result<()> {
+        for command in self.host.take_commands() {
+            self.executor
+                .commands
+                .send(command)
+                .await
+                .map_err(|_| anyhow::anyhow!("Executor stopped"))?;
+        }
+        Ok(())
+    }
+}
+
+/// 复制一份事件流，副本通过通道发给纸面执行器等需要同样行情的组件
+pub fn tee_events(
+    events: impl Stream<Item = MarketEvent>,
+    buffer: usize,
+) -> (impl Stream<Item = MarketEvent>, mpsc::Receiver<MarketEvent>) {
+    let (tx, rx) = mpsc::channel(buffer);
+    let events = events.then(move |event| {
+        let tx = tx.clone();
+        async move {
+            let _ = tx.send(event.clone()).await;
+            event
+        }
+    });
+    (events, rx)
+}
+
+#[derive(Debug, Clone)]
+struct BookInfo {
+    name: String,
+    base_decimals: u32,
+    quote_decimals: u32,
+    fee_bps: Decimal,
+    bids: Vec<Level>,
+    asks: Vec<Level>,
+}
+
+#[derive(Debug, Clone)]
+struct PoolInfo {
+    name: String,
+    base_decimals: u32,
+    quote_decimals: u32,
+    fee_bps: Decimal,
+}
+
+/// 把采集层的 [`MarketUpdate`] 转成策略使用的 [`MarketEvent`]
+///
+/// 合并订单簿两侧、换算 AMM 储备精度、按市场名称标识，只保留 maker 侧成交避免重复。
+#[derive(Debug, Clone, Default)]
+pub struct EventNormalizer {
+    books: HashMap<Pubkey, BookInfo>,
+    pools: HashMap<Pubkey, PoolInfo>,
+}
+
+impl EventNormalizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册订单簿市场
+    pub fn add_book(&mut self, market: Pubkey, name: &str, decimals: (u32, u32), fee_bps: Decimal) {
+        self.books.insert(
+            market,
+            BookInfo {
+                name: name.to_string(),
+                base_decimals: decimals.0,
+                quote_decimals: decimals.1,
+                fee_bps,
+                bids: Vec::new(),
+                asks: Vec::new(),
+            },
+        );
+    }
+
+    /// 注册 AMM 池
+    pub fn add_pool(&mut self, pool: Pubkey, name: &str, decimals: (u32, u32), fee_bps: Decimal) {
+        self.pools.insert(
+            pool,
+            PoolInfo {
+                name: name.to_string(),
+                base_decimals: decimals.0,
+                quote_decimals: decimals.1,
+                fee_bps,
+            },
+        );
+    }
+
+    /// 转换一条更新，未注册的市场或无关的更新返回 None
+    pub fn normalize(&mut self, update: MarketUpdate) -> Option<MarketEvent> {
+        match update {
+            MarketUpdate::Book {
+                market,
+                side,
+                levels,
+                stamp,
+            } => {
+                let book = self.books.get_mut(&market)?;
+                match side {
+                    BookSide::Bids => book.bids = levels,
+                    BookSide::Asks => book.asks = levels,
+                }
+                let spread = match (book.bids.first(), book.asks.first()) {
+                    (Some(bid), Some(ask)) => ask.price - bid.price,
+                    _ => Decimal::ZERO,
+                };
+                let depth = MarketDepth {
+                    bids: book.bids.clone(),
+                    asks: book.asks.clone(),
+                    spread,
+                    total_bid_size: book.bids.last().map(|l| l.total).unwrap_or_default(),
+                    total_ask_size: book.asks.last().map(|l| l.total).unwrap_or_default(),
+                    stamp: stamp.clone(),
+                };
+                Some(MarketEvent::Book {
+                    market: book.name.clone(),
+                    liquidity: Liquidity::OrderBook {
+                        depth,
+                        fee_bps: book.fee_bps,
+                    },
+                    stamp,
+                })
+            }
+            MarketUpdate::Fills {
+                market,
+                fills,
+                stamp,
+            } => {
+                let book = self.books.get(&market)?;
+                let time = stamp.block_time.unwrap_or(stamp.received_at);
+                let trades: Vec<Trade> = fills
+                    .iter()
+                    .filter(|f| f.maker)
+                    .filter_map(|f| {
+                        Some(Trade {
+                            price: f.price(book.base_decimals, book.quote_decimals)?,
+                            size: f.size(book.base_decimals)?,
+                            // maker 在买盘则 taker 卖出
+                            taker_side: match f.side {
+                                BookSide::Bids => TradeSide::Sell,
+                                BookSide::Asks => TradeSide::Buy,
+                            },
+                            time,
+                        })
+                    })
+                    .collect();
+                (!trades.is_empty()).then(|| MarketEvent::Trades {
+                    market: book.name.clone(),
+                    trades,
+                    stamp,
+                })
+            }
+            MarketUpdate::Pool {
+                pool,
+                base_reserve,
+                quote_reserve,
+                stamp,
+            } => {
+                let info = self.pools.get(&pool)?;
+                Some(MarketEvent::Book {
+                    market: info.name.clone(),
+                    liquidity: Liquidity::ConstantProduct {
+                        base_reserve: fixed::from_raw(base_reserve, info.base_decimals)?,
+                        quote_reserve: fixed::from_raw(quote_reserve, info.quote_decimals)?,
+                        fee_bps: info.fee_bps,
+                    },
+                    stamp,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// 把采集层的更新流转换成市场事件流
+    pub fn normalize_stream(
+        mut self,
+        updates: impl Stream<Item = MarketUpdate>,
+    ) -> impl Stream<Item = MarketEvent> {
+        updates.filter_map(move |update| futures::future::ready(self.normalize(update)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    struct Idle;
+
+    impl Strategy for Idle {
+        fn name(&self) -> &str {
+            "idle"
+        }
+    }
+
+    #[test]
+    fn zero_timer_is_rejected() {
+        let (handle, _endpoint) = executor_channel(1);
+        assert!(StrategyRuntime::new(Idle, handle, Duration::ZERO).is_err());
+    }
+
+    #[tokio::test]
+    async fn stopped_executor_is_fatal() {
+        let (handle, endpoint) = executor_channel(1);
+        drop(endpoint);
+        let runtime = StrategyRuntime::new(Idle, handle, Duration::from_secs(60)).unwrap();
+        let result = runtime.run(futures::stream::pending::<MarketEvent>()).await;
+        assert!(result.is_err());
+    }
+}
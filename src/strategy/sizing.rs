@@ -0,0 +1,257 @@
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::MathematicalOps;
+use serde::{Deserialize, Serialize};
+
+use crate::dex_collect::fixed::{Decimal, BPS};
+use crate::dex_collect::serum::serum_slippage::TradeSide;
+use crate::strategy::quote::{LegQuote, Liquidity};
+
+/// 路径中的一步: 在 `liquidity` 上按 `side` 精确输入兑换
+///
+/// 买入时输入 quote 得到 base，卖出时输入 base 得到 quote，
+/// 上一步的输出即下一步的输入。
+#[derive(Debug, Clone)]
+pub struct RouteLeg<'a> {
+    pub liquidity: &'a Liquidity,
+    pub side: TradeSide,
+    pub max_in: Option<Decimal>, // 该步输入上限，None 时仅受流动性限制
+}
+
+/// 求解方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SolveMethod {
+    ClosedForm, // 全部为恒定乘积池
+    Numeric,    // 含订单簿等，黄金分割搜索
+}
+
+/// 最优交易规模
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OptimalSize {
+    pub amount_in: Decimal,
+    pub amount_out: Decimal,
+    pub profit: Decimal,                // amount_out - amount_in，首尾为同一代币
+    pub marginal_rate: Option<Decimal>, // 最优点处每单位追加输入的边际输出
+    pub legs: Vec<LegQuote>,
+    pub method: SolveMethod,
+    pub bounded: bool, // 最优点受资金或流动性上限约束
+}
+
+/// 求解配置
+#[derive(Debug, Clone)]
+pub struct SizingConfig {
+    pub capital: Decimal,  // 可用资金，以路径输入代币计
+    pub iterations: usize, // 二分和黄金分割的迭代次数
+    pub min_profit: Decimal,
+}
+
+impl Default for SizingConfig {
+    fn default() -> Self {
+        Self {
+            capital: Decimal::from(1_000),
+            iterations: 64,
+            min_profit: Decimal::ZERO,
+        }
+    }
+}
+
+/// 求使路径利润最大的输入数量，无利可图或无法成交时返回 None
+///
+/// 利润对输入是凹函数: 先求资金和各步流动性允许的最大输入，
+/// 恒定乘积路径用闭式解，其余用黄金分割搜索，结果再按真实报价复核。
+pub fn optimal_size(route: &[RouteLeg], config: &SizingConfig) -> Option<OptimalSize> {
+    if route.is_empty() || config.capital <= Decimal::ZERO {
+        return None;
+    }
+    let upper = max_input(route, config);
+    if upper <= Decimal::ZERO {
+        return None;
+    }
+
+    let (amount_in, method) = match closed_form(route) {
+        Some(optimum) => (optimum.min(upper), SolveMethod::ClosedForm),
+        None => (
+            golden_section(route, upper, config.iterations),
+            SolveMethod::Numeric,
+        ),
+    };
+    let (amount_out, legs) = run(route, amount_in)?;
+    let profit = amount_out - amount_in;
+    if profit <= config.min_profit {
+        return None;
+    }
+
+    // 有限差分估计边际汇率，受上限约束时可能大于 1
+    let step = amount_in / BPS;
+    let marginal_rate = match run(route, amount_in + step) {
+        Some((out, _)) => (out - amount_out).checked_div(step),
+        None => {
+            run(route, amount_in - step).and_then(|(out, _)| (amount_out - out).checked_div(step))
+        }
+    };
+
+    Some(OptimalSize {
+        amount_in,
+        amount_out,
+        profit,
+        marginal_rate,
+        legs,
+        method,
+        bounded: amount_in >= upper,
+    })
+}
+
+/// 按真实报价逐步执行路径，任一步超出上限或流动性不足时返回 None
+fn run(route: &[RouteLeg], amount_in: Decimal) -> Option<(Decimal, Vec<LegQuote>)> {
+    let mut amount = amount_in;
+    let mut legs = Vec::with_capacity(route.len());
+    for leg in route {
+        if leg.max_in.is_some_and(|max| amount > max) {
+            return None;
+        }
+        let quote = leg.liquidity.quote_exact_in(leg.side, amount)?;
+        amount = match leg.side {
+            TradeSide::Sell => quote.quote,
+            TradeSide::Buy => quote.base,
+        };
+        legs.push(quote);
+    }
+    Some((amount, legs))
+}
+
+/// 资金和流动性允许的最大输入，可成交性对输入单调，二分求边界
+fn max_input(route: &[RouteLeg], config: &SizingConfig) -> Decimal {
+    if run(route, config.capital).is_some() {
+        return config.capital;
+    }
+    let (mut lo, mut hi) = (Decimal::ZERO, config.capital);
+    for _ in 0..config.iterations {
+        let mid = (lo + hi) / Decimal::TWO;
+        if run(route, mid).is_some() {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+/// 全部为恒定乘积池时的闭式最优输入
+///
+/// 每一步 out = γy·a / (x + γa) 是分式线性函数，复合后仍为 P·a / (Q + S·a)，
+/// 利润 P·a / (Q + S·a) - a 在 a = (√(PQ) - Q) / S 处取最大。
+/// 每步后按 Q 归一化 (Q 恒为 1) 以免 Decimal 溢出，溢出时交给数值搜索。
+fn closed_form(route: &[RouteLeg]) -> Option<Decimal> {
+    let (mut p, mut s) = (Decimal::ONE, Decimal::ZERO);
+    for leg in route {
+        let Liquidity::ConstantProduct {
+            base_reserve,
+            quote_reserve,
+            fee_bps,
+        } = leg.liquidity
+        else {
+            return None;
+        };
+        let (x, y) = match leg.side {
+            TradeSide::Buy => (*quote_reserve, *base_reserve),
+            TradeSide::Sell => (*base_reserve, *quote_reserve),
+        };
+        let gamma = Decimal::ONE - *fee_bps / BPS;
+        let gp = gamma.checked_mul(p)?.checked_div(x)?;
+        (p, s) = (gp.checked_mul(y)?, s.checked_add(gp)?);
+    }
+    // 起点边际汇率 P/Q 不大于 1 时无利可图
+    if p <= Decimal::ONE || s <= Decimal::ZERO {
+        return Some(Decimal::ZERO);
+    }
+    (p.sqrt()? - Decimal::ONE).checked_div(s)
+}
+
+/// 在 [0, upper] 上对凹的利润函数做黄金分割搜索
+fn golden_section(route: &[RouteLeg], upper: Decimal, iterations: usize) -> Decimal {
+    let ratio = Decimal::from_f64((5f64.sqrt() - 1.0) / 2.0).unwrap_or(Decimal::ONE / Decimal::TWO);
+    let profit = |a: Decimal| run(route, a).map(|(out, _)| out - a);
+    let (mut lo, mut hi) = (Decimal::ZERO, upper);
+    for _ in 0..iterations {
+        let width = hi - lo;
+        let m1 = hi - width * ratio;
+        let m2 = lo + width * ratio;
+        if profit(m1) < profit(m2) {
+            lo = m1;
+        } else {
+            hi = m2;
+        }
+    }
+    (lo + hi) / Decimal::TWO
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(base: i64, quote: i64) -> Liquidity {
+        Liquidity::ConstantProduct {
+            base_reserve: Decimal::from(base),
+            quote_reserve: Decimal::from(quote),
+            fee_bps: Decimal::from(30),
+        }
+    }
+
+    /// 在便宜的池子买入 SOL，再到贵的池子卖出
+    fn route<'a>(cheap: &'a Liquidity, rich: &'a Liquidity) -> Vec<RouteLeg<'a>> {
+        vec![
+            RouteLeg {
+                liquidity: cheap,
+                side: TradeSide::Buy,
+                max_in: None,
+            },
+            RouteLeg {
+                liquidity: rich,
+                side: TradeSide::Sell,
+                max_in: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn closed_form_matches_golden_section() {
+        let (cheap, rich) = (pool(1_000, 100_000), pool(1_000, 110_000));
+        let route = route(&cheap, &rich);
+        let config = SizingConfig {
+            capital: Decimal::from(100_000),
+            ..Default::default()
+        };
+
+        let optimum = optimal_size(&route, &config).unwrap();
+        assert_eq!(optimum.method, SolveMethod::ClosedForm);
+        assert!(!optimum.bounded);
+
+        let numeric = golden_section(&route, config.capital, 128);
+        let tolerance = optimum.amount_in / Decimal::from(1_000_000);
+        assert!((optimum.amount_in - numeric).abs() < tolerance);
+        // 最优点处边际汇率约为 1
+        let rate = optimum.marginal_rate.unwrap();
+        assert!((rate - Decimal::ONE).abs() < Decimal::new(1, 3));
+    }
+
+    #[test]
+    fn capital_bounds_the_optimum() {
+        let (cheap, rich) = (pool(1_000, 100_000), pool(1_000, 110_000));
+        let config = SizingConfig {
+            capital: Decimal::from(100),
+            ..Default::default()
+        };
+        let optimum = optimal_size(&route(&cheap, &rich), &config).unwrap();
+        assert_eq!(optimum.amount_in, Decimal::from(100));
+        assert!(optimum.bounded);
+        assert!(optimum.profit > Decimal::ZERO);
+    }
+
+    #[test]
+    fn unprofitable_route_has_no_size() {
+        let (cheap, rich) = (pool(1_000, 100_000), pool(1_000, 100_000));
+        assert_eq!(
+            optimal_size(&route(&cheap, &rich), &SizingConfig::default()),
+            None
+        );
+    }
+}